@@ -1,5 +1,10 @@
 use crate::models::SshBuddyError;
-use crate::services::{AddKeyResult, AgentKeyInfo, AgentService, RemoveKeyResult};
+use crate::services::{
+    AddKeyResult, AgentEnvironment, AgentKeyInfo, AgentKeyOrderEntry, AgentPing, AgentService,
+    AgentSettings, AgentSignTest, RemoveKeyResult,
+};
+use crate::utils::expand_path;
+use tauri::State;
 
 /// Check if SSH Agent is running
 #[tauri::command]
@@ -10,6 +15,22 @@ pub async fn is_agent_running() -> bool {
     running
 }
 
+/// Time a round trip to the SSH agent and count its identities
+/// A slow result points at a bad forwarded agent; a dead one fails with ConnectionTimeout
+#[tauri::command]
+pub async fn agent_ping() -> Result<AgentPing, SshBuddyError> {
+    log::info!("[agent] Pinging SSH agent");
+    AgentService::ping().await
+}
+
+/// Have the agent sign a random challenge with the key of this SHA256 fingerprint
+/// Catches keys that are listed but can't sign, e.g. an unplugged hardware key
+#[tauri::command]
+pub async fn test_agent_key_signing(fingerprint: String) -> Result<AgentSignTest, SshBuddyError> {
+    log::info!("[agent] Testing signing with agent key: {}", fingerprint);
+    AgentService::test_sign(&fingerprint).await
+}
+
 /// List all keys in the Agent
 #[tauri::command]
 pub async fn list_agent_keys() -> Result<Vec<AgentKeyInfo>, SshBuddyError> {
@@ -19,34 +40,138 @@ pub async fn list_agent_keys() -> Result<Vec<AgentKeyInfo>, SshBuddyError> {
     Ok(keys)
 }
 
+/// List Agent keys that are not backed by a local public key file
+#[tauri::command]
+pub async fn list_unmatched_agent_keys() -> Result<Vec<AgentKeyInfo>, SshBuddyError> {
+    log::info!("[agent] Listing agent keys without a local key file");
+    let keys = AgentService::list_unmatched_keys().await?;
+    log::info!("[agent] Found {} unmatched keys in agent", keys.len());
+    Ok(keys)
+}
+
+/// List Agent keys in the order they are offered to servers
+/// Useful for diagnosing "Too many authentication failures"
+#[tauri::command]
+pub async fn agent_key_order() -> Result<Vec<AgentKeyOrderEntry>, SshBuddyError> {
+    log::info!("[agent] Getting agent key order");
+    let order = AgentService::key_order().await?;
+    log::info!("[agent] Agent offers {} keys", order.len());
+    Ok(order)
+}
+
+/// Report whether the Agent looks forwarded from another machine,
+/// so the UI can warn before adding or removing keys
+#[tauri::command]
+pub fn agent_environment() -> AgentEnvironment {
+    let environment = AgentService::environment();
+    log::info!(
+        "[agent] Agent environment: forwarded={}, reasons={:?}",
+        environment.forwarded,
+        environment.reasons
+    );
+    environment
+}
+
+/// Save the public keys of all Agent identities as .pub files in dest_dir
+/// Returns the paths of the written files
+#[tauri::command]
+pub async fn export_agent_public_keys(dest_dir: String) -> Result<Vec<String>, SshBuddyError> {
+    let dest_dir = expand_path(&dest_dir)?;
+    log::info!(
+        "[agent] Exporting agent public keys to {}",
+        dest_dir.display()
+    );
+    let written = AgentService::export_public_keys(&dest_dir).await?;
+    log::info!("[agent] Exported {} public keys", written.len());
+    Ok(written)
+}
+
 /// Check if a key is in the Agent
 #[tauri::command]
 pub async fn is_key_in_agent(key_path: String) -> Result<bool, SshBuddyError> {
+    let key_path = expand_path(&key_path)?.to_string_lossy().to_string();
     log::info!("[agent] Checking if key is in agent: {}", key_path);
     let in_agent = AgentService::is_key_in_agent(&key_path).await?;
     log::info!("[agent] Key in agent: {}", in_agent);
     Ok(in_agent)
 }
 
+/// Which Agents hold a key: the default one plus each socket given (e.g. gpg-agent, 1Password)
+/// Returns the sockets holding it; unreachable sockets, and paths that aren't a Unix socket
+/// (or a `\\.\pipe\` named pipe on Windows), are skipped
+#[tauri::command]
+pub async fn is_key_in_any_agent(
+    key_path: String,
+    sockets: Vec<String>,
+) -> Result<Vec<String>, SshBuddyError> {
+    let key_path = expand_path(&key_path)?.to_string_lossy().to_string();
+    let sockets = sockets
+        .iter()
+        .map(|socket| Ok(expand_path(socket)?.to_string_lossy().to_string()))
+        .collect::<Result<Vec<_>, SshBuddyError>>()?;
+    log::info!(
+        "[agent] Checking {} in {} extra agent(s)",
+        key_path,
+        sockets.len()
+    );
+    let holders = AgentService::is_key_in_any_agent(&key_path, &sockets).await?;
+    log::info!("[agent] Key held by: {:?}", holders);
+    Ok(holders)
+}
+
 /// Add a key to the Agent
 /// Passphrase is optional. If the key requires a passphrase but none is provided,
 /// returns needs_passphrase: true
+/// lifetime_secs overrides the default key lifetime for this key
 #[tauri::command]
 pub async fn add_key_to_agent(
+    settings: State<'_, AgentSettings>,
     key_path: String,
     passphrase: Option<String>,
+    lifetime_secs: Option<u32>,
 ) -> Result<AddKeyResult, SshBuddyError> {
-    log::info!("[agent] Adding key to agent: {}", key_path);
-    let result = AgentService::add_key(&key_path, passphrase.as_deref()).await?;
+    let key_path = expand_path(&key_path)?.to_string_lossy().to_string();
+    let lifetime_secs = lifetime_secs.or_else(|| settings.default_key_lifetime_secs());
+    log::info!(
+        "[agent] Adding key to agent: {} (lifetime: {:?}s)",
+        key_path,
+        lifetime_secs
+    );
+    let result = AgentService::add_key(&key_path, passphrase.as_deref(), lifetime_secs).await?;
     log::info!("[agent] Add key result: {:?}", result);
     Ok(result)
 }
 
+/// Add all unencrypted keys in ~/.ssh to the Agent
+/// Encrypted keys are skipped and reported with needs_passphrase: true
+#[tauri::command]
+pub async fn add_all_keys_to_agent(
+    settings: State<'_, AgentSettings>,
+) -> Result<Vec<(String, AddKeyResult)>, SshBuddyError> {
+    log::info!("[agent] Adding all unencrypted keys to agent");
+    let results = AgentService::add_all_unencrypted(settings.default_key_lifetime_secs()).await?;
+    log::info!(
+        "[agent] Added {} of {} keys",
+        results.iter().filter(|(_, r)| r.success).count(),
+        results.len()
+    );
+    Ok(results)
+}
+
 /// Remove a key from the Agent
 #[tauri::command]
 pub async fn remove_key_from_agent(key_path: String) -> Result<RemoveKeyResult, SshBuddyError> {
+    let key_path = expand_path(&key_path)?.to_string_lossy().to_string();
     log::info!("[agent] Removing key from agent: {}", key_path);
     let result = AgentService::remove_key(&key_path).await?;
     log::info!("[agent] Remove key result: {:?}", result);
     Ok(result)
 }
+
+/// Set how long newly added keys stay in the Agent
+/// None or 0 keeps them until removed (the default)
+#[tauri::command]
+pub fn set_default_key_lifetime(settings: State<'_, AgentSettings>, lifetime_secs: Option<u32>) {
+    log::info!("[agent] Setting default key lifetime: {:?}s", lifetime_secs);
+    settings.set_default_key_lifetime_secs(lifetime_secs);
+}
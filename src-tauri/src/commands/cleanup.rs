@@ -0,0 +1,10 @@
+use crate::models::SshBuddyError;
+use crate::services::{CleanupItem, CleanupService};
+
+/// Review ~/.ssh for leftovers and risky keys
+/// Only reports; each item says which fix (if any) the UI can offer
+#[tauri::command]
+pub async fn cleanup_recommendations() -> Result<Vec<CleanupItem>, SshBuddyError> {
+    log::info!("[cleanup] Collecting recommendations");
+    CleanupService::recommendations().await
+}
@@ -0,0 +1,94 @@
+use crate::models::SshBuddyError;
+use crate::services::SshConnectionService;
+use crate::utils::{
+    read_ssh_file, ssh_dir, ConfigConflict, ConfigWarning, EffectiveValue, SshConfigParser,
+    SshFileContents,
+};
+use std::collections::BTreeMap;
+use tokio::fs;
+
+/// Read ~/.ssh/config as written, for display
+/// A missing file reads as empty; a config symlinked outside ~/.ssh is refused
+#[tauri::command]
+pub async fn read_ssh_config() -> Result<SshFileContents, SshBuddyError> {
+    log::info!("[config] Reading SSH config");
+    read_ssh_file(&ssh_dir()?, "config").await
+}
+
+/// Save edited content as ~/.ssh/config (mode 600)
+/// Content with lines that don't parse is refused with InvalidConfig and nothing is written;
+/// otherwise the previous config is backed up first and the backup path returned for undo
+#[tauri::command]
+pub async fn write_ssh_config(content: String) -> Result<Option<String>, SshBuddyError> {
+    log::info!("[config] Writing SSH config ({} bytes)", content.len());
+    SshConnectionService::write_config(&content).await
+}
+
+/// Options of the `Host *` block in ~/.ssh/config, keyed by directive name
+#[tauri::command]
+pub async fn get_global_ssh_options() -> Result<BTreeMap<String, String>, SshBuddyError> {
+    log::info!("[config] Reading global SSH options");
+    SshConnectionService::global_options().await
+}
+
+/// Set one option in the `Host *` block, creating the block if the config has none
+/// Values of well-known options are checked first; returns the backup path like write_ssh_config
+#[tauri::command]
+pub async fn set_global_ssh_option(
+    key: String,
+    value: String,
+) -> Result<Option<String>, SshBuddyError> {
+    log::info!("[config] Setting global SSH option {}", key);
+    SshConnectionService::set_global_option(&key, &value).await
+}
+
+/// Lint SSH config content, reporting lines the parser skips
+/// Validates `content` when given (e.g. unsaved editor text), otherwise ~/.ssh/config
+#[tauri::command]
+pub async fn validate_ssh_config(
+    content: Option<String>,
+) -> Result<Vec<ConfigWarning>, SshBuddyError> {
+    let content = match content {
+        Some(content) => content,
+        None => {
+            let config_path = ssh_dir()?.join("config");
+            log::info!("[config] Validating SSH config: {:?}", config_path);
+            if !config_path.exists() {
+                return Ok(Vec::new());
+            }
+            fs::read_to_string(&config_path).await?
+        }
+    };
+
+    let (_, warnings) = SshConfigParser::parse_with_diagnostics(&content);
+    log::info!("[config] Found {} config warning(s)", warnings.len());
+    Ok(warnings)
+}
+
+/// Fully merged configuration for a host, like `ssh -G <alias>`
+/// Each value carries the config line it came from, so overrides can be traced
+#[tauri::command]
+pub async fn get_effective_config(
+    alias: String,
+) -> Result<BTreeMap<String, EffectiveValue>, SshBuddyError> {
+    log::info!("[config] Resolving effective config for: {}", alias);
+    SshConnectionService::effective_config(&alias).await
+}
+
+/// Per-host settings that a later `Host *` or other broader block overrides
+/// Explains why a value set for an alias isn't taking effect
+#[tauri::command]
+pub async fn config_conflicts() -> Result<Vec<ConfigConflict>, SshBuddyError> {
+    log::info!("[config] Checking for shadowed host settings");
+    Ok(SshConnectionService::config_conflicts().await)
+}
+
+/// Aliases in ~/.ssh/config whose IdentityFile is ~/.ssh/<key_name>
+/// Lets the UI warn before a key that hosts depend on is deleted
+#[tauri::command]
+pub async fn config_hosts_using_key(key_name: String) -> Result<Vec<String>, SshBuddyError> {
+    log::info!("[config] Finding hosts using key: {}", key_name);
+    let aliases = SshConnectionService::hosts_using_key(&key_name).await?;
+    log::info!("[config] {} host(s) use {}", aliases.len(), key_name);
+    Ok(aliases)
+}
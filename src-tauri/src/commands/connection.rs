@@ -1,13 +1,32 @@
 use crate::models::SshBuddyError;
-use crate::services::{ConnectionTestResult, SshConnectionService};
+use crate::services::{
+    AuthMethodsProbe, ConnectionPreflight, ConnectionTestResult, InstallKeyResult, Motd,
+    SshConnectionService, WorkingKeyResult,
+};
 
 /// Test SSH connection
+/// `identity_file` forces a specific key under ~/.ssh, bypassing the host's config
+/// `fetch_banner` opens a session to read the welcome banner after authenticating;
+/// defaults to true for known Git platforms and false otherwise
+/// `prefer_agent` tries the SSH agent before the key file; defaults to file first
 #[tauri::command]
 pub async fn test_ssh_connection(
     host_alias: String,
+    identity_file: Option<String>,
+    fetch_banner: Option<bool>,
+    prefer_agent: Option<bool>,
 ) -> Result<ConnectionTestResult, SshBuddyError> {
     log::info!("[connection] Testing SSH connection to: {}", host_alias);
-    let result = SshConnectionService::test_connection(&host_alias).await?;
+    if let Some(ref path) = identity_file {
+        log::info!("[connection] Using identity override: {}", path);
+    }
+    let result = SshConnectionService::test_connection(
+        &host_alias,
+        identity_file.as_deref(),
+        fetch_banner,
+        prefer_agent,
+    )
+    .await?;
     log::info!(
         "[connection] Test result: success={}, output={}",
         result.success,
@@ -15,3 +34,59 @@ pub async fn test_ssh_connection(
     );
     Ok(result)
 }
+
+/// Install a local public key in a host's authorized_keys (like ssh-copy-id)
+#[tauri::command]
+pub async fn install_public_key_on_host(
+    host_alias: String,
+    key_name: String,
+) -> Result<InstallKeyResult, SshBuddyError> {
+    log::info!(
+        "[connection] Installing public key {} on {}",
+        key_name,
+        host_alias
+    );
+    let result = SshConnectionService::install_public_key(&host_alias, &key_name).await?;
+    log::info!("[connection] Install result: {:?}", result);
+    Ok(result)
+}
+
+/// Try every local key against a host to find one it accepts
+#[tauri::command]
+pub async fn find_working_key(host_alias: String) -> Result<WorkingKeyResult, SshBuddyError> {
+    log::info!("[connection] Finding a working key for: {}", host_alias);
+    let result = SshConnectionService::find_working_key(&host_alias).await?;
+    log::info!(
+        "[connection] Working key: {:?} ({} tried)",
+        result.working_key.as_ref().map(|key| &key.name),
+        result.attempts.len()
+    );
+    Ok(result)
+}
+
+/// List the authentication methods a host's server offers, without authenticating
+/// Explains why a key is never tried, e.g. a server that only allows passwords
+/// Fails with HostKeyUnknown or HostKeyChanged unless the host key is in known_hosts
+#[tauri::command]
+pub async fn probe_auth_methods(host_alias: String) -> Result<AuthMethodsProbe, SshBuddyError> {
+    log::info!("[connection] Probing auth methods of: {}", host_alias);
+    SshConnectionService::probe_auth_methods(&host_alias).await
+}
+
+/// Count the keys a connection to a host would offer, before connecting
+/// Flags hosts likely to fail with "Too many authentication failures"
+#[tauri::command]
+pub async fn connection_preflight(
+    host_alias: String,
+) -> Result<ConnectionPreflight, SshBuddyError> {
+    log::info!("[connection] Preflight for: {}", host_alias);
+    SshConnectionService::connection_preflight(&host_alias).await
+}
+
+/// Log in to a host and return its MOTD verbatim, without judging the connection
+/// Long output is cut off and flagged as truncated
+#[tauri::command]
+pub async fn fetch_motd(host_alias: String) -> Result<Motd, SshBuddyError> {
+    log::info!("[connection] Fetching MOTD of: {}", host_alias);
+    SshConnectionService::fetch_motd(&host_alias).await
+}
@@ -0,0 +1,10 @@
+use crate::models::SshBuddyError;
+use crate::services::{InventoryService, SshDirInventory};
+
+/// Counts and sizes of ~/.ssh for the overview card
+/// Parts that can't be read come back as None instead of failing the call
+#[tauri::command]
+pub async fn ssh_dir_inventory() -> Result<SshDirInventory, SshBuddyError> {
+    log::info!("[inventory] Collecting ~/.ssh inventory");
+    InventoryService::ssh_dir_inventory().await
+}
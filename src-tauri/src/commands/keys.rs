@@ -1,5 +1,15 @@
-use crate::models::{KeyDetails, SSHKeyInfo, SshBuddyError};
-use crate::services::{GenerateKeyOptions, KeyManager};
+use crate::models::{
+    CertInfo, DeleteKeyResult, GitKeyPayload, KeyAlgorithmInfo, KeyDetails, KeyEncryptResult,
+    KeyFilter, KeyPairStatus, KeyValidation, KeyWithHosts, KeyWithUsage, MigrateKeyResult,
+    PublicKeyFormats, RotateKeyResult, SSHKeyInfo, SortKey, SshBuddyError,
+};
+use crate::services::{
+    GenerateKeyOptions, KeyManager, KeySettings, KeyUsageStore, SshConnectionService,
+};
+use crate::utils::expand_path;
+use ssh_key::HashAlg;
+use tauri::State;
+use tauri_plugin_clipboard_manager::ClipboardExt;
 
 /// List all SSH keys
 #[tauri::command]
@@ -11,6 +21,54 @@ pub async fn list_ssh_keys() -> Result<Vec<SSHKeyInfo>, SshBuddyError> {
     Ok(keys)
 }
 
+/// List SSH keys matching a filter, sorted by name, type, bit size or age
+/// Includes private keys that have no public key file
+#[tauri::command]
+pub async fn list_ssh_keys_filtered(
+    sort_by: Option<SortKey>,
+    filter: Option<KeyFilter>,
+) -> Result<Vec<SSHKeyInfo>, SshBuddyError> {
+    let sort_by = sort_by.unwrap_or_default();
+    let filter = filter.unwrap_or_default();
+    log::info!("[keys] Listing SSH keys by {:?} with {:?}", sort_by, filter);
+    let manager = KeyManager::new()?;
+    let keys = manager.list_keys_filtered(sort_by, &filter).await?;
+    log::info!("[keys] Found {} matching keys", keys.len());
+    Ok(keys)
+}
+
+/// List all SSH keys with when each last passed a connection test
+#[tauri::command]
+pub async fn list_ssh_keys_with_usage() -> Result<Vec<KeyWithUsage>, SshBuddyError> {
+    log::info!("[keys] Listing SSH keys with usage");
+    let manager = KeyManager::new()?;
+    let keys = manager.list_keys().await?;
+    let usage = KeyUsageStore::new()?.load().await;
+    Ok(keys
+        .into_iter()
+        .map(|key| KeyWithUsage {
+            last_used: usage.get(&key.private_key_path).copied(),
+            key,
+        })
+        .collect())
+}
+
+/// List all SSH keys with the config aliases whose IdentityFile resolves to each
+#[tauri::command]
+pub async fn list_ssh_keys_with_hosts() -> Result<Vec<KeyWithHosts>, SshBuddyError> {
+    log::info!("[keys] Listing SSH keys with their hosts");
+    let manager = KeyManager::new()?;
+    let keys = manager.list_keys_with_hosts().await?;
+    log::info!(
+        "[keys] Found {} keys, {} used by no host",
+        keys.len(),
+        keys.iter()
+            .filter(|key| key.used_by_hosts.is_empty())
+            .count()
+    );
+    Ok(keys)
+}
+
 /// Read public key content
 #[tauri::command]
 pub async fn read_public_key(key_name: String) -> Result<String, SshBuddyError> {
@@ -20,18 +78,145 @@ pub async fn read_public_key(key_name: String) -> Result<String, SshBuddyError>
     Ok(content)
 }
 
+/// Copy public key content to the system clipboard
+/// Returns the copied content (without trailing newline)
+#[tauri::command]
+pub async fn copy_public_key_to_clipboard(
+    app: tauri::AppHandle,
+    key_name: String,
+) -> Result<String, SshBuddyError> {
+    log::info!("[keys] Copying public key to clipboard: {}", key_name);
+    let manager = KeyManager::new()?;
+    let content = manager.read_public_key(&key_name).await?;
+    app.clipboard()
+        .write_text(content.clone())
+        .map_err(|e| SshBuddyError::Unknown {
+            message: format!("Failed to write to clipboard: {}", e),
+        })?;
+    Ok(content)
+}
+
+/// Public key as OpenSSH line, RFC 4716 block and bare base64
+#[tauri::command]
+pub async fn get_public_key_formats(key_name: String) -> Result<PublicKeyFormats, SshBuddyError> {
+    log::info!("[keys] Getting public key formats: {}", key_name);
+    let manager = KeyManager::new()?;
+    let formats = manager.public_key_formats(&key_name).await?;
+    Ok(formats)
+}
+
+/// Whether two public keys (OpenSSH format) are the same key, ignoring comments
+#[tauri::command]
+pub fn compare_public_keys(a: String, b: String) -> Result<bool, SshBuddyError> {
+    let equal = KeyManager::keys_equal(&a, &b)?;
+    log::info!("[keys] Public keys equal: {}", equal);
+    Ok(equal)
+}
+
+/// Title and key for a GitHub/GitLab "Add SSH key" form
+#[tauri::command]
+pub async fn github_key_payload(key_name: String) -> Result<GitKeyPayload, SshBuddyError> {
+    log::info!("[keys] Building Git host key payload for: {}", key_name);
+    let manager = KeyManager::new()?;
+    let payload = manager.git_key_payload(&key_name).await?;
+    Ok(payload)
+}
+
+/// Randomart image of a key's fingerprint, as shown by `ssh-keygen -lv`
+/// `hash` is "sha256" (default) or "sha512"
+#[tauri::command]
+pub async fn key_randomart(
+    key_name: String,
+    hash: Option<String>,
+) -> Result<String, SshBuddyError> {
+    log::info!("[keys] Computing randomart for: {}", key_name);
+    let hash = match hash {
+        Some(hash) => HashAlg::new(&hash.to_lowercase())?,
+        None => HashAlg::Sha256,
+    };
+    let manager = KeyManager::new()?;
+    let art = manager.randomart(&key_name, hash).await?;
+    Ok(art)
+}
+
+/// `~/.ssh/config` block pointing `hostname` at a key, ready to paste or add as a host
+#[tauri::command]
+pub async fn suggest_config_block(
+    key_name: String,
+    hostname: String,
+    user: String,
+) -> Result<String, SshBuddyError> {
+    log::info!(
+        "[keys] Suggesting config block for {} on {}",
+        key_name,
+        hostname
+    );
+    let manager = KeyManager::new()?;
+    let block = manager.suggest_config_block(&key_name, &hostname, &user)?;
+    Ok(block)
+}
+
+/// Fully parse a private key file to diagnose corruption
+/// With a passphrase the private section is decrypted too
+#[tauri::command]
+pub async fn validate_key_file(
+    key_name: String,
+    passphrase: Option<String>,
+) -> Result<KeyValidation, SshBuddyError> {
+    log::info!("[keys] Validating key file: {}", key_name);
+    let manager = KeyManager::new()?;
+    let validation = manager
+        .validate_key_file(&key_name, passphrase.as_deref())
+        .await?;
+    Ok(validation)
+}
+
 /// Get key details
 #[tauri::command]
 pub async fn get_key_details(key_path: String) -> Result<KeyDetails, SshBuddyError> {
+    let key_path = expand_path(&key_path)?.to_string_lossy().to_string();
     log::info!("[keys] Getting key details: {}", key_path);
     let manager = KeyManager::new()?;
     let details = manager.get_key_details(&key_path).await?;
     Ok(details)
 }
 
+/// Get key details from a private key file, without needing its passphrase
+#[tauri::command]
+pub async fn get_private_key_details(key_name: String) -> Result<KeyDetails, SshBuddyError> {
+    log::info!("[keys] Getting private key details: {}", key_name);
+    let manager = KeyManager::new()?;
+    let details = manager.get_private_key_details(&key_name).await?;
+    Ok(details)
+}
+
+/// Get SSH certificate details for a key (`<key_name>-cert.pub`)
+#[tauri::command]
+pub async fn get_certificate_details(key_name: String) -> Result<CertInfo, SshBuddyError> {
+    log::info!("[keys] Getting certificate details: {}", key_name);
+    let manager = KeyManager::new()?;
+    let details = manager.get_certificate_details(&key_name).await?;
+    if details.expired {
+        log::warn!("[keys] Certificate {} has expired", details.name);
+    }
+    Ok(details)
+}
+
+/// Key algorithms that can be offered when generating a key
+#[tauri::command]
+pub fn supported_key_algorithms() -> Vec<KeyAlgorithmInfo> {
+    let algorithms = KeyManager::supported_algorithms();
+    log::info!("[keys] {} key algorithms supported", algorithms.len());
+    algorithms
+}
+
 /// Generate a new SSH key pair
 #[tauri::command]
-pub async fn generate_ssh_key(options: GenerateKeyOptions) -> Result<SSHKeyInfo, SshBuddyError> {
+pub async fn generate_ssh_key(
+    settings: State<'_, KeySettings>,
+    mut options: GenerateKeyOptions,
+) -> Result<SSHKeyInfo, SshBuddyError> {
+    settings.apply_default_comment(&mut options);
     log::info!(
         "[keys] Generating {} key: {}",
         options.key_type,
@@ -43,12 +228,161 @@ pub async fn generate_ssh_key(options: GenerateKeyOptions) -> Result<SSHKeyInfo,
     Ok(key_info)
 }
 
-/// Delete an SSH key pair
+/// Set the comment template for keys generated without a comment
+/// Tokens: {user}, {host} and {date}; None or blank restores `{user}@{host}`
+#[tauri::command]
+pub fn set_default_key_comment_template(
+    settings: State<'_, KeySettings>,
+    template: Option<String>,
+) -> Result<(), SshBuddyError> {
+    log::info!(
+        "[keys] Setting default key comment template: {:?}",
+        template
+    );
+    settings.set_comment_template(template)
+}
+
+/// Rotate a key: generate a replacement, keeping the old key
+/// Unspecified type, size and comment are taken from the old key
 #[tauri::command]
-pub async fn delete_ssh_key(key_name: String) -> Result<(), SshBuddyError> {
-    log::info!("[keys] Deleting key: {}", key_name);
+pub async fn rotate_ssh_key(
+    old_name: String,
+    options: GenerateKeyOptions,
+) -> Result<RotateKeyResult, SshBuddyError> {
+    log::info!("[keys] Rotating key {} -> {}", old_name, options.name);
     let manager = KeyManager::new()?;
-    manager.delete_key(&key_name).await?;
-    log::info!("[keys] Key deleted successfully");
+    let result = manager.rotate_key(&old_name, options).await?;
+    log::info!("[keys] Key rotated successfully");
+    Ok(result)
+}
+
+/// DSA and RSA keys below 3072 bits, as candidates for migrating to Ed25519
+#[tauri::command]
+pub async fn legacy_key_report() -> Result<Vec<SSHKeyInfo>, SshBuddyError> {
+    log::info!("[keys] Collecting legacy keys");
+    let manager = KeyManager::new()?;
+    manager.legacy_keys().await
+}
+
+/// Generate an Ed25519 replacement for a legacy key, keeping the old key
+/// Config hosts still using the old key are returned so the new key can be deployed there
+#[tauri::command]
+pub async fn migrate_to_ed25519(
+    old_name: String,
+    new_name: String,
+) -> Result<MigrateKeyResult, SshBuddyError> {
+    log::info!(
+        "[keys] Migrating key {} -> {} (ed25519)",
+        old_name,
+        new_name
+    );
+    let manager = KeyManager::new()?;
+    let rotation = manager.migrate_to_ed25519(&old_name, &new_name).await?;
+    let hosts_to_update = match SshConnectionService::hosts_using_key(&old_name).await {
+        Ok(aliases) => aliases,
+        Err(e) => {
+            log::warn!(
+                "[keys] Could not check config hosts using {}: {}",
+                old_name,
+                e
+            );
+            Vec::new()
+        }
+    };
+    Ok(MigrateKeyResult {
+        rotation,
+        hosts_to_update,
+    })
+}
+
+/// Paths of a key pair's private and public files, which exist, and whether the private key is encrypted
+#[tauri::command]
+pub async fn key_pair_status(key_name: String) -> Result<KeyPairStatus, SshBuddyError> {
+    log::info!("[keys] Checking key pair status: {}", key_name);
+    let manager = KeyManager::new()?;
+    manager.key_pair_status(&key_name).await
+}
+
+/// Encrypt every unencrypted private key in ~/.ssh with one passphrase
+/// Already encrypted and symlinked keys are skipped; each key gets its own result
+#[tauri::command]
+pub async fn bulk_encrypt_keys(passphrase: String) -> Result<Vec<KeyEncryptResult>, SshBuddyError> {
+    log::info!("[keys] Encrypting all unencrypted keys");
+    let manager = KeyManager::new()?;
+    manager.bulk_encrypt_keys(&passphrase).await
+}
+
+/// Move a key pair into ~/.ssh/archive/, out of the key list
+#[tauri::command]
+pub async fn archive_ssh_key(key_name: String) -> Result<(), SshBuddyError> {
+    log::info!("[keys] Archiving key: {}", key_name);
+    let manager = KeyManager::new()?;
+    manager.archive_key(&key_name).await?;
+    Ok(())
+}
+
+/// Move an archived key pair back into ~/.ssh
+#[tauri::command]
+pub async fn unarchive_ssh_key(key_name: String) -> Result<(), SshBuddyError> {
+    log::info!("[keys] Unarchiving key: {}", key_name);
+    let manager = KeyManager::new()?;
+    manager.unarchive_key(&key_name).await?;
     Ok(())
 }
+
+/// List keys in ~/.ssh/archive/
+#[tauri::command]
+pub async fn list_archived_keys() -> Result<Vec<SSHKeyInfo>, SshBuddyError> {
+    log::info!("[keys] Listing archived keys");
+    let manager = KeyManager::new()?;
+    let keys = manager.list_archived_keys().await?;
+    Ok(keys)
+}
+
+/// Delete an SSH key pair
+/// Config hosts that used the key are reported, not protected: the key is deleted anyway
+/// `secure_delete` overwrites the private key with random bytes before removing it
+/// (refused for a symlinked key, whose target lives outside ~/.ssh)
+#[tauri::command]
+pub async fn delete_ssh_key(
+    key_name: String,
+    secure_delete: Option<bool>,
+) -> Result<DeleteKeyResult, SshBuddyError> {
+    let secure_delete = secure_delete.unwrap_or(false);
+    log::info!(
+        "[keys] Deleting key: {} (secure: {})",
+        key_name,
+        secure_delete
+    );
+    let manager = KeyManager::new()?;
+    let referenced_by = match SshConnectionService::hosts_using_key(&key_name).await {
+        Ok(aliases) => aliases,
+        Err(e) => {
+            log::warn!(
+                "[keys] Could not check config hosts using {}: {}",
+                key_name,
+                e
+            );
+            Vec::new()
+        }
+    };
+    manager.delete_key(&key_name, secure_delete).await?;
+    if referenced_by.is_empty() {
+        log::info!("[keys] Key deleted successfully");
+    } else {
+        log::warn!(
+            "[keys] Deleted key {} is still used by: {}",
+            key_name,
+            referenced_by.join(", ")
+        );
+    }
+    let message = secure_delete.then(|| {
+        "Private key overwritten before deletion. Copy-on-write filesystems, SSDs and \
+         backups may still hold the old contents."
+            .to_string()
+    });
+    Ok(DeleteKeyResult {
+        referenced_by,
+        message,
+    })
+}
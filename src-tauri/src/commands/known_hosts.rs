@@ -1,27 +1,251 @@
 use crate::models::SshBuddyError;
-use crate::services::{KnownHostAddResult, KnownHostRemoveResult, KnownHostsService};
+use crate::services::{
+    AnnotatedLine, KnownHostAddResult, KnownHostRemoveResult, KnownHostStatusDto,
+    KnownHostsDuplicates, KnownHostsService, PlatformKeyStatus, ResolvedHost, ScannedKey,
+    SshfpVerification, VerifiedHostResult,
+};
+use crate::utils::{read_ssh_file, ssh_dir, SshFileContents};
+use tauri::Emitter;
+
+/// Read ~/.ssh/known_hosts as written, for display
+/// A missing file reads as empty; a file symlinked outside ~/.ssh is refused
+#[tauri::command]
+pub async fn read_known_hosts_raw() -> Result<SshFileContents, SshBuddyError> {
+    log::info!("[known_hosts] Reading known_hosts");
+    read_ssh_file(&ssh_dir()?, "known_hosts").await
+}
 
 /// Remove a host from known_hosts
+/// known_hosts_file selects the file to edit (must be inside ~/.ssh); defaults to ~/.ssh/known_hosts
+/// port limits removal to that port's entries; without it the host's entries go on every port,
+/// except hashed entries for other ports, which need the port to be recognized
 #[tauri::command]
-pub async fn remove_known_host(hostname: String) -> Result<KnownHostRemoveResult, SshBuddyError> {
-    log::info!("[known_hosts] Removing host: {}", hostname);
-    let result = KnownHostsService::remove_host(&hostname).await?;
+pub async fn remove_known_host(
+    hostname: String,
+    known_hosts_file: Option<String>,
+    port: Option<u16>,
+) -> Result<KnownHostRemoveResult, SshBuddyError> {
+    log::info!(
+        "[known_hosts] Removing host: {} (port: {:?}, file: {:?})",
+        hostname,
+        port,
+        known_hosts_file
+    );
+    let result =
+        KnownHostsService::remove_host(&hostname, port, known_hosts_file.as_deref()).await?;
     log::info!("[known_hosts] Remove result: {:?}", result);
     Ok(result)
 }
 
 /// Add a host to known_hosts
+/// known_hosts_file selects the file to edit (must be inside ~/.ssh); defaults to ~/.ssh/known_hosts
+/// hash_hostnames writes the host name hashed, as `HashKnownHosts yes` does
+/// timeout_secs gives slow servers longer to return their keys (default 5, at most 60)
+/// host_key_alias stores the keys under that name instead of hostname, as HostKeyAlias does
+/// Fails with NoHostKeys when the server answers but returns no keys
 #[tauri::command]
 pub async fn add_known_host(
     hostname: String,
     port: Option<u16>,
+    known_hosts_file: Option<String>,
+    hash_hostnames: Option<bool>,
+    timeout_secs: Option<u64>,
+    host_key_alias: Option<String>,
 ) -> Result<KnownHostAddResult, SshBuddyError> {
     log::info!(
-        "[known_hosts] Adding host: {}:{}",
+        "[known_hosts] Adding host: {}:{} (file: {:?})",
         hostname,
-        port.unwrap_or(22)
+        port.unwrap_or(22),
+        known_hosts_file
     );
-    let result = KnownHostsService::add_host(&hostname, port).await?;
+    let result = KnownHostsService::add_host(
+        &hostname,
+        port,
+        known_hosts_file.as_deref(),
+        hash_hostnames.unwrap_or(false),
+        timeout_secs,
+        host_key_alias.as_deref(),
+    )
+    .await?;
     log::info!("[known_hosts] Add result: {:?}", result);
     Ok(result)
 }
+
+/// Add a host to ~/.ssh/known_hosts only if its key has the expected SHA256
+/// fingerprint, obtained out-of-band (e.g. from the server's administrator)
+#[tauri::command]
+pub async fn add_known_host_verified(
+    hostname: String,
+    port: Option<u16>,
+    expected_fingerprint: String,
+) -> Result<VerifiedHostResult, SshBuddyError> {
+    log::info!(
+        "[known_hosts] Adding host {}:{} if it matches {}",
+        hostname,
+        port.unwrap_or(22),
+        expected_fingerprint
+    );
+    let result =
+        KnownHostsService::add_host_verified(&hostname, port, &expected_fingerprint).await?;
+    log::info!(
+        "[known_hosts] Verified {} key {}",
+        result.hostname,
+        result.matched_key.fingerprint
+    );
+    Ok(result)
+}
+
+/// Event sent as each host of `scan_host_range` finishes, with a `HostScanProgress` payload
+const HOST_SCAN_RESULT_EVENT: &str = "host-scan-result";
+
+/// Scan a small IPv4 CIDR or comma-separated host list for host keys
+/// Nothing is written; the caller picks which keys to add
+/// Each host's result is also emitted as a `host-scan-result` event when it finishes
+#[tauri::command]
+pub async fn scan_host_range(
+    app: tauri::AppHandle,
+    hosts: String,
+    port: Option<u16>,
+) -> Result<Vec<(String, Vec<ScannedKey>)>, SshBuddyError> {
+    log::info!("[known_hosts] Scanning host range: {}", hosts);
+    let results = KnownHostsService::scan_range(&hosts, port, move |progress| {
+        if let Err(e) = app.emit(HOST_SCAN_RESULT_EVENT, &progress) {
+            log::warn!(
+                "[known_hosts] Failed to emit scan result for {}: {}",
+                progress.host,
+                e
+            );
+        }
+    })
+    .await?;
+    log::info!(
+        "[known_hosts] {} of {} host(s) returned keys",
+        results.iter().filter(|(_, keys)| !keys.is_empty()).count(),
+        results.len()
+    );
+    Ok(results)
+}
+
+/// Rename a host in ~/.ssh/known_hosts without re-scanning its keys
+/// port selects `[host]:port` entries; defaults to 22. Hashed entries are skipped
+#[tauri::command]
+pub async fn relabel_known_host(
+    old_hostname: String,
+    new_hostname: String,
+    port: Option<u16>,
+) -> Result<usize, SshBuddyError> {
+    log::info!(
+        "[known_hosts] Relabeling {} as {}",
+        old_hostname,
+        new_hostname
+    );
+    KnownHostsService::relabel_host(&old_hostname, &new_hostname, port).await
+}
+
+/// Check whether a host is already in known_hosts (no network access)
+#[tauri::command]
+pub async fn is_host_known(
+    hostname: String,
+    port: Option<u16>,
+) -> Result<KnownHostStatusDto, SshBuddyError> {
+    log::info!(
+        "[known_hosts] Checking if host is known: {}:{}",
+        hostname,
+        port.unwrap_or(22)
+    );
+    let result = KnownHostsService::is_host_known(&hostname, port).await?;
+    log::info!("[known_hosts] Host known: {}", result.known);
+    Ok(result)
+}
+
+/// Compare Git platform host keys in known_hosts against their official keys
+#[tauri::command]
+pub async fn check_known_platform_keys() -> Result<Vec<PlatformKeyStatus>, SshBuddyError> {
+    log::info!("[known_hosts] Checking Git platform host keys");
+    let statuses = KnownHostsService::check_platform_keys().await?;
+    log::info!("[known_hosts] Platform key statuses: {:?}", statuses);
+    Ok(statuses)
+}
+
+/// Check that a hostname resolves, listing its addresses
+/// port defaults to 22 and only fills in the returned socket addresses
+#[tauri::command]
+pub async fn resolve_hostname(
+    hostname: String,
+    port: Option<u16>,
+) -> Result<ResolvedHost, SshBuddyError> {
+    log::info!("[known_hosts] Resolving: {}", hostname);
+    let resolved = KnownHostsService::resolve_hostname(&hostname, port.unwrap_or(22)).await?;
+    log::info!(
+        "[known_hosts] {} resolved to {} address(es)",
+        hostname,
+        resolved.addresses.len()
+    );
+    Ok(resolved)
+}
+
+/// Read ~/.ssh/known_hosts line by line, each classified for display
+#[tauri::command]
+pub async fn read_known_hosts_annotated() -> Result<Vec<AnnotatedLine>, SshBuddyError> {
+    let lines = KnownHostsService::read_annotated().await?;
+    let malformed = lines.iter().filter(|line| line.warning.is_some()).count();
+    log::info!(
+        "[known_hosts] Read {} lines ({} malformed)",
+        lines.len(),
+        malformed
+    );
+    Ok(lines)
+}
+
+/// Find repeated entries in ~/.ssh/known_hosts: exact duplicates, and hosts
+/// stored with several different keys of the same algorithm
+#[tauri::command]
+pub async fn find_known_hosts_duplicates() -> Result<KnownHostsDuplicates, SshBuddyError> {
+    log::info!("[known_hosts] Finding duplicate entries");
+    let duplicates = KnownHostsService::find_duplicates().await?;
+    log::info!(
+        "[known_hosts] Found {} duplicate groups, {} hosts with conflicting keys",
+        duplicates.exact.len(),
+        duplicates.conflicting.len()
+    );
+    Ok(duplicates)
+}
+
+/// Remove exact duplicate entries from ~/.ssh/known_hosts, keeping the first of each
+/// Returns the number of lines removed
+#[tauri::command]
+pub async fn dedupe_known_hosts() -> Result<usize, SshBuddyError> {
+    log::info!("[known_hosts] Removing duplicate entries");
+    let removed = KnownHostsService::dedupe().await?;
+    log::info!("[known_hosts] Removed {} lines", removed);
+    Ok(removed)
+}
+
+/// Check a host's keys against the SSHFP records it publishes in DNS
+/// port is where the keys are scanned (default 22); the server is not contacted
+/// when there are no records
+#[tauri::command]
+pub async fn verify_sshfp(
+    hostname: String,
+    port: Option<u16>,
+) -> Result<SshfpVerification, SshBuddyError> {
+    log::info!("[known_hosts] Verifying SSHFP records of {}", hostname);
+    let verification = KnownHostsService::verify_sshfp(&hostname, port).await?;
+    log::info!(
+        "[known_hosts] SSHFP result for {}: {:?} (DNSSEC: {})",
+        hostname,
+        verification.status,
+        verification.dnssec_signed
+    );
+    Ok(verification)
+}
+
+/// Convert ~/.ssh/known_hosts to LF line endings
+/// Returns the number of lines that had a carriage return
+#[tauri::command]
+pub async fn normalize_known_hosts() -> Result<usize, SshBuddyError> {
+    log::info!("[known_hosts] Normalizing known_hosts line endings");
+    let fixed = KnownHostsService::normalize_line_endings().await?;
+    log::info!("[known_hosts] Fixed {} lines", fixed);
+    Ok(fixed)
+}
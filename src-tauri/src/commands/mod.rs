@@ -1,15 +1,48 @@
 pub mod agent;
+pub mod cleanup;
+pub mod config;
 pub mod connection;
+pub mod inventory;
 pub mod keys;
 pub mod known_hosts;
 pub mod permissions;
+pub mod search;
+pub mod system;
 
 pub use agent::{
-    add_key_to_agent, is_agent_running, is_key_in_agent, list_agent_keys, remove_key_from_agent,
+    add_all_keys_to_agent, add_key_to_agent, agent_environment, agent_key_order, agent_ping,
+    export_agent_public_keys, is_agent_running, is_key_in_agent, is_key_in_any_agent,
+    list_agent_keys, list_unmatched_agent_keys, remove_key_from_agent, set_default_key_lifetime,
+    test_agent_key_signing,
+};
+pub use cleanup::cleanup_recommendations;
+pub use config::{
+    config_conflicts, config_hosts_using_key, get_effective_config, get_global_ssh_options,
+    read_ssh_config, set_global_ssh_option, validate_ssh_config, write_ssh_config,
+};
+pub use connection::{
+    connection_preflight, fetch_motd, find_working_key, install_public_key_on_host,
+    probe_auth_methods, test_ssh_connection,
+};
+pub use inventory::ssh_dir_inventory;
+pub use keys::{
+    archive_ssh_key, bulk_encrypt_keys, compare_public_keys, copy_public_key_to_clipboard,
+    delete_ssh_key, generate_ssh_key, get_certificate_details, get_key_details,
+    get_private_key_details, get_public_key_formats, github_key_payload, key_pair_status,
+    key_randomart, legacy_key_report, list_archived_keys, list_ssh_keys, list_ssh_keys_filtered,
+    list_ssh_keys_with_hosts, list_ssh_keys_with_usage, migrate_to_ed25519, read_public_key,
+    rotate_ssh_key, set_default_key_comment_template, suggest_config_block,
+    supported_key_algorithms, unarchive_ssh_key, validate_key_file,
+};
+pub use known_hosts::{
+    add_known_host, add_known_host_verified, check_known_platform_keys, dedupe_known_hosts,
+    find_known_hosts_duplicates, is_host_known, normalize_known_hosts, read_known_hosts_annotated,
+    read_known_hosts_raw, relabel_known_host, remove_known_host, resolve_hostname, scan_host_range,
+    verify_sshfp,
 };
-pub use connection::test_ssh_connection;
-pub use keys::{delete_ssh_key, generate_ssh_key, get_key_details, list_ssh_keys, read_public_key};
-pub use known_hosts::{add_known_host, remove_known_host};
 pub use permissions::{
-    check_key_permissions, check_ssh_dir_permissions, fix_key_permissions, fix_ssh_dir_permissions,
+    check_key_permissions, check_ssh_dir_permissions, fix_all_insecure_keys, fix_key_permissions,
+    fix_ssh_dir_permissions, list_insecure_keys,
 };
+pub use search::find_by_fingerprint;
+pub use system::system_ssh_capabilities;
@@ -1,11 +1,15 @@
 use crate::models::SshBuddyError;
-use crate::services::{PermissionCheckResult, PermissionFixResult, PermissionService};
+use crate::services::{
+    InsecureKey, InsecureKeyFix, PermissionCheckResult, PermissionFixResult, PermissionService,
+};
+use crate::utils::expand_path;
 
 /// Check key file permissions
 #[tauri::command]
 pub async fn check_key_permissions(
     key_path: String,
 ) -> Result<PermissionCheckResult, SshBuddyError> {
+    let key_path = expand_path(&key_path)?.to_string_lossy().to_string();
     log::info!("[permissions] Checking permissions for: {}", key_path);
     let result = PermissionService::check_key_permissions(&key_path).await?;
     log::info!("[permissions] Check result: {:?}", result);
@@ -15,6 +19,7 @@ pub async fn check_key_permissions(
 /// Fix key file permissions
 #[tauri::command]
 pub async fn fix_key_permissions(key_path: String) -> Result<PermissionFixResult, SshBuddyError> {
+    let key_path = expand_path(&key_path)?.to_string_lossy().to_string();
     log::info!("[permissions] Fixing permissions for: {}", key_path);
     let result = PermissionService::fix_key_permissions(&key_path).await?;
     log::info!("[permissions] Fix result: {:?}", result);
@@ -38,3 +43,25 @@ pub async fn fix_ssh_dir_permissions() -> Result<PermissionFixResult, SshBuddyEr
     log::info!("[permissions] Fix result: {:?}", result);
     Ok(result)
 }
+
+/// Private keys whose permissions are too open, with their current mode
+#[tauri::command]
+pub async fn list_insecure_keys() -> Result<Vec<InsecureKey>, SshBuddyError> {
+    log::info!("[permissions] Listing keys with insecure permissions");
+    let keys = PermissionService::list_insecure_keys().await?;
+    log::info!("[permissions] Found {} insecure key(s)", keys.len());
+    Ok(keys)
+}
+
+/// Fix the permissions of every key `list_insecure_keys` reports
+#[tauri::command]
+pub async fn fix_all_insecure_keys() -> Result<Vec<InsecureKeyFix>, SshBuddyError> {
+    log::info!("[permissions] Fixing all insecure keys");
+    let fixes = PermissionService::fix_all_insecure_keys().await?;
+    log::info!(
+        "[permissions] Fixed {} of {} key(s)",
+        fixes.iter().filter(|fix| fix.result.success).count(),
+        fixes.len()
+    );
+    Ok(fixes)
+}
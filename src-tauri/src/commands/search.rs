@@ -0,0 +1,14 @@
+use crate::models::SshBuddyError;
+use crate::services::{FingerprintLocation, FingerprintSearch};
+
+/// Find local keys, Agent identities and known_hosts entries with a fingerprint
+/// Accepts SHA256 (with or without the "SHA256:" prefix) or MD5 fingerprints
+#[tauri::command]
+pub async fn find_by_fingerprint(
+    fingerprint: String,
+) -> Result<Vec<FingerprintLocation>, SshBuddyError> {
+    log::info!("[search] Finding fingerprint: {}", fingerprint);
+    let locations = FingerprintSearch::find(&fingerprint).await?;
+    log::info!("[search] Found {} location(s)", locations.len());
+    Ok(locations)
+}
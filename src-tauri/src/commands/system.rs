@@ -0,0 +1,13 @@
+use crate::models::SshBuddyError;
+use crate::services::{SshCapabilities, SystemTools};
+
+/// Which OpenSSH/PuTTY command line tools are installed, with their versions
+/// Lets the UI hide actions (FIDO keys, ppk export) whose tooling is missing
+#[tauri::command]
+pub async fn system_ssh_capabilities() -> Result<SshCapabilities, SshBuddyError> {
+    tokio::task::spawn_blocking(SystemTools::capabilities)
+        .await
+        .map_err(|e| SshBuddyError::IoError {
+            message: format!("Failed to probe SSH tools: {}", e),
+        })
+}
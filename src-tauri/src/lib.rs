@@ -3,12 +3,35 @@ mod models;
 mod services;
 mod utils;
 
+/// Services the integration tests drive against a mock SSH directory
+#[doc(hidden)]
+pub mod test_support {
+    pub use crate::services::{AgentService, KeyManager};
+}
+
 use commands::{
-    add_key_to_agent, add_known_host, check_key_permissions, check_ssh_dir_permissions,
-    delete_ssh_key, fix_key_permissions, fix_ssh_dir_permissions, generate_ssh_key,
-    get_key_details, is_agent_running, is_key_in_agent, list_agent_keys, list_ssh_keys,
-    read_public_key, remove_key_from_agent, remove_known_host, test_ssh_connection,
+    add_all_keys_to_agent, add_key_to_agent, add_known_host, add_known_host_verified,
+    agent_environment, agent_key_order, agent_ping, archive_ssh_key, bulk_encrypt_keys,
+    check_key_permissions, check_known_platform_keys, check_ssh_dir_permissions,
+    cleanup_recommendations, compare_public_keys, config_conflicts, config_hosts_using_key,
+    connection_preflight, copy_public_key_to_clipboard, dedupe_known_hosts, delete_ssh_key,
+    export_agent_public_keys, fetch_motd, find_by_fingerprint, find_known_hosts_duplicates,
+    find_working_key, fix_all_insecure_keys, fix_key_permissions, fix_ssh_dir_permissions,
+    generate_ssh_key, get_certificate_details, get_effective_config, get_global_ssh_options,
+    get_key_details, get_private_key_details, get_public_key_formats, github_key_payload,
+    install_public_key_on_host, is_agent_running, is_host_known, is_key_in_agent,
+    is_key_in_any_agent, key_pair_status, key_randomart, legacy_key_report, list_agent_keys,
+    list_archived_keys, list_insecure_keys, list_ssh_keys, list_ssh_keys_filtered,
+    list_ssh_keys_with_hosts, list_ssh_keys_with_usage, list_unmatched_agent_keys,
+    migrate_to_ed25519, normalize_known_hosts, probe_auth_methods, read_known_hosts_annotated,
+    read_known_hosts_raw, read_public_key, read_ssh_config, relabel_known_host,
+    remove_key_from_agent, remove_known_host, resolve_hostname, rotate_ssh_key, scan_host_range,
+    set_default_key_comment_template, set_default_key_lifetime, set_global_ssh_option,
+    ssh_dir_inventory, suggest_config_block, supported_key_algorithms, system_ssh_capabilities,
+    test_agent_key_signing, test_ssh_connection, unarchive_ssh_key, validate_key_file,
+    validate_ssh_config, verify_sshfp, write_ssh_config,
 };
+use services::{AgentSettings, KeySettings};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -18,29 +41,98 @@ pub fn run() {
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_os::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .manage(AgentSettings::default())
+        .manage(KeySettings::default())
         .invoke_handler(tauri::generate_handler![
             // Key management
             list_ssh_keys,
+            list_ssh_keys_filtered,
+            list_ssh_keys_with_usage,
+            list_ssh_keys_with_hosts,
             read_public_key,
+            copy_public_key_to_clipboard,
+            get_public_key_formats,
+            github_key_payload,
+            compare_public_keys,
             get_key_details,
+            validate_key_file,
+            key_pair_status,
+            bulk_encrypt_keys,
+            key_randomart,
+            suggest_config_block,
+            get_private_key_details,
+            get_certificate_details,
+            supported_key_algorithms,
             generate_ssh_key,
+            set_default_key_comment_template,
+            rotate_ssh_key,
+            legacy_key_report,
+            migrate_to_ed25519,
             delete_ssh_key,
+            archive_ssh_key,
+            unarchive_ssh_key,
+            list_archived_keys,
             // SSH Agent
             is_agent_running,
+            agent_ping,
+            test_agent_key_signing,
+            agent_environment,
             list_agent_keys,
+            list_unmatched_agent_keys,
+            agent_key_order,
+            export_agent_public_keys,
             is_key_in_agent,
+            is_key_in_any_agent,
             add_key_to_agent,
+            add_all_keys_to_agent,
             remove_key_from_agent,
+            set_default_key_lifetime,
+            // SSH config
+            validate_ssh_config,
+            read_ssh_config,
+            write_ssh_config,
+            get_global_ssh_options,
+            set_global_ssh_option,
+            get_effective_config,
+            config_conflicts,
+            config_hosts_using_key,
             // SSH connection test
             test_ssh_connection,
+            install_public_key_on_host,
+            find_working_key,
+            probe_auth_methods,
+            connection_preflight,
+            fetch_motd,
             // Known Hosts
             add_known_host,
+            add_known_host_verified,
+            scan_host_range,
             remove_known_host,
+            relabel_known_host,
+            read_known_hosts_annotated,
+            read_known_hosts_raw,
+            normalize_known_hosts,
+            find_known_hosts_duplicates,
+            dedupe_known_hosts,
+            verify_sshfp,
+            is_host_known,
+            check_known_platform_keys,
+            resolve_hostname,
             // Permission management
             check_key_permissions,
             fix_key_permissions,
             check_ssh_dir_permissions,
             fix_ssh_dir_permissions,
+            list_insecure_keys,
+            fix_all_insecure_keys,
+            // Search
+            find_by_fingerprint,
+            // Cleanup
+            cleanup_recommendations,
+            ssh_dir_inventory,
+            // System
+            system_ssh_capabilities,
         ])
         .setup(|app| {
             if cfg!(debug_assertions) {
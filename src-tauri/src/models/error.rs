@@ -26,6 +26,9 @@ pub enum SshBuddyError {
     #[error("Invalid key name: {message}")]
     InvalidKeyName { message: String },
 
+    #[error("Invalid SSH config: {message}")]
+    InvalidConfig { message: String },
+
     // Connection errors
     #[error("Host key changed: {hostname}")]
     HostKeyChanged { hostname: String },
@@ -33,6 +36,12 @@ pub enum SshBuddyError {
     #[error("Host key unknown: {hostname}")]
     HostKeyUnknown { hostname: String },
 
+    #[error("No host key of {hostname} matches {expected}")]
+    HostKeyMismatch { hostname: String, expected: String },
+
+    #[error("Connected to {hostname} but it returned no host keys")]
+    NoHostKeys { hostname: String },
+
     #[error("Connection refused: {message}")]
     ConnectionRefused { message: String },
 
@@ -42,6 +51,9 @@ pub enum SshBuddyError {
     #[error("DNS resolution failed: {hostname}")]
     DnsResolutionFailed { hostname: String },
 
+    #[error("Too many connection tests of {host}, wait {wait_secs} seconds")]
+    RateLimited { host: String, wait_secs: u64 },
+
     // Authentication errors
     #[error("Permission denied: {reason}")]
     PermissionDenied { reason: String },
@@ -56,6 +68,9 @@ pub enum SshBuddyError {
     #[error("IO error: {message}")]
     IoError { message: String },
 
+    #[error("{tool} is not installed")]
+    ExternalToolMissing { tool: String },
+
     #[error("Agent not running")]
     AgentNotRunning,
 
@@ -110,15 +125,20 @@ impl SshBuddyError {
             SshBuddyError::InvalidPath { .. } => "InvalidPath",
             SshBuddyError::PathTraversalDetected { .. } => "PathTraversalDetected",
             SshBuddyError::InvalidKeyName { .. } => "InvalidKeyName",
+            SshBuddyError::InvalidConfig { .. } => "InvalidConfig",
             SshBuddyError::HostKeyChanged { .. } => "HostKeyChanged",
             SshBuddyError::HostKeyUnknown { .. } => "HostKeyUnknown",
+            SshBuddyError::HostKeyMismatch { .. } => "HostKeyMismatch",
+            SshBuddyError::NoHostKeys { .. } => "NoHostKeys",
             SshBuddyError::ConnectionRefused { .. } => "ConnectionRefused",
             SshBuddyError::ConnectionTimeout => "ConnectionTimeout",
             SshBuddyError::DnsResolutionFailed { .. } => "DnsResolutionFailed",
+            SshBuddyError::RateLimited { .. } => "RateLimited",
             SshBuddyError::PermissionDenied { .. } => "PermissionDenied",
             SshBuddyError::PassphraseRequired { .. } => "PassphraseRequired",
             SshBuddyError::KeyNotInAgent { .. } => "KeyNotInAgent",
             SshBuddyError::IoError { .. } => "IoError",
+            SshBuddyError::ExternalToolMissing { .. } => "ExternalToolMissing",
             SshBuddyError::AgentNotRunning => "AgentNotRunning",
             SshBuddyError::HomeDirNotFound => "HomeDirNotFound",
             SshBuddyError::Unknown { .. } => "Unknown",
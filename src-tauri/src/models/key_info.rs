@@ -36,6 +36,99 @@ impl std::fmt::Display for KeyType {
     }
 }
 
+/// Strength rating derived from key type and size
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyStrength {
+    Strong,
+    Acceptable,
+    Weak,
+    Deprecated,
+}
+
+impl KeyStrength {
+    /// Rate a key: Ed25519 and RSA >= 3072 are strong, RSA 2048 acceptable,
+    /// smaller RSA weak, and DSA or 1024-bit RSA deprecated
+    pub fn rate(key_type: &KeyType, bit_size: Option<u32>) -> Self {
+        match key_type {
+            KeyType::Ed25519 => KeyStrength::Strong,
+            KeyType::Rsa => match bit_size {
+                Some(bits) if bits >= 3072 => KeyStrength::Strong,
+                Some(bits) if bits >= 2048 => KeyStrength::Acceptable,
+                Some(bits) if bits > 1024 => KeyStrength::Weak,
+                Some(_) => KeyStrength::Deprecated,
+                None => KeyStrength::Acceptable,
+            },
+            KeyType::Ecdsa => KeyStrength::Acceptable,
+            KeyType::Dsa => KeyStrength::Deprecated,
+            KeyType::Unknown => KeyStrength::Acceptable,
+        }
+    }
+}
+
+/// A key algorithm that can be offered when generating a key
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyAlgorithmInfo {
+    /// Value to pass as `GenerateKeyOptions.key_type`
+    pub id: String,
+    pub display_name: String,
+    pub default_bits: Option<u32>,
+    /// Accepted sizes, when the size can be chosen
+    pub min_bits: Option<u32>,
+    pub max_bits: Option<u32>,
+    pub curve: Option<String>,
+    /// Needs a FIDO/U2F authenticator (sk-* keys)
+    pub requires_hardware_key: bool,
+    /// Rating of a key generated with the defaults
+    pub strength: KeyStrength,
+    pub recommended: bool,
+}
+
+/// One public key in the encodings other tools ask for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicKeyFormats {
+    /// `ssh-ed25519 AAAA... comment`
+    pub openssh: String,
+    /// `---- BEGIN SSH2 PUBLIC KEY ----` block, as written by `ssh-keygen -e`
+    pub rfc4716: String,
+    /// Bare base64 of the key blob
+    pub base64: String,
+}
+
+/// A key with the last time it authenticated a successful connection test
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyWithUsage {
+    #[serde(flatten)]
+    pub key: SSHKeyInfo,
+    /// Unix seconds; None if the key was never used in a successful test
+    pub last_used: Option<u64>,
+}
+
+/// A key with the config aliases that use it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyWithHosts {
+    #[serde(flatten)]
+    pub key: SSHKeyInfo,
+    /// Aliases whose resolved IdentityFile is this key; empty when no host uses it
+    pub used_by_hosts: Vec<String>,
+}
+
+/// Fields of a Git host's "Add SSH key" form
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitKeyPayload {
+    /// `<computer> <comment> <YYYY-MM-DD>`
+    pub title: String,
+    /// The OpenSSH public key line, without a trailing newline
+    pub key: String,
+    /// Algorithm name as listed by GitHub and GitLab (e.g. `ssh-ed25519`)
+    pub key_type: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SSHKeyInfo {
@@ -48,6 +141,171 @@ pub struct SSHKeyInfo {
     pub fingerprint: Option<String>,
     pub comment: Option<String>,
     pub bit_size: Option<u32>,
+    pub strength: KeyStrength,
+    /// Companion certificate (`<name>-cert.pub`), if present
+    pub certificate: Option<CertInfo>,
+    /// Set when the private key is readable by group or others (Unix only)
+    pub permission_warning: Option<String>,
+}
+
+impl SSHKeyInfo {
+    /// Weak or deprecated, readable by others, or missing its public key
+    pub fn needs_attention(&self) -> bool {
+        matches!(self.strength, KeyStrength::Weak | KeyStrength::Deprecated)
+            || self.permission_warning.is_some()
+            || !self.has_public_key
+    }
+
+    /// DSA, or RSA below 3072 bits: worth replacing with an Ed25519 key
+    /// RSA keys of unknown size are left out
+    pub fn is_migration_candidate(&self) -> bool {
+        match self.key_type {
+            KeyType::Dsa => true,
+            KeyType::Rsa => self.bit_size.is_some_and(|bits| bits < 3072),
+            _ => false,
+        }
+    }
+}
+
+/// Order of a filtered key list
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SortKey {
+    #[default]
+    Name,
+    Type,
+    /// Smallest first; sizes that can't be read last
+    BitSize,
+    /// Oldest first, by the private key's modification time
+    Age,
+}
+
+/// Restricts a filtered key list; the default keeps every key
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct KeyFilter {
+    pub key_type: Option<KeyType>,
+    /// Only keys for which `SSHKeyInfo::needs_attention` holds
+    pub needs_attention: bool,
+}
+
+impl KeyFilter {
+    pub fn matches(&self, key: &SSHKeyInfo) -> bool {
+        if let Some(ref key_type) = self.key_type {
+            if key.key_type != *key_type {
+                return false;
+            }
+        }
+        !self.needs_attention || key.needs_attention()
+    }
+}
+
+/// SSH certificate details (`*-cert.pub`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CertInfo {
+    pub name: String,
+    pub path: String,
+    /// Type of the certified key
+    #[serde(rename = "type")]
+    pub key_type: KeyType,
+    /// "user" or "host"
+    pub cert_type: String,
+    pub key_id: String,
+    pub serial: u64,
+    /// Empty means valid for any principal
+    pub principals: Vec<String>,
+    /// Unix timestamp (seconds)
+    pub valid_after: u64,
+    /// Unix timestamp (seconds); u64::MAX means no expiry
+    pub valid_before: u64,
+    pub expired: bool,
+    pub not_yet_valid: bool,
+    /// Fingerprint of the certified public key
+    pub fingerprint: String,
+    /// Fingerprint of the CA key that signed the certificate
+    pub ca_fingerprint: String,
+    pub comment: String,
+}
+
+/// Result of rotating a key: the untouched old key and its replacement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RotateKeyResult {
+    pub old_key: SSHKeyInfo,
+    pub new_key: SSHKeyInfo,
+    /// Public key line of the new key, ready to deploy
+    pub new_public_key: String,
+}
+
+/// Result of migrating a legacy key to Ed25519
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrateKeyResult {
+    #[serde(flatten)]
+    pub rotation: RotateKeyResult,
+    /// Config aliases that still use the old key; deploy the new key there
+    pub hosts_to_update: Vec<String>,
+}
+
+/// Result of deleting a key pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteKeyResult {
+    /// Config aliases whose IdentityFile was the deleted key; they need a new key
+    pub referenced_by: Vec<String>,
+    /// Caveat shown after a secure delete; absent for a plain delete
+    pub message: Option<String>,
+}
+
+/// Paths of a key pair's two files and which of them exist
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyPairStatus {
+    pub private_path: String,
+    pub private_exists: bool,
+    pub public_path: String,
+    pub public_exists: bool,
+    /// Whether the private key needs a passphrase; None when there is no private key
+    pub private_is_encrypted: Option<bool>,
+}
+
+/// What happened to one key during a bulk encryption
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyEncryptStatus {
+    Encrypted,
+    /// Left alone: the key already has a passphrase
+    AlreadyEncrypted,
+    /// Left alone: the key is a symlink, and its target lives outside the SSH directory
+    SkippedSymlink,
+    Failed,
+}
+
+/// Outcome of encrypting one private key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyEncryptResult {
+    pub key_name: String,
+    pub path: String,
+    pub status: KeyEncryptStatus,
+    /// Reason for a failure or a skipped symlink
+    pub message: Option<String>,
+}
+
+/// Result of checking a private key file for corruption
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyValidation {
+    /// The file parsed as an OpenSSH private key
+    pub parseable: bool,
+    pub encrypted: bool,
+    /// The private section was decrypted with the given passphrase (or needed none)
+    pub decrypted: bool,
+    /// Whether the key's public half matches the `.pub` file; None when there is none
+    pub public_key_matches: Option<bool>,
+    /// Why parsing or decryption failed
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
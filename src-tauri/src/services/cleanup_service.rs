@@ -0,0 +1,305 @@
+use crate::models::{KeyStrength, SSHKeyInfo, SshResult};
+use crate::services::{KeyManager, PermissionService};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Kind of problem found in ~/.ssh
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CleanupCategory {
+    /// Private key readable by other users
+    LoosePermissions,
+    /// `.pub` file whose private key is gone
+    OrphanPublicKey,
+    /// Private key without its `.pub` file
+    MissingPublicKey,
+    ExpiredCertificate,
+    /// Same key stored under several names
+    DuplicateKey,
+    /// DSA or undersized RSA key
+    WeakKey,
+}
+
+/// Fix the UI can offer for an item
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CleanupFix {
+    /// Delete the file at `path`
+    Delete,
+    /// Restrict the permissions of `path` to 600
+    Chmod,
+    /// Recreate the public key from the private key at `path`
+    Restore,
+}
+
+/// One finding of the cleanup sweep
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupItem {
+    pub category: CleanupCategory,
+    pub path: String,
+    pub message: String,
+    /// None when the user has to decide (e.g. which duplicate to keep)
+    pub fix: Option<CleanupFix>,
+}
+
+/// A private key file and what was found about it
+struct PrivateKeyFile {
+    path: PathBuf,
+    has_public_key: bool,
+    /// Current mode, when it is looser than it should be
+    loose_mode: Option<String>,
+}
+
+/// Collects cleanup recommendations from the key and permission services
+/// Nothing is changed; fixes are applied by the existing commands once the user picks them
+pub struct CleanupService;
+
+impl CleanupService {
+    pub async fn recommendations() -> SshResult<Vec<CleanupItem>> {
+        let manager = KeyManager::new()?;
+        let keys = manager.list_keys().await?;
+
+        let mut private_keys = Vec::new();
+        for path in manager.list_private_key_paths().await? {
+            let path_str = path.to_string_lossy().to_string();
+            let loose_mode = match PermissionService::check_key_permissions(&path_str).await {
+                Ok(check) if !check.is_valid => {
+                    Some(check.current_mode.unwrap_or_else(|| "unknown".to_string()))
+                }
+                _ => None,
+            };
+            private_keys.push(PrivateKeyFile {
+                has_public_key: Self::public_key_path(&path).exists(),
+                path,
+                loose_mode,
+            });
+        }
+
+        let items = Self::assess(&keys, &private_keys);
+        log::info!("[cleanup] {} recommendation(s)", items.len());
+        Ok(items)
+    }
+
+    /// `<private key>.pub`, keeping any dots in the key name
+    fn public_key_path(private_key_path: &Path) -> PathBuf {
+        let mut path = private_key_path.as_os_str().to_owned();
+        path.push(".pub");
+        PathBuf::from(path)
+    }
+
+    fn assess(keys: &[SSHKeyInfo], private_keys: &[PrivateKeyFile]) -> Vec<CleanupItem> {
+        let mut items = Vec::new();
+
+        for private_key in private_keys {
+            let path = private_key.path.to_string_lossy().to_string();
+            if let Some(ref mode) = private_key.loose_mode {
+                items.push(CleanupItem {
+                    category: CleanupCategory::LoosePermissions,
+                    path: path.clone(),
+                    message: format!("Private key permissions are {}, should be 600", mode),
+                    fix: Some(CleanupFix::Chmod),
+                });
+            }
+            if !private_key.has_public_key {
+                items.push(CleanupItem {
+                    category: CleanupCategory::MissingPublicKey,
+                    path,
+                    message: "Private key has no .pub file".to_string(),
+                    fix: Some(CleanupFix::Restore),
+                });
+            }
+        }
+
+        for key in keys {
+            if !Path::new(&key.private_key_path).exists() {
+                items.push(CleanupItem {
+                    category: CleanupCategory::OrphanPublicKey,
+                    path: key.public_key_path.clone(),
+                    message: format!("Public key {} has no private key", key.name),
+                    fix: Some(CleanupFix::Delete),
+                });
+            }
+
+            if let Some(ref cert) = key.certificate {
+                if cert.expired {
+                    items.push(CleanupItem {
+                        category: CleanupCategory::ExpiredCertificate,
+                        path: cert.path.clone(),
+                        message: format!("Certificate {} has expired", cert.name),
+                        fix: Some(CleanupFix::Delete),
+                    });
+                }
+            }
+
+            if matches!(key.strength, KeyStrength::Weak | KeyStrength::Deprecated) {
+                let size = key
+                    .bit_size
+                    .map(|bits| format!(" ({} bits)", bits))
+                    .unwrap_or_default();
+                items.push(CleanupItem {
+                    category: CleanupCategory::WeakKey,
+                    path: key.private_key_path.clone(),
+                    message: format!("{} is a weak {:?} key{}", key.name, key.key_type, size),
+                    fix: None,
+                });
+            }
+        }
+
+        // Group by fingerprint, keeping the (sorted) order of the keys
+        let mut by_fingerprint: HashMap<&str, Vec<&SSHKeyInfo>> = HashMap::new();
+        for key in keys {
+            if let Some(ref fingerprint) = key.fingerprint {
+                by_fingerprint.entry(fingerprint).or_default().push(key);
+            }
+        }
+        for key in keys {
+            let copies = match key.fingerprint.as_deref() {
+                Some(fingerprint) => &by_fingerprint[fingerprint],
+                None => continue,
+            };
+            if copies.len() > 1 {
+                let others: Vec<&str> = copies
+                    .iter()
+                    .filter(|other| other.name != key.name)
+                    .map(|other| other.name.as_str())
+                    .collect();
+                items.push(CleanupItem {
+                    category: CleanupCategory::DuplicateKey,
+                    path: key.public_key_path.clone(),
+                    message: format!("{} is the same key as {}", key.name, others.join(", ")),
+                    fix: None,
+                });
+            }
+        }
+
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CertInfo, KeyType};
+    use tempfile::TempDir;
+
+    fn key(dir: &Path, name: &str, fingerprint: &str) -> SSHKeyInfo {
+        SSHKeyInfo {
+            name: name.to_string(),
+            key_type: KeyType::Ed25519,
+            has_public_key: true,
+            public_key_path: dir
+                .join(format!("{}.pub", name))
+                .to_string_lossy()
+                .to_string(),
+            private_key_path: dir.join(name).to_string_lossy().to_string(),
+            fingerprint: Some(fingerprint.to_string()),
+            comment: None,
+            bit_size: Some(256),
+            strength: KeyStrength::Strong,
+            certificate: None,
+            permission_warning: None,
+        }
+    }
+
+    fn categories(items: &[CleanupItem]) -> Vec<CleanupCategory> {
+        items.iter().map(|item| item.category).collect()
+    }
+
+    #[test]
+    fn test_assess_clean_directory() {
+        let temp = TempDir::new().unwrap();
+        std::fs::write(temp.path().join("id_ed25519"), "key").unwrap();
+        let keys = vec![key(temp.path(), "id_ed25519", "SHA256:aaa")];
+        let private_keys = vec![PrivateKeyFile {
+            path: temp.path().join("id_ed25519"),
+            has_public_key: true,
+            loose_mode: None,
+        }];
+
+        assert!(CleanupService::assess(&keys, &private_keys).is_empty());
+    }
+
+    #[test]
+    fn test_assess_private_key_problems() {
+        let private_keys = vec![PrivateKeyFile {
+            path: PathBuf::from("/home/user/.ssh/id_old"),
+            has_public_key: false,
+            loose_mode: Some("644".to_string()),
+        }];
+
+        let items = CleanupService::assess(&[], &private_keys);
+        assert_eq!(
+            categories(&items),
+            vec![
+                CleanupCategory::LoosePermissions,
+                CleanupCategory::MissingPublicKey
+            ]
+        );
+        assert_eq!(items[0].fix, Some(CleanupFix::Chmod));
+        assert_eq!(items[1].fix, Some(CleanupFix::Restore));
+    }
+
+    #[test]
+    fn test_assess_public_key_problems() {
+        let temp = TempDir::new().unwrap();
+        for name in ["id_work", "id_copy", "id_dsa"] {
+            std::fs::write(temp.path().join(name), "key").unwrap();
+        }
+
+        let mut expired = key(temp.path(), "id_work", "SHA256:same");
+        expired.certificate = Some(CertInfo {
+            name: "id_work-cert".to_string(),
+            path: temp
+                .path()
+                .join("id_work-cert.pub")
+                .to_string_lossy()
+                .to_string(),
+            key_type: KeyType::Ed25519,
+            cert_type: "user".to_string(),
+            key_id: "work".to_string(),
+            serial: 1,
+            principals: vec![],
+            valid_after: 0,
+            valid_before: 1,
+            expired: true,
+            not_yet_valid: false,
+            fingerprint: "SHA256:same".to_string(),
+            ca_fingerprint: "SHA256:ca".to_string(),
+            comment: String::new(),
+        });
+        let mut weak = key(temp.path(), "id_dsa", "SHA256:dsa");
+        weak.key_type = KeyType::Dsa;
+        weak.strength = KeyStrength::Deprecated;
+
+        let keys = vec![
+            key(temp.path(), "id_copy", "SHA256:same"),
+            weak,
+            key(temp.path(), "id_gone", "SHA256:gone"),
+            expired,
+        ];
+
+        let items = CleanupService::assess(&keys, &[]);
+        assert_eq!(
+            categories(&items),
+            vec![
+                CleanupCategory::WeakKey,
+                CleanupCategory::OrphanPublicKey,
+                CleanupCategory::ExpiredCertificate,
+                CleanupCategory::DuplicateKey,
+                CleanupCategory::DuplicateKey,
+            ]
+        );
+        assert!(items[1].path.ends_with("id_gone.pub"));
+        assert_eq!(items[3].message, "id_copy is the same key as id_work");
+    }
+
+    #[test]
+    fn test_public_key_path_keeps_dots() {
+        assert_eq!(
+            CleanupService::public_key_path(Path::new("/home/user/.ssh/id_ed25519.work")),
+            PathBuf::from("/home/user/.ssh/id_ed25519.work.pub")
+        );
+    }
+}
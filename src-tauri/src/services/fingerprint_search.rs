@@ -0,0 +1,355 @@
+use crate::models::{SSHKeyInfo, SshBuddyError, SshResult};
+use crate::services::known_hosts::KnownHostEntry;
+use crate::services::{AgentService, KeyManager, KnownHostsService};
+use serde::{Deserialize, Serialize};
+use ssh_key::{HashAlg, PublicKey};
+use tokio::fs;
+
+/// A fingerprint to search for, normalized from user input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FingerprintQuery {
+    /// Unpadded base64 SHA256 digest (without the "SHA256:" prefix)
+    Sha256(String),
+    /// Lowercase colon-separated hex MD5 digest (without the "MD5:" prefix)
+    Md5(String),
+}
+
+impl FingerprintQuery {
+    /// Parse a fingerprint such as "SHA256:abc...", "abc...", "MD5:aa:bb:..." or "aa:bb:..."
+    pub fn parse(input: &str) -> SshResult<Self> {
+        let input = input.trim();
+
+        let query = if let Some(rest) = input.strip_prefix("SHA256:") {
+            Self::Sha256(rest.trim_end_matches('=').to_string())
+        } else if let Some(rest) = input.strip_prefix("MD5:") {
+            Self::Md5(rest.to_lowercase())
+        } else if Self::looks_like_md5(input) {
+            Self::Md5(input.to_lowercase())
+        } else {
+            Self::Sha256(input.trim_end_matches('=').to_string())
+        };
+
+        let is_empty = match &query {
+            Self::Sha256(value) | Self::Md5(value) => value.is_empty(),
+        };
+        if is_empty {
+            return Err(SshBuddyError::InvalidKeyFormat {
+                message: "Fingerprint cannot be empty".to_string(),
+            });
+        }
+
+        Ok(query)
+    }
+
+    /// 16 colon-separated hex bytes
+    fn looks_like_md5(input: &str) -> bool {
+        let parts: Vec<&str> = input.split(':').collect();
+        parts.len() == 16
+            && parts
+                .iter()
+                .all(|p| p.len() == 2 && p.chars().all(|c| c.is_ascii_hexdigit()))
+    }
+
+    /// Whether a public key has this fingerprint
+    pub fn matches(&self, pub_key: &PublicKey) -> bool {
+        match self {
+            Self::Sha256(_) => {
+                self.matches_sha256(&pub_key.fingerprint(HashAlg::Sha256).to_string())
+            }
+            Self::Md5(value) => match pub_key.to_bytes() {
+                Ok(blob) => md5_fingerprint(&blob) == *value,
+                Err(_) => false,
+            },
+        }
+    }
+
+    /// Whether an already computed "SHA256:..." fingerprint is this one
+    fn matches_sha256(&self, fingerprint: &str) -> bool {
+        match self {
+            Self::Sha256(value) => fingerprint.strip_prefix("SHA256:") == Some(value.as_str()),
+            Self::Md5(_) => false,
+        }
+    }
+}
+
+/// OpenSSH-style MD5 fingerprint of a public key blob ("aa:bb:...")
+fn md5_fingerprint(blob: &[u8]) -> String {
+    md5::compute(blob)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Where a fingerprint was found
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum FingerprintLocation {
+    /// A public key file in ~/.ssh
+    #[serde(rename_all = "camelCase")]
+    LocalKey {
+        name: String,
+        public_key_path: String,
+        comment: String,
+    },
+    /// An identity loaded in the SSH Agent
+    #[serde(rename_all = "camelCase")]
+    AgentKey {
+        comment: String,
+        #[serde(rename = "type")]
+        key_type: String,
+    },
+    /// A known_hosts entry
+    #[serde(rename_all = "camelCase")]
+    KnownHost {
+        /// Host field as written in known_hosts (may be hashed)
+        hosts: String,
+        #[serde(rename = "type")]
+        key_type: String,
+        /// 1-based line number
+        line: usize,
+    },
+}
+
+/// Searches local keys, the SSH Agent and known_hosts for a fingerprint
+pub struct FingerprintSearch;
+
+impl FingerprintSearch {
+    /// Find every location holding a key with the given fingerprint
+    pub async fn find(fingerprint: &str) -> SshResult<Vec<FingerprintLocation>> {
+        let query = FingerprintQuery::parse(fingerprint)?;
+
+        let local_keys = KeyManager::new()?.list_keys().await?;
+        let mut locations = Self::search_local_keys(&local_keys, &query).await;
+
+        // Agent is optional; a stopped Agent simply contributes nothing
+        match AgentService::list_identities().await {
+            Ok(identities) => locations.extend(Self::search_agent(&identities, &query)),
+            Err(e) => log::info!("[fingerprint_search] Skipping Agent: {}", e),
+        }
+
+        let entries = KnownHostsService::load_entries().await;
+        locations.extend(Self::search_known_hosts(&entries, &query));
+
+        Ok(locations)
+    }
+
+    /// Search listed local keys
+    /// SHA256 uses the listed fingerprint; MD5 needs the public key itself
+    async fn search_local_keys(
+        keys: &[SSHKeyInfo],
+        query: &FingerprintQuery,
+    ) -> Vec<FingerprintLocation> {
+        let mut locations = Vec::new();
+
+        for key in keys {
+            let fingerprint = match &key.fingerprint {
+                Some(fingerprint) => fingerprint,
+                None => continue,
+            };
+
+            let matched = match query {
+                FingerprintQuery::Sha256(_) => query.matches_sha256(fingerprint),
+                FingerprintQuery::Md5(_) => match fs::read_to_string(&key.public_key_path).await {
+                    Ok(content) => PublicKey::from_openssh(&content)
+                        .map(|pub_key| query.matches(&pub_key))
+                        .unwrap_or(false),
+                    Err(_) => false,
+                },
+            };
+
+            if matched {
+                locations.push(FingerprintLocation::LocalKey {
+                    name: key.name.clone(),
+                    public_key_path: key.public_key_path.clone(),
+                    comment: key.comment.clone().unwrap_or_default(),
+                });
+            }
+        }
+
+        locations
+    }
+
+    /// Search Agent identities (public key, comment)
+    fn search_agent(
+        identities: &[(PublicKey, String)],
+        query: &FingerprintQuery,
+    ) -> Vec<FingerprintLocation> {
+        identities
+            .iter()
+            .filter(|(pub_key, _)| query.matches(pub_key))
+            .map(|(pub_key, comment)| FingerprintLocation::AgentKey {
+                comment: comment.clone(),
+                key_type: pub_key.algorithm().as_str().to_string(),
+            })
+            .collect()
+    }
+
+    /// Search parsed known_hosts entries, including hashed ones
+    fn search_known_hosts(
+        entries: &[KnownHostEntry],
+        query: &FingerprintQuery,
+    ) -> Vec<FingerprintLocation> {
+        entries
+            .iter()
+            .filter_map(|entry| {
+                let pub_key = PublicKey::from_openssh(&entry.key()).ok()?;
+                query
+                    .matches(&pub_key)
+                    .then(|| FingerprintLocation::KnownHost {
+                        hosts: entry.hosts.clone(),
+                        key_type: pub_key.algorithm().as_str().to_string(),
+                        line: entry.line,
+                    })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{KeyStrength, KeyType};
+    use tempfile::TempDir;
+
+    const TEST_PUBLIC_KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIFlXOQk34tnLe4gTVThVboRl89gl4sC9wNcw+PtGp1Mk test@example";
+
+    fn test_fingerprint() -> String {
+        PublicKey::from_openssh(TEST_PUBLIC_KEY)
+            .unwrap()
+            .fingerprint(HashAlg::Sha256)
+            .to_string()
+    }
+
+    // ========================================
+    // Query parsing tests
+    // ========================================
+
+    #[test]
+    fn test_parse_query_with_and_without_prefix() {
+        let fingerprint = test_fingerprint();
+        let bare = fingerprint.trim_start_matches("SHA256:");
+
+        assert_eq!(
+            FingerprintQuery::parse(&fingerprint).unwrap(),
+            FingerprintQuery::Sha256(bare.to_string())
+        );
+        assert_eq!(
+            FingerprintQuery::parse(bare).unwrap(),
+            FingerprintQuery::Sha256(bare.to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_query_md5() {
+        let md5 = "16:27:AC:A5:76:28:2D:36:63:1B:56:4D:EB:DF:A6:48";
+        let expected = FingerprintQuery::Md5(md5.to_lowercase());
+
+        assert_eq!(FingerprintQuery::parse(md5).unwrap(), expected);
+        assert_eq!(
+            FingerprintQuery::parse(&format!("MD5:{}", md5)).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_parse_query_rejects_empty() {
+        assert!(FingerprintQuery::parse("").is_err());
+        assert!(FingerprintQuery::parse("SHA256:").is_err());
+    }
+
+    #[test]
+    fn test_md5_query_matches() {
+        let pub_key = PublicKey::from_openssh(TEST_PUBLIC_KEY).unwrap();
+        let md5 = md5_fingerprint(&pub_key.to_bytes().unwrap());
+
+        assert!(FingerprintQuery::parse(&md5).unwrap().matches(&pub_key));
+    }
+
+    // ========================================
+    // Location search tests
+    // ========================================
+
+    fn local_key(name: &str, public_key_path: &str, fingerprint: Option<String>) -> SSHKeyInfo {
+        SSHKeyInfo {
+            name: name.to_string(),
+            key_type: KeyType::Ed25519,
+            has_public_key: fingerprint.is_some(),
+            public_key_path: public_key_path.to_string(),
+            private_key_path: String::new(),
+            fingerprint,
+            comment: Some("test@example".to_string()),
+            bit_size: Some(256),
+            strength: KeyStrength::Strong,
+            certificate: None,
+            permission_warning: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_local_keys() {
+        let keys = vec![
+            local_key("id_test", "/keys/id_test.pub", Some(test_fingerprint())),
+            local_key("other", "/keys/other.pub", Some("SHA256:other".to_string())),
+            local_key("no_pub", "", None),
+        ];
+
+        let query = FingerprintQuery::parse(&test_fingerprint()).unwrap();
+        let locations = FingerprintSearch::search_local_keys(&keys, &query).await;
+
+        assert_eq!(
+            locations,
+            vec![FingerprintLocation::LocalKey {
+                name: "id_test".to_string(),
+                public_key_path: "/keys/id_test.pub".to_string(),
+                comment: "test@example".to_string(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_local_keys_md5() {
+        let temp = TempDir::new().unwrap();
+        let pub_path = temp.path().join("id_test.pub");
+        fs::write(&pub_path, TEST_PUBLIC_KEY).await.unwrap();
+        let keys = vec![local_key(
+            "id_test",
+            pub_path.to_str().unwrap(),
+            Some(test_fingerprint()),
+        )];
+
+        let pub_key = PublicKey::from_openssh(TEST_PUBLIC_KEY).unwrap();
+        let md5 = md5_fingerprint(&pub_key.to_bytes().unwrap());
+        let query = FingerprintQuery::parse(&md5).unwrap();
+
+        assert_eq!(
+            FingerprintSearch::search_local_keys(&keys, &query)
+                .await
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_search_known_hosts() {
+        let key_data = TEST_PUBLIC_KEY.trim_end_matches(" test@example");
+        let content = format!(
+            "# comment\ngithub.com ssh-rsa AAAAB3NzaC1yc2E\n[example.com]:2222,10.0.0.1 {}\n",
+            key_data
+        );
+
+        let bare = test_fingerprint().trim_start_matches("SHA256:").to_string();
+        let query = FingerprintQuery::parse(&bare).unwrap();
+        let entries = KnownHostsService::parse_entries(&content);
+        let locations = FingerprintSearch::search_known_hosts(&entries, &query);
+
+        assert_eq!(
+            locations,
+            vec![FingerprintLocation::KnownHost {
+                hosts: "[example.com]:2222,10.0.0.1".to_string(),
+                key_type: "ssh-ed25519".to_string(),
+                line: 3,
+            }]
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use crate::models::SshResult;
+use crate::services::{AgentService, KeyManager, KnownHostsService};
+use crate::utils::{ssh_dir, SshConfigParser};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Counts and sizes of ~/.ssh for the overview card
+/// Each count is None when its part of the directory could not be read
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SshDirInventory {
+    pub key_pair_count: Option<usize>,
+    pub encrypted_key_count: Option<usize>,
+    pub unencrypted_key_count: Option<usize>,
+    pub known_hosts_count: Option<usize>,
+    /// Host blocks in ~/.ssh/config, not counting `Host *`
+    pub config_host_count: Option<usize>,
+    /// Size of every file under ~/.ssh, archived keys included
+    pub total_bytes: Option<u64>,
+    /// Whether a `config.<unix time>.bak` left by a config write exists
+    pub has_backup: bool,
+}
+
+/// Summarizes ~/.ssh from the key, known_hosts and config services
+pub struct InventoryService;
+
+impl InventoryService {
+    pub async fn ssh_dir_inventory() -> SshResult<SshDirInventory> {
+        Ok(Self::inventory_in(&ssh_dir()?).await)
+    }
+
+    async fn inventory_in(ssh_dir: &Path) -> SshDirInventory {
+        let manager = KeyManager::in_dir(ssh_dir.to_path_buf());
+
+        let key_pair_count = match manager.list_keys().await {
+            Ok(keys) => Some(keys.len()),
+            Err(e) => {
+                log::warn!("[inventory] Failed to list keys: {}", e);
+                None
+            }
+        };
+
+        let (encrypted_key_count, unencrypted_key_count) =
+            match manager.list_private_key_paths().await {
+                Ok(paths) => {
+                    let encrypted = paths
+                        .iter()
+                        .filter(|path| AgentService::is_key_encrypted(&path.to_string_lossy()))
+                        .count();
+                    (Some(encrypted), Some(paths.len() - encrypted))
+                }
+                Err(e) => {
+                    log::warn!("[inventory] Failed to list private keys: {}", e);
+                    (None, None)
+                }
+            };
+
+        let known_hosts_count = Self::read_optional(&ssh_dir.join("known_hosts"))
+            .await
+            .map(|content| KnownHostsService::parse_entries(&content).len());
+
+        let config_host_count = Self::read_optional(&ssh_dir.join("config"))
+            .await
+            .map(|content| {
+                SshConfigParser::parse(&content)
+                    .iter()
+                    .filter(|host| host.host_pattern != "*")
+                    .count()
+            });
+
+        let inventory = SshDirInventory {
+            key_pair_count,
+            encrypted_key_count,
+            unencrypted_key_count,
+            known_hosts_count,
+            config_host_count,
+            total_bytes: Self::total_bytes(ssh_dir).await,
+            has_backup: Self::has_config_backup(ssh_dir).await,
+        };
+        log::info!("[inventory] {:?}", inventory);
+        inventory
+    }
+
+    /// File content, empty when the file does not exist, None when it can't be read
+    async fn read_optional(path: &Path) -> Option<String> {
+        match fs::read_to_string(path).await {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Some(String::new()),
+            Err(e) => {
+                log::warn!("[inventory] Failed to read {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// Total size of the files under `dir`; symlinks are not followed
+    async fn total_bytes(dir: &Path) -> Option<u64> {
+        if !dir.exists() {
+            return Some(0);
+        }
+
+        let mut total = 0;
+        let mut pending: Vec<PathBuf> = vec![dir.to_path_buf()];
+        while let Some(current) = pending.pop() {
+            let mut entries = match fs::read_dir(&current).await {
+                Ok(entries) => entries,
+                Err(e) => {
+                    log::warn!("[inventory] Failed to read {:?}: {}", current, e);
+                    return None;
+                }
+            };
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let Ok(metadata) = fs::symlink_metadata(entry.path()).await else {
+                    continue;
+                };
+                if metadata.is_dir() {
+                    pending.push(entry.path());
+                } else if metadata.is_file() {
+                    total += metadata.len();
+                }
+            }
+        }
+        Some(total)
+    }
+
+    async fn has_config_backup(dir: &Path) -> bool {
+        let Ok(mut entries) = fs::read_dir(dir).await else {
+            return false;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with("config.") && name.ends_with(".bak") {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+    use ssh_key::{Algorithm, LineEnding, PrivateKey};
+    use tempfile::TempDir;
+
+    fn write_key_pair(dir: &Path, name: &str, passphrase: Option<&str>) {
+        let key = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).unwrap();
+        let key = match passphrase {
+            Some(passphrase) => key.encrypt(&mut OsRng, passphrase).unwrap(),
+            None => key,
+        };
+        std::fs::write(
+            dir.join(name),
+            key.to_openssh(LineEnding::LF).unwrap().as_bytes(),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(format!("{}.pub", name)),
+            key.public_key().to_openssh().unwrap(),
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_inventory_counts() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        write_key_pair(dir, "id_ed25519", None);
+        write_key_pair(dir, "id_work", Some("secret"));
+        std::fs::write(
+            dir.join("known_hosts"),
+            "github.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl\n\
+             # comment\n\
+             [example.com]:2222 ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("config"),
+            "Host *\n  AddKeysToAgent yes\n\nHost github.com\n  User git\n\nHost work\n  HostName work.example.com\n",
+        )
+        .unwrap();
+
+        let inventory = InventoryService::inventory_in(dir).await;
+        assert_eq!(inventory.key_pair_count, Some(2));
+        assert_eq!(inventory.encrypted_key_count, Some(1));
+        assert_eq!(inventory.unencrypted_key_count, Some(1));
+        assert_eq!(inventory.known_hosts_count, Some(2));
+        assert_eq!(inventory.config_host_count, Some(2));
+        assert!(inventory.total_bytes.unwrap() > 0);
+        assert!(!inventory.has_backup);
+
+        std::fs::write(dir.join("config.1700000000.bak"), "").unwrap();
+        assert!(InventoryService::inventory_in(dir).await.has_backup);
+    }
+
+    #[tokio::test]
+    async fn test_inventory_degrades_per_area() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        // A directory where known_hosts should be can't be read as a file
+        std::fs::create_dir(dir.join("known_hosts")).unwrap();
+
+        let inventory = InventoryService::inventory_in(dir).await;
+        assert_eq!(inventory.known_hosts_count, None);
+        assert_eq!(inventory.config_host_count, Some(0));
+        assert_eq!(inventory.key_pair_count, Some(0));
+        assert_eq!(inventory.total_bytes, Some(0));
+    }
+}
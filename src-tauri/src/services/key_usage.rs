@@ -0,0 +1,112 @@
+use crate::models::{SshBuddyError, SshResult};
+use crate::utils::atomic_write;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs;
+use tokio::sync::Mutex;
+
+/// App data directory name, the same one Tauri derives from the bundle identifier
+const APP_IDENTIFIER: &str = "com.sshbuddy";
+
+/// File in the app data directory holding the usage records
+const USAGE_FILE_NAME: &str = "key_usage.json";
+
+/// Serializes read-modify-write cycles of the usage file
+static USAGE_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// When each key last authenticated a successful connection test
+/// Stored as JSON mapping the identity file path to Unix seconds
+pub struct KeyUsageStore {
+    path: PathBuf,
+}
+
+impl KeyUsageStore {
+    /// Store in the app data directory
+    pub fn new() -> SshResult<Self> {
+        let data_dir = dirs::data_dir().ok_or(SshBuddyError::HomeDirNotFound)?;
+        Ok(Self::at(
+            data_dir.join(APP_IDENTIFIER).join(USAGE_FILE_NAME),
+        ))
+    }
+
+    /// Store backed by a specific file
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Last successful use of each identity file
+    /// A missing or unreadable file has no records
+    pub async fn load(&self) -> HashMap<String, u64> {
+        let content = match fs::read_to_string(&self.path).await {
+            Ok(content) => content,
+            Err(_) => return HashMap::new(),
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            log::warn!(
+                "[key_usage] Ignoring unreadable usage file {:?}: {}",
+                self.path,
+                e
+            );
+            HashMap::new()
+        })
+    }
+
+    /// Record that `identity_file` authenticated a connection just now
+    pub async fn record_now(&self, identity_file: &str) -> SshResult<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.record(identity_file, now).await
+    }
+
+    async fn record(&self, identity_file: &str, when: u64) -> SshResult<()> {
+        let _guard = USAGE_LOCK.lock().await;
+
+        let mut usage = self.load().await;
+        usage.insert(identity_file.to_string(), when);
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let json = serde_json::to_vec_pretty(&usage).map_err(|e| SshBuddyError::IoError {
+            message: format!("Failed to encode key usage: {}", e),
+        })?;
+        atomic_write(&self.path, &json, 0o600).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_record_and_load() {
+        let temp = TempDir::new().unwrap();
+        let store = KeyUsageStore::at(temp.path().join("data").join(USAGE_FILE_NAME));
+        assert!(store.load().await.is_empty());
+
+        store.record("/home/u/.ssh/id_ed25519", 100).await.unwrap();
+        store.record("/home/u/.ssh/id_rsa", 200).await.unwrap();
+        store.record("/home/u/.ssh/id_ed25519", 300).await.unwrap();
+
+        let usage = store.load().await;
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage.get("/home/u/.ssh/id_ed25519"), Some(&300));
+        assert_eq!(usage.get("/home/u/.ssh/id_rsa"), Some(&200));
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_file_is_replaced() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join(USAGE_FILE_NAME);
+        std::fs::write(&path, "{not json").unwrap();
+        let store = KeyUsageStore::at(path);
+
+        assert!(store.load().await.is_empty());
+        store.record("/home/u/.ssh/id_ed25519", 100).await.unwrap();
+        assert_eq!(store.load().await.len(), 1);
+    }
+}
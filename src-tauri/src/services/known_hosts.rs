@@ -1,27 +1,651 @@
 use crate::models::{SshBuddyError, SshResult};
+use crate::services::SystemTools;
+use crate::utils::{
+    atomic_write, existing_mode_or, expand_path, lookup_sshfp, ssh_dir, validate_hostname,
+    validate_path_in_ssh_dir, SshfpRecord,
+};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
-use std::net::ToSocketAddrs;
-use std::path::PathBuf;
+use sha1::{Digest, Sha1};
+use ssh_key::PublicKey;
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::SystemTime;
 use tokio::fs;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::net::TcpStream;
 use tokio::time::{timeout, Duration};
 
+/// Most hosts a single range scan may cover (a /26)
+const MAX_RANGE_HOSTS: usize = 64;
+
+/// Hosts of a range scanned at the same time
+const RANGE_SCAN_CONCURRENCY: usize = 8;
+
+/// Maximum number of connection attempts when scanning host keys
+const MAX_SCAN_ATTEMPTS: u32 = 3;
+
+/// Initial delay between scan attempts, doubled after each failure
+const INITIAL_SCAN_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Upper bound for the delay between scan attempts
+const MAX_SCAN_BACKOFF: Duration = Duration::from_secs(4);
+
+/// Seconds to wait for a host's banner and keys unless the caller asks for longer
+const DEFAULT_SCAN_TIMEOUT_SECS: u64 = 5;
+
+/// Longest scan timeout a caller may ask for
+const MAX_SCAN_TIMEOUT_SECS: u64 = 60;
+
+/// One lock per known_hosts file, held across each read-modify-write of it
+static FILE_LOCKS: OnceLock<std::sync::Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>> =
+    OnceLock::new();
+
+/// Parsed known_hosts files, keyed by path
+static PARSE_CACHE: OnceLock<std::sync::Mutex<HashMap<PathBuf, CachedKnownHosts>>> =
+    OnceLock::new();
+
+/// A parsed known_hosts file and the modification time and size it was parsed at
+struct CachedKnownHosts {
+    stamp: (SystemTime, u64),
+    keys: KnownHostKeys,
+}
+
+/// Host keys loaded from known_hosts, keyed by host name ("host" or "[host]:port")
+/// Hashed entries stay keyed by their "|1|salt|hash" field and are matched by `stored_keys`
+/// Each value is the normalized "<key-type> <base64>" of an entry, without its comment
+pub type KnownHostKeys = HashMap<String, Vec<String>>;
+
+/// Known hosts check result
+#[derive(Debug, Clone, PartialEq)]
+pub enum KnownHostStatus {
+    /// Host is in known_hosts and key matches
+    Matched,
+    /// Host is not in known_hosts (first connection)
+    Unknown,
+    /// Host is in known_hosts but key is different (possible attack or server reinstall)
+    Changed,
+}
+
+/// A host key line of known_hosts
+#[derive(Debug, Clone, PartialEq)]
+pub struct KnownHostEntry {
+    /// 1-based line number
+    pub line: usize,
+    /// Marker such as "@cert-authority" or "@revoked"
+    pub marker: Option<String>,
+    /// Host field as written (comma-separated names, or a hashed "|1|salt|hash")
+    pub hosts: String,
+    pub key_type: String,
+    pub key_base64: String,
+    /// Trailing comment, with runs of whitespace collapsed
+    pub comment: Option<String>,
+}
+
+impl KnownHostEntry {
+    /// Normalized key data: "<key-type> <base64>"
+    pub fn key(&self) -> String {
+        format!("{} {}", self.key_type, self.key_base64)
+    }
+
+    /// Whether the host field is hashed (HashKnownHosts)
+    pub fn is_hashed(&self) -> bool {
+        self.hosts.starts_with("|1|")
+    }
+}
+
+/// Whether a hashed host field ("|1|base64(salt)|base64(hash)") is the hash of `host`
+/// OpenSSH stores HMAC-SHA1(key = salt, message = host name as written in known_hosts)
+fn hashed_host_matches(hashed: &str, host: &str) -> bool {
+    let (salt, hash) = match hashed
+        .strip_prefix("|1|")
+        .and_then(|rest| rest.split_once('|'))
+    {
+        Some(parts) => parts,
+        None => return false,
+    };
+
+    let engine = base64::engine::general_purpose::STANDARD;
+    let (salt, hash) = match (engine.decode(salt), engine.decode(hash)) {
+        (Ok(salt), Ok(hash)) => (salt, hash),
+        _ => return false,
+    };
+
+    let mut mac = match Hmac::<Sha1>::new_from_slice(&salt) {
+        Ok(mac) => mac,
+        Err(_) => return false,
+    };
+    mac.update(host.as_bytes());
+    mac.verify_slice(&hash).is_ok()
+}
+
+/// Whether a known_hosts host field names `host`: one of its comma-separated
+/// names, or the hash of it when the field is hashed
+fn host_field_matches(hosts: &str, host: &str) -> bool {
+    if hosts.starts_with("|1|") {
+        hashed_host_matches(hosts, host)
+    } else {
+        hosts.split(',').any(|name| name.eq_ignore_ascii_case(host))
+    }
+}
+
+/// Host and port of a "[host]:port" name, None for any other name
+fn split_bracketed_host(name: &str) -> Option<(&str, u16)> {
+    let (host, port) = name.strip_prefix('[')?.split_once("]:")?;
+    Some((host, port.parse().ok()?))
+}
+
+/// Hash a host field as `HashKnownHosts yes` does, with a fresh random salt:
+/// "|1|base64(salt)|base64(HMAC-SHA1(key = salt, message = host))"
+fn hash_host(host: &str) -> SshResult<String> {
+    let mut salt = [0u8; 20];
+    OsRng.fill_bytes(&mut salt);
+
+    let mut mac = Hmac::<Sha1>::new_from_slice(&salt).map_err(|e| SshBuddyError::Unknown {
+        message: format!("Failed to hash host name: {}", e),
+    })?;
+    mac.update(host.as_bytes());
+
+    let engine = base64::engine::general_purpose::STANDARD;
+    Ok(format!(
+        "|1|{}|{}",
+        engine.encode(salt),
+        engine.encode(mac.finalize().into_bytes())
+    ))
+}
+
+/// SHA256 fingerprint of a base64 public key blob, or None if it isn't a valid key
+fn blob_fingerprint(base64: &str) -> Option<ssh_key::Fingerprint> {
+    let blob = base64::engine::general_purpose::STANDARD
+        .decode(base64.trim())
+        .ok()?;
+    let public_key = PublicKey::from_bytes(&blob).ok()?;
+    Some(public_key.fingerprint(ssh_key::HashAlg::Sha256))
+}
+
+/// Current official host key fingerprints of Git platforms (SHA256)
+/// Sources: the platforms' published SSH key fingerprint docs
+const PLATFORM_HOST_KEYS: &[(&str, &str, &[&str])] = &[
+    (
+        "github",
+        "github.com",
+        &[
+            "SHA256:+DiY3wvvV6TuJJhbpZisF/zLDA0zPMSvHdkr4UvCOqU",
+            "SHA256:p2QAMXNIC1TJYWeIOttrVc98/R1BUFWu3/LiyKgUfQM",
+            "SHA256:uNiVztksCsDhcc0u9e8BujQXVUpKZIDTMczCvj3tD2s",
+        ],
+    ),
+    (
+        "gitlab",
+        "gitlab.com",
+        &[
+            "SHA256:eUXGGm1YGsMAS7vkcx6JOJdOGHPem5gQp4taiCfCLB8",
+            "SHA256:HbW3g8zUjNSksFbqTiUWPWg2Bq1x8xdGUrliXFzSnUw",
+            "SHA256:ROQFvPThGrW4RuWLoL9tq9I9zJ42fK4XywyRtbOz/EQ",
+        ],
+    ),
+    (
+        "bitbucket",
+        "bitbucket.org",
+        &[
+            "SHA256:ybgmFkzwOSotHTHLJgHO0QN8L0xErw6vd0VhFA9m3SM",
+            "SHA256:FC73VB6C4OQLSCrjEayhMp9UMxS97caD/Yyi2bhW/J0",
+            "SHA256:46OSHA1Rmj8E8ERTC6xkNcmGOw9oFxYr0WF6zWW8l1E",
+        ],
+    ),
+];
+
 /// Known Hosts service
 pub struct KnownHostsService;
 
 impl KnownHostsService {
     /// Get known_hosts file path
     fn get_known_hosts_path() -> SshResult<PathBuf> {
-        let ssh_dir = dirs::home_dir()
-            .ok_or(SshBuddyError::HomeDirNotFound)?
-            .join(".ssh");
-        Ok(ssh_dir.join("known_hosts"))
+        Ok(ssh_dir()?.join("known_hosts"))
+    }
+
+    /// Path of the default known_hosts file, as shown to the user
+    pub fn default_known_hosts_file() -> Option<String> {
+        Self::get_known_hosts_path()
+            .ok()
+            .map(|path| path.to_string_lossy().to_string())
+    }
+
+    /// known_hosts file to edit: the given one, which must be inside ~/.ssh,
+    /// or ~/.ssh/known_hosts when none is given
+    fn resolve_known_hosts_file(file: Option<&str>) -> SshResult<PathBuf> {
+        let default_path = Self::get_known_hosts_path()?;
+        match file {
+            Some(file) if !file.trim().is_empty() => {
+                let ssh_dir = default_path.parent().unwrap_or(Path::new("."));
+                Self::known_hosts_file_in(file, ssh_dir)
+            }
+            _ => Ok(default_path),
+        }
+    }
+
+    /// Resolve a known_hosts file path and check that its directory is within `ssh_dir`
+    /// The file itself may not exist yet
+    fn known_hosts_file_in(file: &str, ssh_dir: &Path) -> SshResult<PathBuf> {
+        let path = expand_path(file)?;
+        let parent = path.parent().ok_or_else(|| SshBuddyError::InvalidPath {
+            message: format!("Invalid known_hosts file: {}", file),
+        })?;
+        validate_path_in_ssh_dir(parent, ssh_dir)?;
+        Ok(path)
+    }
+
+    /// Lock a known_hosts file for a read-modify-write, so concurrent adds and removes
+    /// can't overwrite each other's changes
+    async fn lock_file(path: &Path) -> tokio::sync::OwnedMutexGuard<()> {
+        let lock = FILE_LOCKS
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(path.to_path_buf())
+            .or_default()
+            .clone();
+        lock.lock_owned().await
+    }
+
+    /// Load known_hosts file
+    /// A missing or unreadable file yields an empty set
+    /// Parsing is cached per file and redone only once its modification time or size changes
+    pub async fn load_known_hosts() -> KnownHostKeys {
+        match Self::get_known_hosts_path() {
+            Ok(path) => Self::load_cached(&path).await,
+            Err(_) => KnownHostKeys::new(),
+        }
+    }
+
+    /// Parsed keys of a known_hosts file, from the cache while the file is unchanged
+    async fn load_cached(path: &Path) -> KnownHostKeys {
+        let stamp = fs::metadata(path)
+            .await
+            .ok()
+            .and_then(|metadata| Some((metadata.modified().ok()?, metadata.len())));
+        let cache = PARSE_CACHE.get_or_init(Default::default);
+
+        if let Some(stamp) = stamp {
+            let cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(cached) = cache.get(path) {
+                if cached.stamp == stamp {
+                    return cached.keys.clone();
+                }
+            }
+        }
+
+        let keys = Self::parse_known_hosts(&Self::read_known_hosts_file(path).await);
+        let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+        match stamp {
+            Some(stamp) => {
+                cache.insert(
+                    path.to_path_buf(),
+                    CachedKnownHosts {
+                        stamp,
+                        keys: keys.clone(),
+                    },
+                );
+            }
+            None => {
+                cache.remove(path);
+            }
+        }
+        keys
+    }
+
+    /// Load every host key line of the known_hosts file, hashed entries included
+    pub async fn load_entries() -> Vec<KnownHostEntry> {
+        Self::parse_entries(&Self::read_known_hosts().await)
+    }
+
+    /// Read the known_hosts file; missing or unreadable yields empty content
+    async fn read_known_hosts() -> String {
+        match Self::get_known_hosts_path() {
+            Ok(path) => Self::read_known_hosts_file(&path).await,
+            Err(_) => String::new(),
+        }
+    }
+
+    /// Read a known_hosts file; missing or unreadable yields empty content
+    async fn read_known_hosts_file(known_hosts_path: &Path) -> String {
+        log::info!(
+            "[known_hosts] Loading known_hosts from: {:?}",
+            known_hosts_path
+        );
+
+        if !known_hosts_path.exists() {
+            log::warn!("[known_hosts] known_hosts file does not exist");
+            return String::new();
+        }
+
+        match fs::read_to_string(known_hosts_path).await {
+            Ok(content) => content,
+            Err(e) => {
+                log::error!("[known_hosts] Failed to read known_hosts: {}", e);
+                String::new()
+            }
+        }
+    }
+
+    /// Parse the host key lines of known_hosts content
+    /// Comments, empty and malformed lines are skipped
+    pub fn parse_entries(content: &str) -> Vec<KnownHostEntry> {
+        let mut entries = Vec::new();
+
+        for (idx, line) in content.lines().enumerate() {
+            // Trimming also drops the '\r' of CRLF line endings
+            let line = line.trim();
+            // Skip empty lines and comments
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // Format: [marker] hostname[,hostname2,...] key-type key [comment]
+            // Fields may be separated by any run of spaces or tabs
+            let mut fields = line.split_whitespace().peekable();
+            let marker = match fields.peek() {
+                Some(field) if field.starts_with('@') => fields.next().map(str::to_string),
+                _ => None,
+            };
+
+            let (hosts, key_type, key_base64) = match (fields.next(), fields.next(), fields.next())
+            {
+                (Some(hosts), Some(key_type), Some(key_base64)) => (hosts, key_type, key_base64),
+                _ => {
+                    log::debug!("[known_hosts] Skipping malformed line {}", idx + 1);
+                    continue;
+                }
+            };
+            let comment = fields.collect::<Vec<_>>().join(" ");
+
+            entries.push(KnownHostEntry {
+                line: idx + 1,
+                marker,
+                hosts: hosts.to_string(),
+                key_type: key_type.to_string(),
+                key_base64: key_base64.to_string(),
+                comment: (!comment.is_empty()).then_some(comment),
+            });
+        }
+
+        entries
     }
 
-    /// Remove host from known_hosts
-    pub async fn remove_host(hostname: &str) -> SshResult<RemoveHostResult> {
+    /// Read every line of the known_hosts file with its classification, in file order
+    /// A missing file yields no lines
+    pub async fn read_annotated() -> SshResult<Vec<AnnotatedLine>> {
         let known_hosts_path = Self::get_known_hosts_path()?;
+        if !known_hosts_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&known_hosts_path).await?;
+        Ok(Self::annotate_lines(&content))
+    }
+
+    /// Classify each line of known_hosts content; key lines get their type and fingerprint
+    fn annotate_lines(content: &str) -> Vec<AnnotatedLine> {
+        let entries: HashMap<usize, KnownHostEntry> = Self::parse_entries(content)
+            .into_iter()
+            .map(|entry| (entry.line, entry))
+            .collect();
+
+        content
+            .lines()
+            .enumerate()
+            .map(|(idx, text)| {
+                let mut annotated = AnnotatedLine {
+                    line: idx + 1,
+                    text: text.to_string(),
+                    kind: LineKind::Malformed,
+                    hosts: None,
+                    key_type: None,
+                    fingerprint: None,
+                    warning: None,
+                };
+
+                let trimmed = text.trim();
+                if trimmed.is_empty() {
+                    annotated.kind = LineKind::Blank;
+                    return annotated;
+                }
+                if trimmed.starts_with('#') {
+                    annotated.kind = LineKind::Comment;
+                    return annotated;
+                }
+
+                let entry = match entries.get(&(idx + 1)) {
+                    Some(entry) => entry,
+                    None => {
+                        annotated.warning =
+                            Some("Expected a host field, key type and key".to_string());
+                        return annotated;
+                    }
+                };
+
+                let kind = match entry.marker.as_deref() {
+                    Some("@cert-authority") => LineKind::CertAuthority,
+                    Some("@revoked") => LineKind::Revoked,
+                    Some(marker) => {
+                        annotated.warning = Some(format!("Unknown marker {}", marker));
+                        return annotated;
+                    }
+                    None if entry.is_hashed() => LineKind::Hashed,
+                    None => LineKind::Standard,
+                };
+
+                let public_key = match PublicKey::from_openssh(&entry.key()) {
+                    Ok(public_key) => public_key,
+                    Err(e) => {
+                        annotated.warning = Some(format!("Key does not parse: {}", e));
+                        return annotated;
+                    }
+                };
+
+                annotated.kind = kind;
+                annotated.hosts = Some(entry.hosts.clone());
+                annotated.key_type = Some(public_key.algorithm().as_str().to_string());
+                annotated.fingerprint =
+                    Some(public_key.fingerprint(ssh_key::HashAlg::Sha256).to_string());
+                annotated
+            })
+            .collect()
+    }
+
+    /// Parse known_hosts content into keys per host name
+    /// Hashed entries are kept under their hashed field, since their host names cannot be
+    /// recovered; marker lines (@cert-authority, @revoked) are skipped as they don't trust a host key
+    pub fn parse_known_hosts(content: &str) -> KnownHostKeys {
+        let mut known_hosts = KnownHostKeys::new();
+        let mut parsed_count = 0;
+
+        for entry in Self::parse_entries(content) {
+            if entry.marker.is_some() {
+                continue;
+            }
+            if entry.is_hashed() {
+                let key = entry.key();
+                known_hosts.entry(entry.hosts).or_default().push(key);
+                parsed_count += 1;
+                continue;
+            }
+
+            // There may be multiple hostnames
+            for hostname in entry.hosts.split(',') {
+                let hostname = hostname.trim();
+                known_hosts
+                    .entry(hostname.to_string())
+                    .or_default()
+                    .push(entry.key());
+                parsed_count += 1;
+            }
+        }
+
+        log::info!(
+            "[known_hosts] Parsed {} entries from {} lines",
+            parsed_count,
+            content.lines().count()
+        );
+
+        known_hosts
+    }
+
+    /// Host names a host may be stored under in known_hosts
+    fn host_variants(hostname: &str, port: u16) -> Vec<String> {
+        if port == 22 {
+            vec![hostname.to_string()]
+        } else {
+            vec![format!("[{}]:{}", hostname, port), hostname.to_string()]
+        }
+    }
+
+    /// Host name and port a host's key is stored under
+    /// A HostKeyAlias replaces both, as in OpenSSH, which never adds a port to the alias
+    pub fn entry_target(hostname: &str, port: u16, host_key_alias: Option<&str>) -> (String, u16) {
+        match host_key_alias
+            .map(str::trim)
+            .filter(|alias| !alias.is_empty())
+        {
+            Some(alias) => (alias.to_string(), 22),
+            None => (hostname.to_string(), port),
+        }
+    }
+
+    /// Stored key entries for a host, across all of its host name variants
+    /// and any hashed entries whose hash matches one of them
+    fn stored_keys<'a>(known_hosts: &'a KnownHostKeys, hostname: &str, port: u16) -> Vec<&'a str> {
+        let variants = Self::host_variants(hostname, port);
+
+        let plain = variants
+            .iter()
+            .filter_map(|variant| known_hosts.get(variant))
+            .flatten();
+        let hashed = known_hosts
+            .iter()
+            .filter(|(hosts, _)| hosts.starts_with("|1|"))
+            .filter(|(hosts, _)| {
+                variants
+                    .iter()
+                    .any(|variant| hashed_host_matches(hosts, variant))
+            })
+            .flat_map(|(_, keys)| keys);
+
+        plain.chain(hashed).map(String::as_str).collect()
+    }
+
+    /// Check a server key (base64 blob) against the keys stored for a host
+    pub fn check_host_key(
+        known_hosts: &KnownHostKeys,
+        hostname: &str,
+        port: u16,
+        server_key_base64: &str,
+    ) -> KnownHostStatus {
+        let stored = Self::stored_keys(known_hosts, hostname, port);
+
+        // Compare SHA256 fingerprints of the decoded keys; blobs that don't decode
+        // as public keys can only match byte for byte
+        let server_key_base64 = server_key_base64.trim();
+        let server_fingerprint = blob_fingerprint(server_key_base64);
+        let matches_server_key = |key: &&str| {
+            key.split_once(' ').is_some_and(|(_, base64)| {
+                match (&server_fingerprint, blob_fingerprint(base64)) {
+                    (Some(server), Some(stored)) => *server == stored,
+                    _ => base64 == server_key_base64,
+                }
+            })
+        };
+
+        if stored.iter().any(matches_server_key) {
+            KnownHostStatus::Matched
+        } else if !stored.is_empty() {
+            KnownHostStatus::Changed
+        } else {
+            KnownHostStatus::Unknown
+        }
+    }
+
+    /// Check whether a host is already in known_hosts, without connecting to it
+    pub async fn is_host_known(hostname: &str, port: Option<u16>) -> SshResult<KnownHostStatusDto> {
+        let port = port.unwrap_or(22);
+        let known_hosts = Self::load_known_hosts().await;
+        Ok(Self::host_status(&known_hosts, hostname, port))
+    }
+
+    /// Build the offline trust status of a host from loaded known_hosts keys
+    fn host_status(known_hosts: &KnownHostKeys, hostname: &str, port: u16) -> KnownHostStatusDto {
+        let stored = Self::stored_keys(known_hosts, hostname, port);
+
+        let mut fingerprints: Vec<String> = Vec::new();
+        for key in &stored {
+            if let Ok(pub_key) = PublicKey::from_openssh(key) {
+                let fingerprint = pub_key.fingerprint(ssh_key::HashAlg::Sha256).to_string();
+                if !fingerprints.contains(&fingerprint) {
+                    fingerprints.push(fingerprint);
+                }
+            }
+        }
+
+        KnownHostStatusDto {
+            hostname: hostname.to_string(),
+            port,
+            known: !stored.is_empty(),
+            fingerprints,
+        }
+    }
+
+    /// Compare stored Git platform host keys against the bundled official fingerprints
+    pub async fn check_platform_keys() -> SshResult<Vec<PlatformKeyStatus>> {
+        let known_hosts = Self::load_known_hosts().await;
+        Ok(Self::platform_key_statuses(&known_hosts))
+    }
+
+    /// Per-platform host key status for loaded known_hosts keys
+    fn platform_key_statuses(known_hosts: &KnownHostKeys) -> Vec<PlatformKeyStatus> {
+        PLATFORM_HOST_KEYS
+            .iter()
+            .map(|(platform, hostname, official)| {
+                let stored = Self::stored_keys(known_hosts, hostname, 22);
+                let stale_fingerprints: Vec<String> = stored
+                    .iter()
+                    .filter_map(|key| PublicKey::from_openssh(key).ok())
+                    .map(|key| key.fingerprint(ssh_key::HashAlg::Sha256).to_string())
+                    .filter(|fingerprint| !official.contains(&fingerprint.as_str()))
+                    .collect();
+
+                let status = if stored.is_empty() {
+                    PlatformKeyState::Missing
+                } else if !stale_fingerprints.is_empty() {
+                    PlatformKeyState::Outdated
+                } else {
+                    PlatformKeyState::UpToDate
+                };
+
+                PlatformKeyStatus {
+                    platform: platform.to_string(),
+                    hostname: hostname.to_string(),
+                    status,
+                    stale_fingerprints,
+                }
+            })
+            .collect()
+    }
+
+    /// Remove host from a known_hosts file (~/.ssh/known_hosts by default)
+    /// With `port`, only that port's entries go: `[host]:port`, or bare `host` for 22
+    pub async fn remove_host(
+        hostname: &str,
+        port: Option<u16>,
+        known_hosts_file: Option<&str>,
+    ) -> SshResult<RemoveHostResult> {
+        let known_hosts_path = Self::resolve_known_hosts_file(known_hosts_file)?;
+        let _file_guard = Self::lock_file(&known_hosts_path).await;
 
         if !known_hosts_path.exists() {
             return Ok(RemoveHostResult {
@@ -39,11 +663,86 @@ impl KnownHostsService {
                     message: format!("Failed to read known_hosts: {}", e),
                 })?;
 
-        // Filter out matching lines
+        let (new_content, removed_count) = Self::remove_host_lines(&content, hostname, port);
+
+        // Write back to file
+        let mode = existing_mode_or(&known_hosts_path, 0o644);
+        atomic_write(&known_hosts_path, new_content.as_bytes(), mode)
+            .await
+            .map_err(|e| SshBuddyError::IoError {
+                message: format!("Failed to write known_hosts: {}", e),
+            })?;
+
+        Ok(RemoveHostResult {
+            success: true,
+            message: if removed_count > 0 {
+                format!("Removed {} entries for {}", removed_count, hostname)
+            } else {
+                format!("No entries found for {}", hostname)
+            },
+            removed_count,
+        })
+    }
+
+    /// Rename a host in ~/.ssh/known_hosts, keeping its keys and comments
+    /// Entries for `port` are matched in the `[host]:port` form unless it is 22;
+    /// hashed entries are skipped, as their names cannot be read back
+    /// Returns the number of entries updated
+    pub async fn relabel_host(
+        old_hostname: &str,
+        new_hostname: &str,
+        port: Option<u16>,
+    ) -> SshResult<usize> {
+        validate_hostname(old_hostname)?;
+        validate_hostname(new_hostname)?;
+
+        let known_hosts_path = Self::get_known_hosts_path()?;
+        let _file_guard = Self::lock_file(&known_hosts_path).await;
+        if !known_hosts_path.exists() {
+            return Ok(0);
+        }
+
+        let content = fs::read_to_string(&known_hosts_path).await?;
+        let (new_content, updated) =
+            Self::relabel_content(&content, old_hostname, new_hostname, port.unwrap_or(22));
+
+        if updated > 0 {
+            let mode = existing_mode_or(&known_hosts_path, 0o644);
+            atomic_write(&known_hosts_path, new_content.as_bytes(), mode).await?;
+        }
+        log::info!(
+            "[known_hosts] Relabeled {} entries from {} to {}",
+            updated,
+            old_hostname,
+            new_hostname
+        );
+        Ok(updated)
+    }
+
+    /// Drop the lines of known_hosts content naming `hostname`; returns the new
+    /// content and the number of lines removed
+    /// With `port`, or a `hostname` written as "[host]:port", names match only as
+    /// `[hostname]:port` (or bare `hostname` for 22), hashed or not
+    /// Without either, plain entries match on any port, but a hashed entry only
+    /// matches the bare name since its port can't be read back from the hash
+    fn remove_host_lines(content: &str, hostname: &str, port: Option<u16>) -> (String, usize) {
+        let (hostname, port) = match (split_bracketed_host(hostname), port) {
+            (Some((host, named_port)), None) => (host, Some(named_port)),
+            _ => (hostname, port),
+        };
         let hostname_lower = hostname.to_lowercase();
+        let port_field = port.map(|port| {
+            if port == 22 {
+                hostname_lower.clone()
+            } else {
+                format!("[{}]:{}", hostname_lower, port)
+            }
+        });
         let mut removed_count = 0;
         let new_lines: Vec<&str> = content
             .lines()
+            // Stray carriage returns (a file edited on Windows) would end up in the host field
+            .map(|line| line.trim_end_matches('\r'))
             .filter(|line| {
                 let line_trimmed = line.trim();
                 if line_trimmed.is_empty() || line_trimmed.starts_with('#') {
@@ -55,20 +754,27 @@ impl KnownHostsService {
                 // or hashed format: |1|base64|base64 key-type key
                 let first_field = line_trimmed.split_whitespace().next().unwrap_or("");
 
-                // Check if hashed format
-                if first_field.starts_with("|1|") {
-                    // Cannot directly match hashed entries, needs special handling
-                    // We keep it since we cannot determine if it matches
-                    return true;
-                }
-
-                // Check hostname list
-                let hostnames: Vec<&str> = first_field.split(',').collect();
-                let matches = hostnames.iter().any(|h| {
-                    let h_clean = h.trim_start_matches('[').split(':').next().unwrap_or(h);
-                    h_clean.to_lowercase() == hostname_lower
-                        || h_clean.to_lowercase().contains(&hostname_lower)
-                });
+                let matches = if let Some(ref port_field) = port_field {
+                    if first_field.starts_with("|1|") {
+                        hashed_host_matches(first_field, port_field)
+                    } else {
+                        first_field.split(',').any(|h| {
+                            let h = h.to_lowercase();
+                            h == *port_field
+                                || (port == Some(22) && h == format!("[{}]:22", hostname_lower))
+                        })
+                    }
+                } else if first_field.starts_with("|1|") {
+                    hashed_host_matches(first_field, hostname)
+                } else {
+                    // Check hostname list
+                    let hostnames: Vec<&str> = first_field.split(',').collect();
+                    hostnames.iter().any(|h| {
+                        let h_clean = h.trim_start_matches('[').split(':').next().unwrap_or(h);
+                        h_clean.to_lowercase() == hostname_lower
+                            || h_clean.to_lowercase().contains(&hostname_lower)
+                    })
+                };
 
                 if matches {
                     removed_count += 1;
@@ -79,103 +785,834 @@ impl KnownHostsService {
             })
             .collect();
 
-        // Write back to file
-        let new_content = new_lines.join("\n");
-        fs::write(&known_hosts_path, new_content)
-            .await
-            .map_err(|e| SshBuddyError::IoError {
-                message: format!("Failed to write known_hosts: {}", e),
-            })?;
-
-        Ok(RemoveHostResult {
-            success: true,
-            message: if removed_count > 0 {
-                format!("Removed {} entries for {}", removed_count, hostname)
-            } else {
-                format!("No entries found for {}", hostname)
-            },
-            removed_count,
-        })
+        (new_lines.join("\n"), removed_count)
     }
 
-    /// Scan and add host's SSH public key to known_hosts
-    pub async fn add_host(hostname: &str, port: Option<u16>) -> SshResult<AddHostResult> {
-        let port = port.unwrap_or(22);
+    /// Rewrite ~/.ssh/known_hosts with LF line endings if it has CRLF or stray '\r'
+    /// Returns the number of lines fixed
+    pub async fn normalize_line_endings() -> SshResult<usize> {
         let known_hosts_path = Self::get_known_hosts_path()?;
+        let _file_guard = Self::lock_file(&known_hosts_path).await;
+        if !known_hosts_path.exists() {
+            return Ok(0);
+        }
 
-        // Connect to host and retrieve host key
-        let host_keys = Self::scan_host_keys(hostname, port).await?;
+        let content = fs::read_to_string(&known_hosts_path).await?;
+        let (new_content, fixed) = Self::strip_carriage_returns(&content);
 
-        if host_keys.is_empty() {
-            return Ok(AddHostResult {
-                success: false,
-                message: format!("Could not retrieve host keys from {}:{}", hostname, port),
-                keys_added: 0,
-            });
+        if fixed > 0 {
+            let mode = existing_mode_or(&known_hosts_path, 0o644);
+            atomic_write(&known_hosts_path, new_content.as_bytes(), mode).await?;
         }
+        log::info!("[known_hosts] Fixed line endings of {} lines", fixed);
+        Ok(fixed)
+    }
 
-        // Ensure known_hosts file exists
-        let mut existing_content = if known_hosts_path.exists() {
-            fs::read_to_string(&known_hosts_path)
-                .await
-                .unwrap_or_default()
-        } else {
-            // Ensure .ssh directory exists
-            if let Some(parent) = known_hosts_path.parent() {
-                fs::create_dir_all(parent).await.ok();
-            }
-            String::new()
-        };
+    /// Remove every '\r' from content; returns the new content and the lines that had one
+    fn strip_carriage_returns(content: &str) -> (String, usize) {
+        let mut fixed = 0;
+        let lines: Vec<String> = content
+            .split('\n')
+            .map(|line| {
+                if line.contains('\r') {
+                    fixed += 1;
+                    line.replace('\r', "")
+                } else {
+                    line.to_string()
+                }
+            })
+            .collect();
+        (lines.join("\n"), fixed)
+    }
 
-        // Add new host keys
-        let mut keys_added = 0;
-        for key in &host_keys {
-            // Check if already exists
-            let entry = if port == 22 {
-                format!("{} {}", hostname, key)
-            } else {
-                format!("[{}]:{} {}", hostname, port, key)
-            };
+    /// Find repeated entries in ~/.ssh/known_hosts: lines with the same host field and
+    /// key, and host names stored with several different keys of one algorithm
+    pub async fn find_duplicates() -> SshResult<KnownHostsDuplicates> {
+        let known_hosts_path = Self::get_known_hosts_path()?;
+        let content = Self::read_known_hosts_file(&known_hosts_path).await;
+        Ok(Self::duplicates_in(&Self::parse_entries(&content)))
+    }
 
-            if !existing_content.contains(&entry) {
-                if !existing_content.is_empty() && !existing_content.ends_with('\n') {
-                    existing_content.push('\n');
+    fn duplicates_in(entries: &[KnownHostEntry]) -> KnownHostsDuplicates {
+        let mut exact: Vec<DuplicateEntries> = Vec::new();
+        let mut seen: HashMap<(Option<&str>, String, String), usize> = HashMap::new();
+        for entry in entries {
+            let identity = (
+                entry.marker.as_deref(),
+                entry.hosts.to_lowercase(),
+                entry.key(),
+            );
+            match seen.get(&identity) {
+                Some(&group) => exact[group].lines.push(entry.line),
+                None => {
+                    seen.insert(identity, exact.len());
+                    exact.push(DuplicateEntries {
+                        hosts: entry.hosts.clone(),
+                        key_type: entry.key_type.clone(),
+                        fingerprint: blob_fingerprint(&entry.key_base64).map(|f| f.to_string()),
+                        lines: vec![entry.line],
+                    });
                 }
-                existing_content.push_str(&entry);
-                existing_content.push('\n');
-                keys_added += 1;
             }
         }
+        exact.retain(|group| group.lines.len() > 1);
 
-        // Write back to file
-        fs::write(&known_hosts_path, &existing_content)
-            .await
-            .map_err(|e| SshBuddyError::IoError {
-                message: format!("Failed to write known_hosts: {}", e),
-            })?;
+        // Hashed names can't be read back and markers aren't plain host keys
+        let mut conflicting: Vec<ConflictingHostKeys> = Vec::new();
+        let mut distinct_keys: Vec<Vec<&str>> = Vec::new();
+        let mut by_host: HashMap<(String, &str), usize> = HashMap::new();
+        for entry in entries
+            .iter()
+            .filter(|entry| entry.marker.is_none() && !entry.is_hashed())
+        {
+            let mut names: Vec<String> = Vec::new();
+            for name in entry.hosts.split(',').map(str::to_lowercase) {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            for name in names {
+                let key = (name.clone(), entry.key_type.as_str());
+                let group = *by_host.entry(key).or_insert_with(|| {
+                    conflicting.push(ConflictingHostKeys {
+                        host: name,
+                        key_type: entry.key_type.clone(),
+                        fingerprints: Vec::new(),
+                        lines: Vec::new(),
+                    });
+                    distinct_keys.push(Vec::new());
+                    conflicting.len() - 1
+                });
+                conflicting[group].lines.push(entry.line);
+                if !distinct_keys[group].contains(&entry.key_base64.as_str()) {
+                    distinct_keys[group].push(&entry.key_base64);
+                    conflicting[group].fingerprints.push(
+                        blob_fingerprint(&entry.key_base64)
+                            .map(|f| f.to_string())
+                            .unwrap_or_else(|| "invalid key".to_string()),
+                    );
+                }
+            }
+        }
+        let conflicting = conflicting
+            .into_iter()
+            .zip(distinct_keys)
+            .filter(|(_, keys)| keys.len() > 1)
+            .map(|(group, _)| group)
+            .collect();
+
+        KnownHostsDuplicates { exact, conflicting }
+    }
+
+    /// Remove exact duplicate entries from ~/.ssh/known_hosts, keeping the first of
+    /// each and the order of everything else
+    /// Returns the number of lines removed
+    pub async fn dedupe() -> SshResult<usize> {
+        let known_hosts_path = Self::get_known_hosts_path()?;
+        let _file_guard = Self::lock_file(&known_hosts_path).await;
+        if !known_hosts_path.exists() {
+            return Ok(0);
+        }
+
+        let content = fs::read_to_string(&known_hosts_path).await?;
+        let (new_content, removed) = Self::dedupe_content(&content);
+
+        if removed > 0 {
+            let mode = existing_mode_or(&known_hosts_path, 0o644);
+            atomic_write(&known_hosts_path, new_content.as_bytes(), mode).await?;
+        }
+        log::info!("[known_hosts] Removed {} duplicate entries", removed);
+        Ok(removed)
+    }
+
+    /// Drop the entries of known_hosts content repeating an earlier host field and key
+    /// Comments are not compared; returns the new content and the lines removed
+    fn dedupe_content(content: &str) -> (String, usize) {
+        let duplicate_lines: std::collections::HashSet<usize> =
+            Self::duplicates_in(&Self::parse_entries(content))
+                .exact
+                .iter()
+                .flat_map(|group| group.lines.iter().skip(1).copied())
+                .collect();
+        if duplicate_lines.is_empty() {
+            return (content.to_string(), 0);
+        }
+
+        let mut new_content = content
+            .lines()
+            .enumerate()
+            .filter(|(idx, _)| !duplicate_lines.contains(&(idx + 1)))
+            .map(|(_, line)| line)
+            .collect::<Vec<_>>()
+            .join("\n");
+        if content.ends_with('\n') {
+            new_content.push('\n');
+        }
+        (new_content, duplicate_lines.len())
+    }
+
+    /// Rewrite the host fields naming `old_hostname` to `new_hostname`, leaving the
+    /// rest of every line untouched; returns the new content and the lines changed
+    fn relabel_content(
+        content: &str,
+        old_hostname: &str,
+        new_hostname: &str,
+        port: u16,
+    ) -> (String, usize) {
+        let host_field = |host: &str| {
+            if port == 22 {
+                host.to_string()
+            } else {
+                format!("[{}]:{}", host, port)
+            }
+        };
+        let old_field = host_field(old_hostname);
+        let new_field = host_field(new_hostname);
+
+        let mut updated = 0;
+        let mut lines: Vec<String> = Vec::new();
+        for line in content.lines() {
+            // Byte range of the host field, after any @marker
+            let mut start = line.len() - line.trim_start().len();
+            if line[start..].starts_with('@') {
+                let marker_end = line[start..]
+                    .find(char::is_whitespace)
+                    .map_or(line.len(), |i| start + i);
+                start = line.len() - line[marker_end..].trim_start().len();
+            }
+            let end = line[start..]
+                .find(char::is_whitespace)
+                .map_or(line.len(), |i| start + i);
+            let hosts = &line[start..end];
+
+            if hosts.is_empty() || hosts.starts_with('#') || hosts.starts_with("|1|") {
+                lines.push(line.to_string());
+                continue;
+            }
+
+            let mut names: Vec<&str> = Vec::new();
+            let mut renamed = false;
+            for name in hosts.split(',') {
+                let name = if name.eq_ignore_ascii_case(&old_field) {
+                    renamed = true;
+                    new_field.as_str()
+                } else {
+                    name
+                };
+                if !names.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+                    names.push(name);
+                }
+            }
+
+            if renamed {
+                updated += 1;
+                lines.push(format!(
+                    "{}{}{}",
+                    &line[..start],
+                    names.join(","),
+                    &line[end..]
+                ));
+            } else {
+                lines.push(line.to_string());
+            }
+        }
+
+        let mut new_content = lines.join("\n");
+        if content.ends_with('\n') {
+            new_content.push('\n');
+        }
+        (new_content, updated)
+    }
+
+    /// Scan and add host's SSH public key to a known_hosts file (~/.ssh/known_hosts by default)
+    /// `timeout_secs` bounds the wait for the banner and for ssh-keyscan (5s by default)
+    pub async fn add_host(
+        hostname: &str,
+        port: Option<u16>,
+        known_hosts_file: Option<&str>,
+        hash_hostnames: bool,
+        timeout_secs: Option<u64>,
+        host_key_alias: Option<&str>,
+    ) -> SshResult<AddHostResult> {
+        let port = port.unwrap_or(22);
+        let known_hosts_path = Self::resolve_known_hosts_file(known_hosts_file)?;
+        if let Some(alias) = host_key_alias {
+            validate_hostname(alias)?;
+        }
+
+        // Connect to host and retrieve host key
+        let (host_keys, attempts) =
+            Self::scan_host_keys(hostname, port, Self::scan_timeout_secs(timeout_secs)).await?;
+
+        // Only trust keys that actually parse as public keys
+        let (valid_keys, keys_rejected) = Self::validate_scanned_keys(&host_keys);
+        if keys_rejected > 0 {
+            log::warn!(
+                "[known_hosts] Rejected {} malformed key(s) scanned from {}:{}",
+                keys_rejected,
+                hostname,
+                port
+            );
+        }
+
+        if valid_keys.is_empty() {
+            return Ok(AddHostResult {
+                success: false,
+                message: format!(
+                    "All {} key(s) scanned from {}:{} were malformed",
+                    keys_rejected, hostname, port
+                ),
+                keys_added: 0,
+                keys_rejected,
+            });
+        }
+
+        // Scanned from the real host, stored under its HostKeyAlias if it has one
+        let (entry_host, entry_port) = Self::entry_target(hostname, port, host_key_alias);
+        let keys_added = Self::append_host_keys(
+            &known_hosts_path,
+            &entry_host,
+            entry_port,
+            &valid_keys,
+            hash_hostnames,
+        )
+        .await?;
+
+        let mut message = if keys_rejected > 0 {
+            format!(
+                "Added {} key(s) for {}, rejected {} malformed key(s)",
+                keys_added, hostname, keys_rejected
+            )
+        } else {
+            format!("Added {} key(s) for {}", keys_added, hostname)
+        };
+        if attempts > 1 {
+            message.push_str(&format!(" (succeeded after {} attempts)", attempts));
+        }
 
         Ok(AddHostResult {
             success: true,
-            message: format!("Added {} key(s) for {}", keys_added, hostname),
+            message,
             keys_added,
+            keys_rejected,
         })
     }
 
-    /// Scan host's SSH public keys (similar to ssh-keyscan)
-    async fn scan_host_keys(hostname: &str, port: u16) -> SshResult<Vec<String>> {
-        let addr = format!("{}:{}", hostname, port);
-
-        // Parse address
-        let socket_addr = addr
-            .to_socket_addrs()
-            .map_err(|e| SshBuddyError::DnsResolutionFailed {
-                hostname: format!("{}: {}", hostname, e),
-            })?
-            .next()
-            .ok_or_else(|| SshBuddyError::DnsResolutionFailed {
+    /// Add a host only if one of its scanned keys has the expected SHA256 fingerprint,
+    /// obtained out-of-band; only that key is written to ~/.ssh/known_hosts
+    /// Fails with HostKeyMismatch when no key matches
+    pub async fn add_host_verified(
+        hostname: &str,
+        port: Option<u16>,
+        expected_sha256: &str,
+    ) -> SshResult<VerifiedHostResult> {
+        let port = port.unwrap_or(22);
+        let known_hosts_path = Self::resolve_known_hosts_file(None)?;
+
+        let (host_keys, _) =
+            Self::scan_host_keys(hostname, port, DEFAULT_SCAN_TIMEOUT_SECS).await?;
+        let scanned = Self::scanned_keys(&Self::validate_scanned_keys(&host_keys).0);
+
+        let matched_key = match Self::matching_key(&scanned, expected_sha256) {
+            Some(key) => key.clone(),
+            None => {
+                log::warn!(
+                    "[known_hosts] {}:{} offered {:?}, none matching {}",
+                    hostname,
+                    port,
+                    scanned.iter().map(|k| &k.fingerprint).collect::<Vec<_>>(),
+                    expected_sha256
+                );
+                return Err(SshBuddyError::HostKeyMismatch {
+                    hostname: hostname.to_string(),
+                    expected: expected_sha256.trim().to_string(),
+                });
+            }
+        };
+
+        let keys_added = Self::append_host_keys(
+            &known_hosts_path,
+            hostname,
+            port,
+            std::slice::from_ref(&matched_key.key),
+            false,
+        )
+        .await?;
+
+        Ok(VerifiedHostResult {
+            hostname: hostname.to_string(),
+            port,
+            matched_key,
+            key_added: keys_added > 0,
+        })
+    }
+
+    /// Check the host's keys against the SSHFP records published in DNS (RFC 4255)
+    /// No SSHFP records is reported as such without contacting the server;
+    /// `dnssec_signed` is set when every record was proven secure, see `SshfpAnswer`
+    pub async fn verify_sshfp(hostname: &str, port: Option<u16>) -> SshResult<SshfpVerification> {
+        validate_hostname(hostname)?;
+        let port = port.unwrap_or(22);
+
+        let answer = lookup_sshfp(hostname).await?;
+        log::info!(
+            "[known_hosts] {} has {} SSHFP records (DNSSEC: {})",
+            hostname,
+            answer.records.len(),
+            answer.authenticated
+        );
+        if answer.records.is_empty() {
+            return Ok(SshfpVerification {
                 hostname: hostname.to_string(),
+                port,
+                status: SshfpStatus::NoRecords,
+                dnssec_signed: answer.authenticated,
+                record_count: 0,
+                keys: Vec::new(),
+            });
+        }
+
+        let (host_keys, _) =
+            Self::scan_host_keys(hostname, port, DEFAULT_SCAN_TIMEOUT_SECS).await?;
+        let scanned = Self::scanned_keys(&Self::validate_scanned_keys(&host_keys).0);
+        Ok(Self::sshfp_verification(
+            hostname,
+            port,
+            &answer.records,
+            answer.authenticated,
+            &scanned,
+        ))
+    }
+
+    fn sshfp_verification(
+        hostname: &str,
+        port: u16,
+        records: &[SshfpRecord],
+        dnssec_signed: bool,
+        scanned: &[ScannedKey],
+    ) -> SshfpVerification {
+        let keys: Vec<SshfpKeyResult> = scanned
+            .iter()
+            .map(|key| SshfpKeyResult {
+                key_type: key.key_type.clone(),
+                fingerprint: key.fingerprint.clone(),
+                status: Self::sshfp_match(records, &key.key),
+            })
+            .collect();
+
+        let status = if keys.iter().any(|k| k.status == SshfpMatch::Mismatch) {
+            SshfpStatus::Mismatch
+        } else if keys.iter().any(|k| k.status == SshfpMatch::Match) {
+            SshfpStatus::Verified
+        } else {
+            SshfpStatus::NoMatchingAlgorithm
+        };
+        if status == SshfpStatus::Mismatch {
+            log::warn!(
+                "[known_hosts] {}:{} presented a key not matching its SSHFP records",
+                hostname,
+                port
+            );
+        }
+
+        SshfpVerification {
+            hostname: hostname.to_string(),
+            port,
+            status,
+            dnssec_signed,
+            record_count: records.len(),
+            keys,
+        }
+    }
+
+    /// How a scanned "<type> <base64>" key compares to the records for its algorithm
+    /// Records with an unknown fingerprint type are ignored
+    fn sshfp_match(records: &[SshfpRecord], key: &str) -> SshfpMatch {
+        let Ok(public_key) = PublicKey::from_openssh(key) else {
+            return SshfpMatch::NoRecord;
+        };
+        let algorithm = match public_key.algorithm() {
+            ssh_key::Algorithm::Rsa { .. } => 1,
+            ssh_key::Algorithm::Dsa => 2,
+            ssh_key::Algorithm::Ecdsa { .. } => 3,
+            ssh_key::Algorithm::Ed25519 => 4,
+            _ => return SshfpMatch::NoRecord,
+        };
+        let Ok(blob) = public_key.to_bytes() else {
+            return SshfpMatch::NoRecord;
+        };
+
+        let mut compared = false;
+        for record in records.iter().filter(|r| r.algorithm == algorithm) {
+            let digest = match record.fingerprint_type {
+                1 => Sha1::digest(&blob).to_vec(),
+                2 => public_key
+                    .fingerprint(ssh_key::HashAlg::Sha256)
+                    .as_bytes()
+                    .to_vec(),
+                _ => continue,
+            };
+            if digest == record.fingerprint {
+                return SshfpMatch::Match;
+            }
+            compared = true;
+        }
+        if compared {
+            SshfpMatch::Mismatch
+        } else {
+            SshfpMatch::NoRecord
+        }
+    }
+
+    /// Scanned key whose fingerprint is `expected`, given with or without the "SHA256:" prefix
+    fn matching_key<'a>(keys: &'a [ScannedKey], expected: &str) -> Option<&'a ScannedKey> {
+        let normalize = |fingerprint: &str| {
+            let fingerprint = fingerprint.trim();
+            fingerprint
+                .strip_prefix("SHA256:")
+                .unwrap_or(fingerprint)
+                .trim_end_matches('=')
+                .to_string()
+        };
+
+        let expected = normalize(expected);
+        if expected.is_empty() {
+            return None;
+        }
+        keys.iter()
+            .find(|key| normalize(&key.fingerprint) == expected)
+    }
+
+    /// Scan every host of a small IPv4 CIDR (e.g. "10.0.0.0/28") or comma-separated
+    /// list, without writing anything to known_hosts
+    /// Hosts are scanned concurrently, a few at a time; unreachable hosts get no keys
+    /// `on_result` is called as each host finishes, failed ones included
+    pub async fn scan_range<F>(
+        cidr_or_list: &str,
+        port: Option<u16>,
+        on_result: F,
+    ) -> SshResult<Vec<(String, Vec<ScannedKey>)>>
+    where
+        F: Fn(HostScanProgress) + Send + Sync + 'static,
+    {
+        let port = port.unwrap_or(22);
+        let hosts = Self::expand_host_range(cidr_or_list)?;
+        let total = hosts.len();
+        log::info!("[known_hosts] Scanning {} host(s) on port {}", total, port);
+
+        let permits = Arc::new(tokio::sync::Semaphore::new(RANGE_SCAN_CONCURRENCY));
+        let completed = Arc::new(AtomicUsize::new(0));
+        let on_result = Arc::new(on_result);
+        let tasks: Vec<_> = hosts
+            .into_iter()
+            .map(|host| {
+                let permits = permits.clone();
+                let completed = completed.clone();
+                let on_result = on_result.clone();
+                tokio::spawn(async move {
+                    let _permit = permits.acquire_owned().await;
+                    let (keys, error) =
+                        match Self::scan_with_keyscan(&host, port, DEFAULT_SCAN_TIMEOUT_SECS).await
+                        {
+                            Ok(keys) => (
+                                Self::scanned_keys(&Self::validate_scanned_keys(&keys).0),
+                                None,
+                            ),
+                            Err(e) => {
+                                log::warn!("[known_hosts] Scan of {} failed: {}", host, e);
+                                (Vec::new(), Some(e.to_string()))
+                            }
+                        };
+                    on_result(HostScanProgress {
+                        host: host.clone(),
+                        keys: keys.clone(),
+                        error,
+                        completed: completed.fetch_add(1, Ordering::SeqCst) + 1,
+                        total,
+                    });
+                    (host, keys)
+                })
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            results.push(task.await.map_err(|e| SshBuddyError::IoError {
+                message: format!("Scan task failed: {}", e),
+            })?);
+        }
+        Ok(results)
+    }
+
+    /// Hosts named by a comma-separated list whose items are hostnames or IPv4 CIDRs
+    /// Network and broadcast addresses are left out of CIDRs larger than /31
+    fn expand_host_range(cidr_or_list: &str) -> SshResult<Vec<String>> {
+        let invalid = |message: String| SshBuddyError::InvalidPath { message };
+
+        let mut hosts = Vec::new();
+        for item in cidr_or_list
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+        {
+            match item.split_once('/') {
+                Some((addr, prefix)) => {
+                    let addr: Ipv4Addr = addr.parse().map_err(|_| {
+                        invalid(format!("Only IPv4 ranges are supported: {}", item))
+                    })?;
+                    let prefix: u32 = match prefix.parse() {
+                        Ok(prefix) if prefix <= 32 => prefix,
+                        _ => return Err(invalid(format!("Invalid prefix length: {}", item))),
+                    };
+                    let size = 1u64 << (32 - prefix);
+                    if size as usize > MAX_RANGE_HOSTS + 2 {
+                        return Err(invalid(format!(
+                            "{} covers {} addresses; at most {} hosts can be scanned at once",
+                            item, size, MAX_RANGE_HOSTS
+                        )));
+                    }
+                    let network = u32::from(addr) & !((size - 1) as u32);
+                    let (first, last) = if size > 2 {
+                        (1, size - 2)
+                    } else {
+                        (0, size - 1)
+                    };
+                    for offset in first..=last {
+                        hosts.push(Ipv4Addr::from(network + offset as u32).to_string());
+                    }
+                }
+                None => {
+                    validate_hostname(item)?;
+                    hosts.push(item.to_string());
+                }
+            }
+            if hosts.len() > MAX_RANGE_HOSTS {
+                return Err(invalid(format!(
+                    "At most {} hosts can be scanned at once",
+                    MAX_RANGE_HOSTS
+                )));
+            }
+        }
+
+        if hosts.is_empty() {
+            return Err(invalid("No hosts to scan".to_string()));
+        }
+        Ok(hosts)
+    }
+
+    /// Describe validated "<type> <base64>" entries for display
+    fn scanned_keys(keys: &[String]) -> Vec<ScannedKey> {
+        keys.iter()
+            .filter_map(|key| {
+                let public_key = PublicKey::from_openssh(key).ok()?;
+                Some(ScannedKey {
+                    key_type: public_key.algorithm().as_str().to_string(),
+                    key: key.clone(),
+                    fingerprint: public_key.fingerprint(ssh_key::HashAlg::Sha256).to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Split scanned "<type> <base64>" entries into those that parse as
+    /// public keys and a count of the ones that don't
+    fn validate_scanned_keys(keys: &[String]) -> (Vec<String>, usize) {
+        let mut valid = Vec::new();
+        let mut rejected = 0;
+        for key in keys {
+            if PublicKey::from_openssh(&format!("{} comment", key.trim())).is_ok() {
+                valid.push(key.trim().to_string());
+            } else {
+                rejected += 1;
+            }
+        }
+        (valid, rejected)
+    }
+
+    /// Append host keys to a known_hosts file, skipping entries already present
+    /// Returns the number of keys added
+    async fn append_host_keys(
+        known_hosts_path: &Path,
+        hostname: &str,
+        port: u16,
+        host_keys: &[String],
+        hash_hostnames: bool,
+    ) -> SshResult<usize> {
+        let _file_guard = Self::lock_file(known_hosts_path).await;
+
+        // Ensure known_hosts file exists
+        let mut existing_content = if known_hosts_path.exists() {
+            fs::read_to_string(known_hosts_path)
+                .await
+                .unwrap_or_default()
+        } else {
+            // Ensure .ssh directory exists
+            if let Some(parent) = known_hosts_path.parent() {
+                fs::create_dir_all(parent).await.ok();
+            }
+            String::new()
+        };
+
+        // Add new host keys
+        let host_field = if port == 22 {
+            hostname.to_string()
+        } else {
+            format!("[{}]:{}", hostname, port)
+        };
+        let mut keys_added = 0;
+        for key in host_keys {
+            // Check if already exists, hashed or not; a hashed entry gets a new salt
+            // each time, so entries are compared by their host field instead of by text
+            let already_present = Self::parse_entries(&existing_content)
+                .iter()
+                .any(|entry| entry.key() == *key && host_field_matches(&entry.hosts, &host_field));
+            let entry = if hash_hostnames {
+                format!("{} {}", hash_host(&host_field)?, key)
+            } else {
+                format!("{} {}", host_field, key)
+            };
+
+            if !already_present {
+                if !existing_content.is_empty() && !existing_content.ends_with('\n') {
+                    existing_content.push('\n');
+                }
+                existing_content.push_str(&entry);
+                existing_content.push('\n');
+                keys_added += 1;
+            }
+        }
+
+        // Write back to file
+        let mode = existing_mode_or(known_hosts_path, 0o644);
+        atomic_write(known_hosts_path, existing_content.as_bytes(), mode)
+            .await
+            .map_err(|e| SshBuddyError::IoError {
+                message: format!("Failed to write known_hosts: {}", e),
             })?;
 
+        Ok(keys_added)
+    }
+
+    /// Requested scan timeout, defaulted and kept within 1..=60 seconds
+    fn scan_timeout_secs(requested: Option<u64>) -> u64 {
+        requested
+            .unwrap_or(DEFAULT_SCAN_TIMEOUT_SECS)
+            .clamp(1, MAX_SCAN_TIMEOUT_SECS)
+    }
+
+    /// Scan host's SSH public keys (similar to ssh-keyscan)
+    /// Transient network failures are retried with exponential backoff
+    /// Returns the keys and the number of attempts it took to reach the host;
+    /// a host that answers but yields no keys fails with NoHostKeys
+    async fn scan_host_keys(
+        hostname: &str,
+        port: u16,
+        timeout_secs: u64,
+    ) -> SshResult<(Vec<String>, u32)> {
+        let mut attempt = 1;
+        loop {
+            match Self::probe_ssh_server(hostname, port, timeout_secs).await {
+                Ok(()) => break,
+                Err(e) if attempt < MAX_SCAN_ATTEMPTS && Self::is_transient_error(&e) => {
+                    let delay = Self::scan_backoff(attempt);
+                    log::warn!(
+                        "[known_hosts] Attempt {} to reach {}:{} failed ({}), retrying in {:?}",
+                        attempt,
+                        hostname,
+                        port,
+                        e,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        // Use ssh-keyscan as fallback (still exists, but controlled by Rust)
+        let keys = Self::scan_with_keyscan(hostname, port, timeout_secs).await?;
+        if keys.is_empty() {
+            // The probe reached the server, so this is not a connection failure
+            log::warn!(
+                "[known_hosts] {}:{} answered but ssh-keyscan returned no keys within {}s",
+                hostname,
+                port,
+                timeout_secs
+            );
+            return Err(SshBuddyError::NoHostKeys {
+                hostname: format!("{}:{}", hostname, port),
+            });
+        }
+        Ok((keys, attempt))
+    }
+
+    /// ssh-keyscan arguments; `-T` is its per-host timeout in seconds
+    fn keyscan_args(hostname: &str, port: u16, timeout_secs: u64) -> Vec<String> {
+        let mut args = vec!["-T".to_string(), timeout_secs.to_string()];
+        if port != 22 {
+            args.push("-p".to_string());
+            args.push(port.to_string());
+        }
+        args.push(hostname.to_string());
+        args
+    }
+
+    /// Whether a scan failure is worth retrying (DNS, timeout or refused connection)
+    fn is_transient_error(error: &SshBuddyError) -> bool {
+        matches!(
+            error,
+            SshBuddyError::DnsResolutionFailed { .. }
+                | SshBuddyError::ConnectionTimeout
+                | SshBuddyError::ConnectionRefused { .. }
+        )
+    }
+
+    /// Delay before the next attempt after `attempt` failed attempts
+    fn scan_backoff(attempt: u32) -> Duration {
+        INITIAL_SCAN_BACKOFF
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(MAX_SCAN_BACKOFF)
+    }
+
+    /// Resolve a hostname (or IP literal, bracketed or not) to socket addresses
+    /// The lookup blocks, so it runs on the blocking thread pool
+    pub async fn resolve_hostname(hostname: &str, port: u16) -> SshResult<ResolvedHost> {
+        let host = hostname
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+
+        let lookup_host = host.clone();
+        let addresses: Vec<SocketAddr> =
+            tokio::task::spawn_blocking(move || (lookup_host.as_str(), port).to_socket_addrs())
+                .await
+                .map_err(|e| SshBuddyError::IoError {
+                    message: format!("DNS lookup task failed: {}", e),
+                })?
+                .map_err(|e| SshBuddyError::DnsResolutionFailed {
+                    hostname: format!("{}: {}", hostname, e),
+                })?
+                .collect();
+
+        if addresses.is_empty() {
+            return Err(SshBuddyError::DnsResolutionFailed {
+                hostname: hostname.to_string(),
+            });
+        }
+
+        Ok(ResolvedHost {
+            hostname: host,
+            has_ipv6: addresses.iter().any(SocketAddr::is_ipv6),
+            addresses,
+        })
+    }
+
+    /// Connect to the SSH server and exchange version banners
+    /// Slow servers get `timeout_secs` to send their banner
+    async fn probe_ssh_server(hostname: &str, port: u16, timeout_secs: u64) -> SshResult<()> {
+        let socket_addr = Self::resolve_hostname(hostname, port).await?.addresses[0];
+
         // Connect to SSH server
         let stream = timeout(Duration::from_secs(10), TcpStream::connect(socket_addr))
             .await
@@ -187,12 +1624,15 @@ impl KnownHostsService {
         // Read SSH version identification
         let mut reader = BufReader::new(stream);
         let mut version_line = String::new();
-        timeout(Duration::from_secs(5), reader.read_line(&mut version_line))
-            .await
-            .map_err(|_| SshBuddyError::ConnectionTimeout)?
-            .map_err(|e| SshBuddyError::IoError {
-                message: e.to_string(),
-            })?;
+        timeout(
+            Duration::from_secs(timeout_secs),
+            reader.read_line(&mut version_line),
+        )
+        .await
+        .map_err(|_| SshBuddyError::ConnectionTimeout)?
+        .map_err(|e| SshBuddyError::IoError {
+            message: e.to_string(),
+        })?;
 
         // Send our version
         let mut stream = reader.into_inner();
@@ -208,28 +1648,31 @@ impl KnownHostsService {
         // If not available, return empty list to let caller know
         drop(stream);
 
-        // Use ssh-keyscan as fallback (still exists, but controlled by Rust)
-        Self::scan_with_keyscan(hostname, port).await
+        Ok(())
     }
 
     /// Scan using ssh-keyscan command (fallback)
-    async fn scan_with_keyscan(hostname: &str, port: u16) -> SshResult<Vec<String>> {
-        use std::process::Command;
+    /// The command blocks, so it runs on the blocking thread pool
+    async fn scan_with_keyscan(
+        hostname: &str,
+        port: u16,
+        timeout_secs: u64,
+    ) -> SshResult<Vec<String>> {
+        let hostname = hostname.to_string();
+        tokio::task::spawn_blocking(move || Self::keyscan_blocking(&hostname, port, timeout_secs))
+            .await
+            .map_err(|e| SshBuddyError::IoError {
+                message: format!("ssh-keyscan task failed: {}", e),
+            })?
+    }
 
-        let port_str = port.to_string();
-        let mut args = vec!["-T", "5"]; // 5 second timeout
-        if port != 22 {
-            args.push("-p");
-            args.push(&port_str);
-        }
-        args.push(hostname);
+    fn keyscan_blocking(hostname: &str, port: u16, timeout_secs: u64) -> SshResult<Vec<String>> {
+        use std::process::Command;
 
         let output = Command::new("ssh-keyscan")
-            .args(&args)
+            .args(Self::keyscan_args(hostname, port, timeout_secs))
             .output()
-            .map_err(|e| SshBuddyError::IoError {
-                message: format!("Failed to run ssh-keyscan: {}", e),
-            })?;
+            .map_err(|e| SystemTools::spawn_error("ssh-keyscan", e))?;
 
         let stdout = String::from_utf8_lossy(&output.stdout);
         let keys: Vec<String> = stdout
@@ -250,6 +1693,160 @@ impl KnownHostsService {
     }
 }
 
+/// A host key returned by a scan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScannedKey {
+    /// Key algorithm, e.g. "ssh-ed25519"
+    pub key_type: String,
+    /// "<type> <base64>", as stored in known_hosts
+    pub key: String,
+    /// SHA256 fingerprint
+    pub fingerprint: String,
+}
+
+/// Classification of a known_hosts line
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LineKind {
+    Comment,
+    Blank,
+    /// Key line with plain host names
+    Standard,
+    /// Key line with a hashed host field (HashKnownHosts)
+    Hashed,
+    CertAuthority,
+    Revoked,
+    /// Missing fields, an unknown marker or a key that doesn't parse
+    Malformed,
+}
+
+/// A line of known_hosts as written, with its classification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotatedLine {
+    /// 1-based line number
+    pub line: usize,
+    pub text: String,
+    pub kind: LineKind,
+    /// Host field of key lines
+    pub hosts: Option<String>,
+    /// Key algorithm of key lines, e.g. "ssh-ed25519"
+    pub key_type: Option<String>,
+    /// SHA256 fingerprint of key lines
+    pub fingerprint: Option<String>,
+    /// Why a line is malformed
+    pub warning: Option<String>,
+}
+
+/// known_hosts lines repeating the same host field and key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateEntries {
+    /// Host field as first written
+    pub hosts: String,
+    pub key_type: String,
+    /// SHA256 fingerprint, None if the key doesn't parse
+    pub fingerprint: Option<String>,
+    /// 1-based line numbers, the first one being the entry kept by a dedupe
+    pub lines: Vec<usize>,
+}
+
+/// A host name stored with several different keys of the same algorithm
+/// Only one of them can be the server's current key, so this is worth a look
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictingHostKeys {
+    pub host: String,
+    pub key_type: String,
+    /// SHA256 fingerprints of the distinct keys, in file order
+    pub fingerprints: Vec<String>,
+    /// 1-based line numbers of every entry for the host with this algorithm
+    pub lines: Vec<usize>,
+}
+
+/// Duplicate and conflicting entries of known_hosts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KnownHostsDuplicates {
+    pub exact: Vec<DuplicateEntries>,
+    pub conflicting: Vec<ConflictingHostKeys>,
+}
+
+/// One host of a range scan finishing, sent while the rest are still scanning
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostScanProgress {
+    pub host: String,
+    /// Valid keys the host returned; empty when it failed or has no SSH server
+    pub keys: Vec<ScannedKey>,
+    pub error: Option<String>,
+    /// Hosts finished so far, this one included
+    pub completed: usize,
+    pub total: usize,
+}
+
+/// Result of adding a host after checking its key fingerprint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiedHostResult {
+    pub hostname: String,
+    pub port: u16,
+    /// The scanned key that had the expected fingerprint
+    pub matched_key: ScannedKey,
+    /// False when the key was already in known_hosts
+    pub key_added: bool,
+}
+
+/// How one host key compares to the SSHFP records of its algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SshfpMatch {
+    Match,
+    /// Records exist for the algorithm but none has this key's fingerprint
+    Mismatch,
+    /// No record for the algorithm
+    NoRecord,
+}
+
+/// Overall result of an SSHFP check
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SshfpStatus {
+    /// At least one key matches and none mismatches
+    Verified,
+    /// A key contradicts the records: possible attack or stale DNS
+    Mismatch,
+    /// The host publishes no SSHFP records
+    NoRecords,
+    /// Records exist, but only for algorithms the server didn't offer
+    NoMatchingAlgorithm,
+}
+
+/// A scanned host key with its SSHFP comparison
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SshfpKeyResult {
+    pub key_type: String,
+    /// SHA256 fingerprint
+    pub fingerprint: String,
+    pub status: SshfpMatch,
+}
+
+/// Host keys checked against the SSHFP records in DNS
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SshfpVerification {
+    pub hostname: String,
+    pub port: u16,
+    pub status: SshfpStatus,
+    /// Every record was validated with DNSSEC, up to the root trust anchor
+    pub dnssec_signed: bool,
+    pub record_count: usize,
+    /// Empty when there are no records, as the server is not scanned then
+    pub keys: Vec<SshfpKeyResult>,
+}
+
 /// Result of removing host
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -259,20 +1856,65 @@ pub struct RemoveHostResult {
     pub removed_count: usize,
 }
 
-/// Result of adding host
+/// Whether a host is already trusted, determined from known_hosts only
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct AddHostResult {
-    pub success: bool,
-    pub message: String,
-    pub keys_added: usize,
+pub struct KnownHostStatusDto {
+    pub hostname: String,
+    pub port: u16,
+    pub known: bool,
+    /// SHA256 fingerprints of the keys stored for this host
+    pub fingerprints: Vec<String>,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::TempDir;
-    use tokio::fs;
+/// Addresses a hostname resolves to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedHost {
+    pub hostname: String,
+    /// In resolver order, each with the requested port
+    pub addresses: Vec<SocketAddr>,
+    pub has_ipv6: bool,
+}
+
+/// Stored host keys of a Git platform compared to its official keys
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlatformKeyState {
+    /// All stored keys are current official keys
+    UpToDate,
+    /// At least one stored key is not a current official key (rotated or stale)
+    Outdated,
+    /// The platform is not in known_hosts
+    Missing,
+}
+
+/// Host key status of a Git platform in known_hosts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlatformKeyStatus {
+    pub platform: String,
+    pub hostname: String,
+    pub status: PlatformKeyState,
+    /// Stored fingerprints that don't match any official key
+    pub stale_fingerprints: Vec<String>,
+}
+
+/// Result of adding host
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddHostResult {
+    pub success: bool,
+    pub message: String,
+    pub keys_added: usize,
+    pub keys_rejected: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use tokio::fs;
 
     /// Create a mock SSH directory with known_hosts
     async fn create_mock_ssh_dir(known_hosts_content: &str) -> TempDir {
@@ -291,26 +1933,9 @@ mod tests {
     // Note: These tests are more of integration tests and would require
     // mocking the home directory. For now, we test the filtering logic.
 
-    /// Test the hostname matching logic used in remove_host
+    /// Whether remove_host would remove this line for the hostname
     fn matches_hostname(line: &str, target_hostname: &str) -> bool {
-        let line_trimmed = line.trim();
-        if line_trimmed.is_empty() || line_trimmed.starts_with('#') {
-            return false;
-        }
-
-        let first_field = line_trimmed.split_whitespace().next().unwrap_or("");
-
-        if first_field.starts_with("|1|") {
-            return false;
-        }
-
-        let hostnames: Vec<&str> = first_field.split(',').collect();
-        let target_lower = target_hostname.to_lowercase();
-
-        hostnames.iter().any(|h| {
-            let h_clean = h.trim_start_matches('[').split(':').next().unwrap_or(h);
-            h_clean.to_lowercase() == target_lower || h_clean.to_lowercase().contains(&target_lower)
-        })
+        KnownHostsService::remove_host_lines(line, target_hostname, None).1 > 0
     }
 
     #[test]
@@ -333,6 +1958,76 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_remove_host_lines_port_specific() {
+        let content = "example.com ssh-ed25519 AAAA1\n\
+                       [example.com]:2222 ssh-ed25519 AAAA2\n\
+                       [example.com]:2200 ssh-ed25519 AAAA3\n\
+                       [example.com]:22 ssh-ed25519 AAAA4";
+
+        let (remaining, removed) =
+            KnownHostsService::remove_host_lines(content, "example.com", Some(2222));
+        assert_eq!(removed, 1);
+        assert_eq!(
+            remaining,
+            "example.com ssh-ed25519 AAAA1\n\
+             [example.com]:2200 ssh-ed25519 AAAA3\n\
+             [example.com]:22 ssh-ed25519 AAAA4"
+        );
+
+        let (remaining, removed) =
+            KnownHostsService::remove_host_lines(content, "example.com", Some(22));
+        assert_eq!(removed, 2);
+        assert_eq!(
+            remaining,
+            "[example.com]:2222 ssh-ed25519 AAAA2\n\
+             [example.com]:2200 ssh-ed25519 AAAA3"
+        );
+    }
+
+    #[test]
+    fn test_remove_host_lines_port_agnostic() {
+        let content = "example.com ssh-ed25519 AAAA1\n\
+                       [example.com]:2222 ssh-ed25519 AAAA2\n\
+                       other.org ssh-ed25519 AAAA3";
+        let (remaining, removed) =
+            KnownHostsService::remove_host_lines(content, "example.com", None);
+        assert_eq!(removed, 2);
+        assert_eq!(remaining, "other.org ssh-ed25519 AAAA3");
+    }
+
+    #[test]
+    fn test_remove_host_lines_port_specific_hashed() {
+        let content = format!(
+            "{} ssh-ed25519 AAAA1\n{} ssh-ed25519 AAAA2",
+            hash_host("example.com").unwrap(),
+            hash_host("[example.com]:2222").unwrap()
+        );
+        let (remaining, removed) =
+            KnownHostsService::remove_host_lines(&content, "example.com", Some(2222));
+        assert_eq!(removed, 1);
+        assert!(remaining.ends_with("AAAA1"));
+    }
+
+    #[test]
+    fn test_remove_host_lines_bracketed_name() {
+        // "[host]:port" selects that port's entries, plain or hashed
+        let content = format!(
+            "example.com ssh-ed25519 AAAA1\n[example.com]:2222 ssh-ed25519 AAAA2\n{} ssh-ed25519 AAAA3",
+            hash_host("[example.com]:2222").unwrap()
+        );
+        let (remaining, removed) =
+            KnownHostsService::remove_host_lines(&content, "[example.com]:2222", None);
+        assert_eq!(removed, 2);
+        assert_eq!(remaining, "example.com ssh-ed25519 AAAA1");
+
+        // Without a port, a hashed entry on another port isn't recognized
+        let (remaining, removed) =
+            KnownHostsService::remove_host_lines(&content, "example.com", None);
+        assert_eq!(removed, 2);
+        assert!(remaining.ends_with("AAAA3"));
+    }
+
     #[test]
     fn test_matches_hostname_multiple() {
         assert!(matches_hostname(
@@ -346,7 +2041,7 @@ mod tests {
     }
 
     #[test]
-    fn test_matches_hostname_skip_hashed() {
+    fn test_matches_hostname_hashed_mismatch() {
         assert!(!matches_hostname(
             "|1|hash1|hash2 ssh-ed25519 AAAA...",
             "anything"
@@ -407,4 +2102,999 @@ mod tests {
 
         assert_eq!(entry, "[example.com]:2222 ssh-ed25519 AAAA...");
     }
+
+    // ========================================
+    // Entry parsing tests
+    // ========================================
+
+    #[test]
+    fn test_parse_entries_keeps_hashed_and_markers() {
+        let content = "# comment\n|1|c2FsdA==|aGFzaA== ssh-ed25519 AAAA\n@revoked bad.example ssh-ed25519 BBBB\nmalformed\n";
+        let entries = KnownHostsService::parse_entries(content);
+
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].is_hashed());
+        assert_eq!(entries[0].line, 2);
+        assert_eq!(entries[1].marker.as_deref(), Some("@revoked"));
+        assert_eq!(entries[1].hosts, "bad.example");
+        assert_eq!(entries[1].key(), "ssh-ed25519 BBBB");
+
+        // Revoked entries don't trust a host; hashed ones stay under their hashed field
+        let known_hosts = KnownHostsService::parse_known_hosts(content);
+        assert_eq!(known_hosts.len(), 1);
+        assert!(known_hosts.contains_key("|1|c2FsdA==|aGFzaA=="));
+    }
+
+    #[test]
+    fn test_parse_entries_with_and_without_comment() {
+        let content = "github.com ssh-ed25519 AAAA user@laptop  added 2024\n\
+                       gitlab.com   ssh-ed25519\tBBBB   \n\
+                       example.com ssh-ed25519 CCCC\n";
+        let entries = KnownHostsService::parse_entries(content);
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].key(), "ssh-ed25519 AAAA");
+        assert_eq!(
+            entries[0].comment.as_deref(),
+            Some("user@laptop added 2024")
+        );
+        assert_eq!(entries[1].hosts, "gitlab.com");
+        assert_eq!(entries[1].key_base64, "BBBB");
+        assert_eq!(entries[1].comment, None);
+        assert_eq!(entries[2].comment, None);
+
+        // Stored keys leave the comment out
+        let known_hosts = KnownHostsService::parse_known_hosts(content);
+        assert_eq!(known_hosts["github.com"], vec!["ssh-ed25519 AAAA"]);
+        assert_eq!(known_hosts["gitlab.com"], vec!["ssh-ed25519 BBBB"]);
+    }
+
+    #[test]
+    fn test_check_host_key_exact_base64_match() {
+        let known_hosts =
+            KnownHostsService::parse_known_hosts("github.com ssh-ed25519 AAAAC3NzaC1lZDI1 comment");
+
+        assert_eq!(
+            KnownHostsService::check_host_key(&known_hosts, "github.com", 22, "AAAAC3NzaC1lZDI1"),
+            KnownHostStatus::Matched
+        );
+        // A substring of the stored key (or of its comment) is not a match
+        assert_eq!(
+            KnownHostsService::check_host_key(&known_hosts, "github.com", 22, "AAAAC3Nza"),
+            KnownHostStatus::Changed
+        );
+        assert_eq!(
+            KnownHostsService::check_host_key(&known_hosts, "github.com", 22, "comment"),
+            KnownHostStatus::Changed
+        );
+    }
+
+    // ========================================
+    // Hashed host tests
+    // ========================================
+
+    /// HMAC-SHA1 of "github.com" and "[git.example.com]:2222" with salt 0x00..0x13
+    const HASHED_GITHUB: &str = "|1|AAECAwQFBgcICQoLDA0ODxAREhM=|/vGHEQmgsSrggHW81/s0OAW0mAs=";
+    const HASHED_EXAMPLE_2222: &str =
+        "|1|AAECAwQFBgcICQoLDA0ODxAREhM=|iB8ji+G7JiWchxZqOjryGAnIiTo=";
+
+    #[test]
+    fn test_hashed_host_matches() {
+        assert!(hashed_host_matches(HASHED_GITHUB, "github.com"));
+        assert!(!hashed_host_matches(HASHED_GITHUB, "gitlab.com"));
+        assert!(!hashed_host_matches("|1|not-base64|x", "github.com"));
+        assert!(!hashed_host_matches("github.com", "github.com"));
+    }
+
+    #[test]
+    fn test_host_status_hashed_non_standard_port() {
+        let known_hosts = KnownHostsService::parse_known_hosts(&format!(
+            "{} {}",
+            HASHED_EXAMPLE_2222, VALID_HOST_KEY
+        ));
+
+        assert!(KnownHostsService::host_status(&known_hosts, "git.example.com", 2222).known);
+        assert!(!KnownHostsService::host_status(&known_hosts, "git.example.com", 22).known);
+    }
+
+    #[test]
+    fn test_platform_keys_hashed_entry() {
+        let key = GITHUB_ED25519.trim_start_matches("github.com ");
+        let known_hosts =
+            KnownHostsService::parse_known_hosts(&format!("{} {}", HASHED_GITHUB, key));
+        let statuses = KnownHostsService::platform_key_statuses(&known_hosts);
+
+        assert_eq!(
+            status_of(&statuses, "github").status,
+            PlatformKeyState::UpToDate
+        );
+    }
+
+    // ========================================
+    // Offline host status tests
+    // ========================================
+
+    #[test]
+    fn test_host_status_known() {
+        let known_hosts = KnownHostsService::parse_known_hosts(&format!(
+            "example.com,10.0.0.1 {}\n[example.com]:2222 {}\n",
+            VALID_HOST_KEY, VALID_HOST_KEY
+        ));
+        let fingerprint = PublicKey::from_openssh(VALID_HOST_KEY)
+            .unwrap()
+            .fingerprint(ssh_key::HashAlg::Sha256)
+            .to_string();
+
+        let status = KnownHostsService::host_status(&known_hosts, "example.com", 22);
+        assert!(status.known);
+        assert_eq!(status.fingerprints, vec![fingerprint.clone()]);
+
+        // Non-standard port also matches the bare host name; duplicates are collapsed
+        let status = KnownHostsService::host_status(&known_hosts, "example.com", 2222);
+        assert!(status.known);
+        assert_eq!(status.fingerprints, vec![fingerprint]);
+    }
+
+    #[test]
+    fn test_host_status_unknown() {
+        let known_hosts =
+            KnownHostsService::parse_known_hosts(&format!("example.com {}", VALID_HOST_KEY));
+
+        let status = KnownHostsService::host_status(&known_hosts, "github.com", 22);
+        assert!(!status.known);
+        assert!(status.fingerprints.is_empty());
+    }
+
+    // ========================================
+    // Platform host key tests
+    // ========================================
+
+    const GITHUB_ED25519: &str =
+        "github.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl";
+
+    fn status_of<'a>(statuses: &'a [PlatformKeyStatus], platform: &str) -> &'a PlatformKeyStatus {
+        statuses.iter().find(|s| s.platform == platform).unwrap()
+    }
+
+    #[test]
+    fn test_platform_keys_up_to_date_and_missing() {
+        let known_hosts = KnownHostsService::parse_known_hosts(GITHUB_ED25519);
+        let statuses = KnownHostsService::platform_key_statuses(&known_hosts);
+
+        let github = status_of(&statuses, "github");
+        assert_eq!(github.status, PlatformKeyState::UpToDate);
+        assert!(github.stale_fingerprints.is_empty());
+        assert_eq!(
+            status_of(&statuses, "gitlab").status,
+            PlatformKeyState::Missing
+        );
+    }
+
+    #[test]
+    fn test_platform_keys_outdated() {
+        let known_hosts = KnownHostsService::parse_known_hosts(&format!(
+            "{}\ngitlab.com {}\n",
+            GITHUB_ED25519, VALID_HOST_KEY
+        ));
+        let statuses = KnownHostsService::platform_key_statuses(&known_hosts);
+
+        let gitlab = status_of(&statuses, "gitlab");
+        assert_eq!(gitlab.status, PlatformKeyState::Outdated);
+        assert_eq!(gitlab.stale_fingerprints.len(), 1);
+        assert_eq!(
+            status_of(&statuses, "github").status,
+            PlatformKeyState::UpToDate
+        );
+    }
+
+    // ========================================
+    // Scan retry tests
+    // ========================================
+
+    #[test]
+    fn test_is_transient_error() {
+        assert!(KnownHostsService::is_transient_error(
+            &SshBuddyError::ConnectionTimeout
+        ));
+        assert!(KnownHostsService::is_transient_error(
+            &SshBuddyError::DnsResolutionFailed {
+                hostname: "example.com".to_string()
+            }
+        ));
+        assert!(KnownHostsService::is_transient_error(
+            &SshBuddyError::ConnectionRefused {
+                message: "refused".to_string()
+            }
+        ));
+        assert!(!KnownHostsService::is_transient_error(
+            &SshBuddyError::IoError {
+                message: "broken pipe".to_string()
+            }
+        ));
+    }
+
+    #[test]
+    fn test_scan_backoff_is_bounded() {
+        assert_eq!(
+            KnownHostsService::scan_backoff(1),
+            Duration::from_millis(500)
+        );
+        assert_eq!(KnownHostsService::scan_backoff(2), Duration::from_secs(1));
+        assert_eq!(KnownHostsService::scan_backoff(3), Duration::from_secs(2));
+        assert_eq!(KnownHostsService::scan_backoff(10), MAX_SCAN_BACKOFF);
+        assert_eq!(KnownHostsService::scan_backoff(40), MAX_SCAN_BACKOFF);
+    }
+
+    #[test]
+    fn test_scan_timeout_and_keyscan_args() {
+        assert_eq!(
+            KnownHostsService::scan_timeout_secs(None),
+            DEFAULT_SCAN_TIMEOUT_SECS
+        );
+        assert_eq!(KnownHostsService::scan_timeout_secs(Some(20)), 20);
+        assert_eq!(KnownHostsService::scan_timeout_secs(Some(0)), 1);
+        assert_eq!(
+            KnownHostsService::scan_timeout_secs(Some(3600)),
+            MAX_SCAN_TIMEOUT_SECS
+        );
+
+        assert_eq!(
+            KnownHostsService::keyscan_args("example.com", 22, 20),
+            vec!["-T", "20", "example.com"]
+        );
+        assert_eq!(
+            KnownHostsService::keyscan_args("example.com", 2222, 5),
+            vec!["-T", "5", "-p", "2222", "example.com"]
+        );
+
+        // A host that answered but had no keys is not worth retrying
+        assert!(!KnownHostsService::is_transient_error(
+            &SshBuddyError::NoHostKeys {
+                hostname: "example.com:22".to_string()
+            }
+        ));
+    }
+
+    /// Port of a local server that sends its SSH banner after `delay`
+    async fn slow_banner_server(delay: Duration) -> u16 {
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(delay).await;
+            stream.write_all(b"SSH-2.0-Slow\r\n").await.ok();
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_probe_waits_for_slow_banner() {
+        let port = slow_banner_server(Duration::from_millis(1500)).await;
+        let result = KnownHostsService::probe_ssh_server("127.0.0.1", port, 1).await;
+        assert!(matches!(result, Err(SshBuddyError::ConnectionTimeout)));
+
+        let port = slow_banner_server(Duration::from_millis(1500)).await;
+        assert!(KnownHostsService::probe_ssh_server("127.0.0.1", port, 3)
+            .await
+            .is_ok());
+    }
+
+    // ========================================
+    // SSHFP tests
+    // ========================================
+
+    fn sshfp_record(fingerprint_type: u8, key: &str) -> SshfpRecord {
+        let public_key = PublicKey::from_openssh(key).unwrap();
+        let fingerprint = match fingerprint_type {
+            1 => Sha1::digest(public_key.to_bytes().unwrap()).to_vec(),
+            _ => public_key
+                .fingerprint(ssh_key::HashAlg::Sha256)
+                .as_bytes()
+                .to_vec(),
+        };
+        SshfpRecord {
+            algorithm: 4,
+            fingerprint_type,
+            fingerprint,
+        }
+    }
+
+    #[test]
+    fn test_sshfp_verification() {
+        let scanned = KnownHostsService::scanned_keys(&[VALID_HOST_KEY.to_string()]);
+        let other_key = GITHUB_ED25519.trim_start_matches("github.com ");
+
+        let verification = KnownHostsService::sshfp_verification(
+            "host.example.com",
+            22,
+            &[
+                sshfp_record(1, VALID_HOST_KEY),
+                sshfp_record(2, VALID_HOST_KEY),
+            ],
+            true,
+            &scanned,
+        );
+        assert_eq!(verification.status, SshfpStatus::Verified);
+        assert!(verification.dnssec_signed);
+        assert_eq!(verification.record_count, 2);
+        assert_eq!(verification.keys[0].status, SshfpMatch::Match);
+
+        // A record for another Ed25519 key only
+        let verification = KnownHostsService::sshfp_verification(
+            "host.example.com",
+            22,
+            &[sshfp_record(2, other_key)],
+            false,
+            &scanned,
+        );
+        assert_eq!(verification.status, SshfpStatus::Mismatch);
+        assert_eq!(verification.keys[0].status, SshfpMatch::Mismatch);
+
+        // Records for RSA only say nothing about an Ed25519 key
+        let rsa_only = SshfpRecord {
+            algorithm: 1,
+            fingerprint_type: 2,
+            fingerprint: vec![0; 32],
+        };
+        let verification = KnownHostsService::sshfp_verification(
+            "host.example.com",
+            22,
+            &[rsa_only],
+            false,
+            &scanned,
+        );
+        assert_eq!(verification.status, SshfpStatus::NoMatchingAlgorithm);
+        assert_eq!(verification.keys[0].status, SshfpMatch::NoRecord);
+    }
+
+    // ========================================
+    // Duplicate entry tests
+    // ========================================
+
+    #[test]
+    fn test_duplicates_exact_and_dedupe() {
+        let content = format!(
+            "{0} first\n# note\nexample.com {1}\n{0} again\n",
+            GITHUB_ED25519, VALID_HOST_KEY
+        );
+
+        let duplicates =
+            KnownHostsService::duplicates_in(&KnownHostsService::parse_entries(&content));
+        assert_eq!(duplicates.exact.len(), 1);
+        assert_eq!(duplicates.exact[0].hosts, "github.com");
+        assert_eq!(duplicates.exact[0].lines, vec![1, 4]);
+        assert!(duplicates.exact[0].fingerprint.is_some());
+        assert!(duplicates.conflicting.is_empty());
+
+        let (deduped, removed) = KnownHostsService::dedupe_content(&content);
+        assert_eq!(removed, 1);
+        assert_eq!(
+            deduped,
+            format!(
+                "{} first\n# note\nexample.com {}\n",
+                GITHUB_ED25519, VALID_HOST_KEY
+            )
+        );
+        assert_eq!(KnownHostsService::dedupe_content(&deduped), (deduped, 0));
+    }
+
+    #[test]
+    fn test_duplicates_same_host_different_keys() {
+        let content = format!(
+            "{}\nlab.example.com,10.0.0.5 {}\n10.0.0.5 {}\n",
+            GITHUB_ED25519.replace("github.com", "lab.example.com"),
+            VALID_HOST_KEY,
+            VALID_HOST_KEY
+        );
+
+        let duplicates =
+            KnownHostsService::duplicates_in(&KnownHostsService::parse_entries(&content));
+        assert!(duplicates.exact.is_empty());
+        assert_eq!(duplicates.conflicting.len(), 1);
+        let conflict = &duplicates.conflicting[0];
+        assert_eq!(conflict.host, "lab.example.com");
+        assert_eq!(conflict.key_type, "ssh-ed25519");
+        assert_eq!(conflict.lines, vec![1, 2]);
+        assert_eq!(conflict.fingerprints.len(), 2);
+        assert_ne!(conflict.fingerprints[0], conflict.fingerprints[1]);
+
+        // Different keys are not exact duplicates, so a dedupe keeps them
+        assert_eq!(KnownHostsService::dedupe_content(&content).1, 0);
+    }
+
+    // ========================================
+    // Scanned key validation tests
+    // ========================================
+
+    const VALID_HOST_KEY: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIFlXOQk34tnLe4gTVThVboRl89gl4sC9wNcw+PtGp1Mk";
+
+    #[test]
+    fn test_validate_scanned_keys() {
+        let keys = vec![
+            VALID_HOST_KEY.to_string(),
+            "ssh-ed25519 bm90LWEta2V5".to_string(),
+        ];
+
+        let (valid, rejected) = KnownHostsService::validate_scanned_keys(&keys);
+        assert_eq!(valid, vec![VALID_HOST_KEY.to_string()]);
+        assert_eq!(rejected, 1);
+    }
+
+    #[tokio::test]
+    async fn test_only_valid_scanned_keys_are_written() {
+        let temp = create_mock_ssh_dir("").await;
+        let known_hosts_path = temp.path().join(".ssh").join("known_hosts");
+        let keys = vec![
+            VALID_HOST_KEY.to_string(),
+            "ssh-rsa !!!garbage!!!".to_string(),
+        ];
+
+        let (valid, rejected) = KnownHostsService::validate_scanned_keys(&keys);
+        let added = KnownHostsService::append_host_keys(
+            &known_hosts_path,
+            "example.com",
+            22,
+            &valid,
+            false,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(added, 1);
+        assert_eq!(rejected, 1);
+        let content = fs::read_to_string(&known_hosts_path).await.unwrap();
+        assert_eq!(content, format!("example.com {}\n", VALID_HOST_KEY));
+        assert!(!content.contains("garbage"));
+    }
+
+    // ========================================
+    // HostKeyAlias tests
+    // ========================================
+
+    #[tokio::test]
+    async fn test_host_key_alias_used_for_lookup_and_write() {
+        use crate::utils::SshConfigParser;
+
+        let config =
+            "Host app\n    HostName lb-17.internal\n    Port 2222\n    HostKeyAlias app.example\n";
+        let hosts = SshConfigParser::parse(config);
+        let host = SshConfigParser::merge_configs(&hosts, "app");
+        assert_eq!(host.host_key_alias.as_deref(), Some("app.example"));
+
+        let (entry_host, entry_port) = KnownHostsService::entry_target(
+            host.get_hostname(),
+            host.get_port(),
+            host.host_key_alias.as_deref(),
+        );
+        assert_eq!((entry_host.as_str(), entry_port), ("app.example", 22));
+        assert_eq!(
+            KnownHostsService::entry_target("lb-17.internal", 2222, None),
+            ("lb-17.internal".to_string(), 2222)
+        );
+
+        let temp = create_mock_ssh_dir("").await;
+        let known_hosts_path = temp.path().join(".ssh").join("known_hosts");
+        let keys = vec![VALID_HOST_KEY.to_string()];
+        KnownHostsService::append_host_keys(
+            &known_hosts_path,
+            &entry_host,
+            entry_port,
+            &keys,
+            false,
+        )
+        .await
+        .unwrap();
+        let content = fs::read_to_string(&known_hosts_path).await.unwrap();
+        assert_eq!(content, format!("app.example {}\n", VALID_HOST_KEY));
+
+        let known_hosts = KnownHostsService::parse_known_hosts(&content);
+        let key_base64 = VALID_HOST_KEY.split_whitespace().nth(1).unwrap();
+        assert_eq!(
+            KnownHostsService::check_host_key(&known_hosts, &entry_host, entry_port, key_base64),
+            KnownHostStatus::Matched
+        );
+        assert_eq!(
+            KnownHostsService::check_host_key(&known_hosts, "lb-17.internal", 2222, key_base64),
+            KnownHostStatus::Unknown
+        );
+    }
+
+    // ========================================
+    // known_hosts file selection tests
+    // ========================================
+
+    #[tokio::test]
+    async fn test_known_hosts_file_must_be_in_ssh_dir() {
+        let temp = create_mock_ssh_dir("").await;
+        let ssh_dir = temp.path().join(".ssh");
+
+        // A file that does not exist yet is fine, as long as it is inside ~/.ssh
+        let path = ssh_dir.join("known_hosts2");
+        assert_eq!(
+            KnownHostsService::known_hosts_file_in(&path.to_string_lossy(), &ssh_dir).unwrap(),
+            path
+        );
+
+        let outside = temp.path().join("known_hosts");
+        assert!(matches!(
+            KnownHostsService::known_hosts_file_in(&outside.to_string_lossy(), &ssh_dir),
+            Err(SshBuddyError::PathTraversalDetected { .. })
+        ));
+    }
+
+    // ========================================
+    // Concurrent write tests
+    // ========================================
+
+    #[tokio::test]
+    async fn test_concurrent_appends_keep_both_hosts() {
+        let temp = create_mock_ssh_dir("").await;
+        let known_hosts_path = temp.path().join(".ssh").join("known_hosts");
+        let keys = vec![VALID_HOST_KEY.to_string()];
+
+        let (first, second) = tokio::join!(
+            KnownHostsService::append_host_keys(
+                &known_hosts_path,
+                "one.example.com",
+                22,
+                &keys,
+                false
+            ),
+            KnownHostsService::append_host_keys(
+                &known_hosts_path,
+                "two.example.com",
+                22,
+                &keys,
+                false
+            ),
+        );
+        assert_eq!(first.unwrap(), 1);
+        assert_eq!(second.unwrap(), 1);
+
+        let content = fs::read_to_string(&known_hosts_path).await.unwrap();
+        assert!(content.contains(&format!("one.example.com {}", VALID_HOST_KEY)));
+        assert!(content.contains(&format!("two.example.com {}", VALID_HOST_KEY)));
+    }
+
+    // ========================================
+    // resolve_hostname tests
+    // ========================================
+
+    #[tokio::test]
+    async fn test_resolve_hostname_localhost() {
+        let resolved = KnownHostsService::resolve_hostname("localhost", 22)
+            .await
+            .unwrap();
+        assert!(!resolved.addresses.is_empty());
+        assert!(resolved
+            .addresses
+            .iter()
+            .all(|addr| addr.ip().is_loopback() && addr.port() == 22));
+        assert_eq!(
+            resolved.has_ipv6,
+            resolved.addresses.iter().any(SocketAddr::is_ipv6)
+        );
+
+        let literal = KnownHostsService::resolve_hostname("[::1]", 2222)
+            .await
+            .unwrap();
+        assert!(literal.has_ipv6);
+        assert_eq!(literal.hostname, "::1");
+    }
+
+    #[tokio::test]
+    async fn test_resolve_hostname_failure() {
+        assert!(matches!(
+            KnownHostsService::resolve_hostname("no-such-host.invalid", 22).await,
+            Err(SshBuddyError::DnsResolutionFailed { .. })
+        ));
+    }
+
+    // ========================================
+    // Host range scan tests
+    // ========================================
+
+    #[test]
+    fn test_expand_host_range_cidr() {
+        let hosts = KnownHostsService::expand_host_range("192.168.1.5/30").unwrap();
+        assert_eq!(hosts, vec!["192.168.1.5", "192.168.1.6"]);
+
+        let hosts = KnownHostsService::expand_host_range("10.0.0.0/28").unwrap();
+        assert_eq!(hosts.len(), 14);
+        assert_eq!(hosts.first().unwrap(), "10.0.0.1");
+        assert_eq!(hosts.last().unwrap(), "10.0.0.14");
+
+        let hosts = KnownHostsService::expand_host_range("10.0.0.7/32").unwrap();
+        assert_eq!(hosts, vec!["10.0.0.7"]);
+    }
+
+    #[test]
+    fn test_expand_host_range_list() {
+        let hosts =
+            KnownHostsService::expand_host_range("git.example.com, 10.0.0.1,10.0.1.0/31").unwrap();
+        assert_eq!(
+            hosts,
+            vec!["git.example.com", "10.0.0.1", "10.0.1.0", "10.0.1.1"]
+        );
+    }
+
+    #[test]
+    fn test_expand_host_range_rejects_large_or_invalid() {
+        for spec in [
+            "10.0.0.0/24",
+            "10.0.0.0/33",
+            "fd00::/120",
+            "bad host",
+            " , ",
+        ] {
+            assert!(
+                KnownHostsService::expand_host_range(spec).is_err(),
+                "{} should be rejected",
+                spec
+            );
+        }
+    }
+
+    #[test]
+    fn test_scanned_keys_fingerprints() {
+        let scanned = KnownHostsService::scanned_keys(&[VALID_HOST_KEY.to_string()]);
+        assert_eq!(scanned.len(), 1);
+        assert_eq!(scanned[0].key, VALID_HOST_KEY);
+        assert!(scanned[0].fingerprint.starts_with("SHA256:"));
+    }
+
+    // ========================================
+    // Verified add tests
+    // ========================================
+
+    #[test]
+    fn test_matching_key() {
+        let scanned = KnownHostsService::scanned_keys(&[VALID_HOST_KEY.to_string()]);
+        let fingerprint = scanned[0].fingerprint.clone();
+        let bare = fingerprint.trim_start_matches("SHA256:");
+
+        for expected in [fingerprint.as_str(), bare, &format!(" {}= ", bare)] {
+            assert_eq!(
+                KnownHostsService::matching_key(&scanned, expected),
+                Some(&scanned[0]),
+                "{} should match",
+                expected
+            );
+        }
+        assert_eq!(
+            KnownHostsService::matching_key(&scanned, "SHA256:AAAAnotthekey"),
+            None
+        );
+        assert_eq!(KnownHostsService::matching_key(&scanned, "SHA256:"), None);
+    }
+
+    // ========================================
+    // Relabel tests
+    // ========================================
+
+    #[test]
+    fn test_relabel_content_round_trip() {
+        let content = format!(
+            "# servers\n\
+             old.example.com {key} build box\n\
+             old.example.com,10.0.0.5\t{key}\n\
+             [old.example.com]:2222 {key}\n\
+             @cert-authority old.example.com {key}\n\
+             |1|c2FsdA==|aGFzaA== {key}\n\
+             other.example.com {key}\n",
+            key = VALID_HOST_KEY
+        );
+
+        let (relabeled, updated) =
+            KnownHostsService::relabel_content(&content, "old.example.com", "new.example.com", 22);
+        assert_eq!(updated, 3);
+
+        let entries = KnownHostsService::parse_entries(&relabeled);
+        let hosts: Vec<&str> = entries.iter().map(|e| e.hosts.as_str()).collect();
+        assert_eq!(
+            hosts,
+            vec![
+                "new.example.com",
+                "new.example.com,10.0.0.5",
+                "[old.example.com]:2222",
+                "new.example.com",
+                "|1|c2FsdA==|aGFzaA==",
+                "other.example.com",
+            ]
+        );
+        // Keys, comments, markers and separators are kept
+        assert_eq!(entries[0].comment.as_deref(), Some("build box"));
+        assert_eq!(entries[3].marker.as_deref(), Some("@cert-authority"));
+        assert!(entries.iter().all(|e| e.key() == VALID_HOST_KEY));
+        assert!(relabeled.contains("new.example.com,10.0.0.5\t"));
+
+        let (restored, updated) = KnownHostsService::relabel_content(
+            &relabeled,
+            "new.example.com",
+            "old.example.com",
+            22,
+        );
+        assert_eq!(updated, 3);
+        assert_eq!(restored, content);
+    }
+
+    #[test]
+    fn test_relabel_content_port_and_duplicates() {
+        let content = format!(
+            "[old.example.com]:2222,[new.example.com]:2222 {key}\nold.example.com {key}",
+            key = VALID_HOST_KEY
+        );
+
+        let (relabeled, updated) = KnownHostsService::relabel_content(
+            &content,
+            "old.example.com",
+            "new.example.com",
+            2222,
+        );
+        assert_eq!(updated, 1);
+        assert_eq!(
+            relabeled,
+            format!(
+                "[new.example.com]:2222 {key}\nold.example.com {key}",
+                key = VALID_HOST_KEY
+            )
+        );
+    }
+
+    // ========================================
+    // Annotated line tests
+    // ========================================
+
+    #[test]
+    fn test_annotate_lines() {
+        let content = format!(
+            "# managed by hand\n\n\
+             example.com {key}\n\
+             |1|c2FsdA==|aGFzaA== {key}\n\
+             @cert-authority *.example.com {key}\n\
+             @revoked old.example.com {key}\n\
+             truncated.example.com ssh-ed25519\n\
+             @bogus example.com {key}\n\
+             broken.example.com ssh-ed25519 AAAAnotakey\n",
+            key = VALID_HOST_KEY
+        );
+        let lines = KnownHostsService::annotate_lines(&content);
+        let kinds: Vec<LineKind> = lines.iter().map(|line| line.kind).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                LineKind::Comment,
+                LineKind::Blank,
+                LineKind::Standard,
+                LineKind::Hashed,
+                LineKind::CertAuthority,
+                LineKind::Revoked,
+                LineKind::Malformed,
+                LineKind::Malformed,
+                LineKind::Malformed,
+            ]
+        );
+        assert_eq!(lines[0].text, "# managed by hand");
+        assert_eq!(lines[2].line, 3);
+        assert_eq!(lines[2].hosts.as_deref(), Some("example.com"));
+        assert_eq!(lines[2].key_type.as_deref(), Some("ssh-ed25519"));
+        assert!(lines[2]
+            .fingerprint
+            .as_deref()
+            .unwrap()
+            .starts_with("SHA256:"));
+        assert_eq!(lines[4].hosts.as_deref(), Some("*.example.com"));
+        assert!(lines[0].fingerprint.is_none());
+        for malformed in &lines[6..] {
+            assert!(malformed.warning.is_some());
+            assert!(malformed.fingerprint.is_none());
+        }
+    }
+
+    // ========================================
+    // Fingerprint comparison tests
+    // ========================================
+
+    #[test]
+    fn test_check_host_key_compares_fingerprints() {
+        let base64 = VALID_HOST_KEY.split_once(' ').unwrap().1;
+        let known_hosts =
+            KnownHostsService::parse_known_hosts(&format!("example.com {}", VALID_HOST_KEY));
+        let check = |server_key: &str| {
+            KnownHostsService::check_host_key(&known_hosts, "example.com", 22, server_key)
+        };
+
+        assert_eq!(check(base64), KnownHostStatus::Matched);
+        assert_eq!(check(&format!(" {}\r\n", base64)), KnownHostStatus::Matched);
+
+        // A server blob containing the stored one, or contained in it, is another key
+        assert_eq!(check(&format!("{}AAAA", base64)), KnownHostStatus::Changed);
+        assert_eq!(check(&base64[..base64.len() - 4]), KnownHostStatus::Changed);
+
+        // Another valid key of the same type
+        let other = GITHUB_ED25519.rsplit_once(' ').unwrap().1;
+        assert_eq!(check(other), KnownHostStatus::Changed);
+    }
+
+    // ========================================
+    // Line ending tests
+    // ========================================
+
+    #[test]
+    fn test_crlf_known_hosts() {
+        let content = format!(
+            "# edited on Windows\r\nexample.com {key}\r\n[other.example]:2222 {key}\r\n",
+            key = VALID_HOST_KEY
+        );
+        let base64 = VALID_HOST_KEY.split_once(' ').unwrap().1;
+
+        // Matching works before the file is fixed
+        let known_hosts = KnownHostsService::parse_known_hosts(&content);
+        assert_eq!(
+            KnownHostsService::check_host_key(&known_hosts, "example.com", 22, base64),
+            KnownHostStatus::Matched
+        );
+        assert_eq!(
+            KnownHostsService::check_host_key(&known_hosts, "other.example", 2222, base64),
+            KnownHostStatus::Matched
+        );
+
+        let (fixed_content, fixed) = KnownHostsService::strip_carriage_returns(&content);
+        assert_eq!(fixed, 3);
+        assert!(!fixed_content.contains('\r'));
+        assert!(fixed_content.ends_with(&format!("{}\n", VALID_HOST_KEY)));
+        assert_eq!(
+            KnownHostsService::strip_carriage_returns(&fixed_content),
+            (fixed_content.clone(), 0)
+        );
+    }
+
+    // ========================================
+    // Scan progress tests
+    // ========================================
+
+    #[tokio::test]
+    async fn test_scan_range_reports_every_host() {
+        let progress = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = progress.clone();
+
+        // Nothing listens on port 1, so each host finishes without keys
+        let results = KnownHostsService::scan_range("127.0.0.1,127.0.0.2", Some(1), move |p| {
+            sink.lock().unwrap().push(p)
+        })
+        .await
+        .unwrap();
+        assert_eq!(results.len(), 2);
+
+        let progress = progress.lock().unwrap();
+        let mut hosts: Vec<&str> = progress.iter().map(|p| p.host.as_str()).collect();
+        hosts.sort();
+        assert_eq!(hosts, vec!["127.0.0.1", "127.0.0.2"]);
+        let mut completed: Vec<usize> = progress.iter().map(|p| p.completed).collect();
+        completed.sort();
+        assert_eq!(completed, vec![1, 2]);
+        assert!(progress.iter().all(|p| p.total == 2 && p.keys.is_empty()));
+    }
+
+    // ========================================
+    // hash_hostnames tests
+    // ========================================
+
+    #[tokio::test]
+    async fn test_hashed_entries_round_trip() {
+        let temp = create_mock_ssh_dir("unrelated.org ssh-ed25519 AAAA\n").await;
+        let known_hosts_path = temp.path().join(".ssh").join("known_hosts");
+        let keys = vec![VALID_HOST_KEY.to_string()];
+
+        for (host, port) in [("example.com", 22), ("example.com", 2222)] {
+            let added =
+                KnownHostsService::append_host_keys(&known_hosts_path, host, port, &keys, true)
+                    .await
+                    .unwrap();
+            assert_eq!(added, 1);
+        }
+        // Adding again finds the hashed entry despite the new salt
+        let added =
+            KnownHostsService::append_host_keys(&known_hosts_path, "example.com", 22, &keys, true)
+                .await
+                .unwrap();
+        assert_eq!(added, 0);
+
+        let content = fs::read_to_string(&known_hosts_path).await.unwrap();
+        assert!(!content.contains("example.com ssh-ed25519 AAAAC3"));
+        let entries = KnownHostsService::parse_entries(&content);
+        assert_eq!(entries.len(), 3);
+        assert!(hashed_host_matches(&entries[1].hosts, "example.com"));
+        assert!(hashed_host_matches(&entries[2].hosts, "[example.com]:2222"));
+
+        let known_hosts = KnownHostsService::parse_known_hosts(&content);
+        let base64 = VALID_HOST_KEY.split_once(' ').unwrap().1;
+        assert_eq!(
+            KnownHostsService::check_host_key(&known_hosts, "example.com", 2222, base64),
+            KnownHostStatus::Matched
+        );
+
+        let (remaining, removed) =
+            KnownHostsService::remove_host_lines(&content, "example.com", None);
+        assert_eq!(removed, 1);
+        let (remaining, removed) =
+            KnownHostsService::remove_host_lines(&remaining, "[example.com]:2222", None);
+        assert_eq!(removed, 1);
+        assert_eq!(remaining, "unrelated.org ssh-ed25519 AAAA");
+    }
+
+    #[tokio::test]
+    async fn test_append_skips_entries_present_in_the_other_form() {
+        let temp = create_mock_ssh_dir(&format!(
+            "Example.com,192.0.2.1 {}\n[example.com]:2222 {}\n",
+            VALID_HOST_KEY, VALID_HOST_KEY
+        ))
+        .await;
+        let known_hosts_path = temp.path().join(".ssh").join("known_hosts");
+        let keys = vec![VALID_HOST_KEY.to_string()];
+
+        // A plain entry already present is not added again hashed
+        for (host, port) in [
+            ("example.com", 22),
+            ("192.0.2.1", 22),
+            ("example.com", 2222),
+        ] {
+            let added =
+                KnownHostsService::append_host_keys(&known_hosts_path, host, port, &keys, true)
+                    .await
+                    .unwrap();
+            assert_eq!(added, 0);
+        }
+
+        // Nor is a hashed entry added again in plain text
+        let added =
+            KnownHostsService::append_host_keys(&known_hosts_path, "other.org", 22, &keys, true)
+                .await
+                .unwrap();
+        assert_eq!(added, 1);
+        let added =
+            KnownHostsService::append_host_keys(&known_hosts_path, "other.org", 22, &keys, false)
+                .await
+                .unwrap();
+        assert_eq!(added, 0);
+
+        let content = fs::read_to_string(&known_hosts_path).await.unwrap();
+        assert_eq!(KnownHostsService::parse_entries(&content).len(), 3);
+        assert!(!content.contains("other.org"));
+    }
+
+    // ========================================
+    // Parse cache tests
+    // ========================================
+
+    #[tokio::test]
+    async fn test_parse_cache_invalidates_on_modification() {
+        let temp = create_mock_ssh_dir(&format!("one.example.com {}\n", VALID_HOST_KEY)).await;
+        let known_hosts_path = temp.path().join(".ssh").join("known_hosts");
+
+        let first = KnownHostsService::load_cached(&known_hosts_path).await;
+        assert!(first.contains_key("one.example.com"));
+        let again = KnownHostsService::load_cached(&known_hosts_path).await;
+        assert_eq!(again, first);
+
+        fs::write(
+            &known_hosts_path,
+            format!("two.example.com,2.example.com {}\n", VALID_HOST_KEY),
+        )
+        .await
+        .unwrap();
+        let edited = KnownHostsService::load_cached(&known_hosts_path).await;
+        assert!(!edited.contains_key("one.example.com"));
+        assert!(edited.contains_key("two.example.com"));
+
+        fs::remove_file(&known_hosts_path).await.unwrap();
+        assert!(KnownHostsService::load_cached(&known_hosts_path)
+            .await
+            .is_empty());
+    }
 }
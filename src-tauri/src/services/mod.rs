@@ -1,14 +1,34 @@
 pub mod agent_service;
+pub mod cleanup_service;
+pub mod fingerprint_search;
+pub mod inventory;
 pub mod key_manager;
+pub mod key_usage;
 pub mod known_hosts;
 pub mod permission_service;
 pub mod ssh_connection;
+pub mod system_tools;
 
-pub use agent_service::{AddKeyResult, AgentKeyInfo, AgentService, RemoveKeyResult};
-pub use key_manager::{GenerateKeyOptions, KeyManager};
+pub use agent_service::{
+    AddKeyResult, AgentEnvironment, AgentKeyInfo, AgentKeyOrderEntry, AgentPing, AgentService,
+    AgentSettings, AgentSignTest, RemoveKeyResult,
+};
+pub use cleanup_service::{CleanupItem, CleanupService};
+pub use fingerprint_search::{FingerprintLocation, FingerprintSearch};
+pub use inventory::{InventoryService, SshDirInventory};
+pub use key_manager::{GenerateKeyOptions, KeyManager, KeySettings};
+pub use key_usage::KeyUsageStore;
 pub use known_hosts::{
-    AddHostResult as KnownHostAddResult, KnownHostsService,
-    RemoveHostResult as KnownHostRemoveResult,
+    AddHostResult as KnownHostAddResult, AnnotatedLine, HostScanProgress, KnownHostStatusDto,
+    KnownHostsDuplicates, KnownHostsService, PlatformKeyStatus,
+    RemoveHostResult as KnownHostRemoveResult, ResolvedHost, ScannedKey, SshfpVerification,
+    VerifiedHostResult,
+};
+pub use permission_service::{
+    InsecureKey, InsecureKeyFix, PermissionCheckResult, PermissionFixResult, PermissionService,
+};
+pub use ssh_connection::{
+    AuthMethodsProbe, ConnectionPreflight, ConnectionTestResult, InstallKeyResult, Motd,
+    SshConnectionService, WorkingKeyResult,
 };
-pub use permission_service::{PermissionCheckResult, PermissionFixResult, PermissionService};
-pub use ssh_connection::{ConnectionTestResult, SshConnectionService};
+pub use system_tools::{SshCapabilities, SystemTools};
@@ -1,6 +1,8 @@
 use crate::models::{SshBuddyError, SshResult};
+use crate::services::KeyManager;
+use crate::utils::{canonical_ssh_dir, ssh_dir};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
@@ -13,6 +15,8 @@ pub struct PermissionCheckResult {
     pub current_mode: Option<String>,
     pub expected_mode: String,
     pub message: String,
+    /// Resolved target when the checked path is a symlink; the mode is the target's
+    pub symlink_target: Option<String>,
 }
 
 /// Permission fix result
@@ -24,6 +28,24 @@ pub struct PermissionFixResult {
     pub new_mode: Option<String>,
 }
 
+/// A private key whose permissions are too open
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InsecureKey {
+    pub name: String,
+    pub path: String,
+    /// Octal mode such as "644" ("ACL" on Windows)
+    pub current_mode: Option<String>,
+}
+
+/// Outcome of fixing one insecure key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InsecureKeyFix {
+    pub path: String,
+    pub result: PermissionFixResult,
+}
+
 /// Permission service
 pub struct PermissionService;
 
@@ -39,6 +61,8 @@ impl PermissionService {
             });
         }
 
+        // metadata() follows the link, so a symlinked key reports its target's mode
+        let symlink_target = Self::symlink_target(path);
         let metadata = std::fs::metadata(path).map_err(|e| SshBuddyError::IoError {
             message: format!("Failed to read file metadata: {}", e),
         })?;
@@ -55,14 +79,18 @@ impl PermissionService {
             is_valid,
             current_mode: Some(mode_str.clone()),
             expected_mode,
-            message: if is_valid {
-                "Key permissions are correct".to_string()
-            } else {
-                format!(
-                    "Key permissions are {} but should be 600. File is too accessible.",
-                    mode_str
-                )
-            },
+            message: Self::with_symlink_note(
+                if is_valid {
+                    "Key permissions are correct".to_string()
+                } else {
+                    format!(
+                        "Key permissions are {} but should be 600. File is too accessible.",
+                        mode_str
+                    )
+                },
+                &symlink_target,
+            ),
+            symlink_target,
         })
     }
 
@@ -91,6 +119,7 @@ impl PermissionService {
                 current_mode: None,
                 expected_mode: "User only".to_string(),
                 message: "Failed to check file permissions".to_string(),
+                symlink_target: None,
             });
         }
 
@@ -125,19 +154,24 @@ impl PermissionService {
         }
 
         let is_valid = user_has_access && !has_other_users;
+        let symlink_target = Self::symlink_target(path);
 
         Ok(PermissionCheckResult {
             is_valid,
             current_mode: Some("ACL".to_string()),
             expected_mode: "User only".to_string(),
-            message: if is_valid {
-                "Key permissions are correct (restricted to current user)".to_string()
-            } else if has_other_users {
-                "Key file is accessible by other users. Consider restricting permissions."
-                    .to_string()
-            } else {
-                "Unable to verify key permissions".to_string()
-            },
+            message: Self::with_symlink_note(
+                if is_valid {
+                    "Key permissions are correct (restricted to current user)".to_string()
+                } else if has_other_users {
+                    "Key file is accessible by other users. Consider restricting permissions."
+                        .to_string()
+                } else {
+                    "Unable to verify key permissions".to_string()
+                },
+                &symlink_target,
+            ),
+            symlink_target,
         })
     }
 
@@ -152,7 +186,11 @@ impl PermissionService {
             });
         }
 
-        // Set permissions to 600
+        if let Some(refusal) = Self::refuse_symlink_outside_ssh_dir(path) {
+            return Ok(refusal);
+        }
+
+        // Set permissions to 600 (on the target, for a symlink inside ~/.ssh)
         let permissions = std::fs::Permissions::from_mode(0o600);
         std::fs::set_permissions(path, permissions).map_err(|e| SshBuddyError::IoError {
             message: format!("Failed to set permissions: {}", e),
@@ -183,6 +221,10 @@ impl PermissionService {
             });
         }
 
+        if let Some(refusal) = Self::refuse_symlink_outside_ssh_dir(path) {
+            return Ok(refusal);
+        }
+
         let current_user = whoami::username();
 
         // Windows requires icacls or PowerShell to set ACL
@@ -226,12 +268,113 @@ impl PermissionService {
         }
     }
 
+    /// Private keys in ~/.ssh whose permissions are not 600 (user-only on Windows)
+    pub async fn list_insecure_keys() -> SshResult<Vec<InsecureKey>> {
+        let paths = KeyManager::new()?.list_private_key_paths().await?;
+        Ok(Self::insecure_keys(&paths).await)
+    }
+
+    /// Restrict the permissions of every insecure private key
+    /// Each key gets its own result, so one failure doesn't hide the others
+    pub async fn fix_all_insecure_keys() -> SshResult<Vec<InsecureKeyFix>> {
+        let mut fixes = Vec::new();
+        for key in Self::list_insecure_keys().await? {
+            let result = match Self::fix_key_permissions(&key.path).await {
+                Ok(result) => result,
+                Err(e) => PermissionFixResult {
+                    success: false,
+                    message: e.to_string(),
+                    new_mode: None,
+                },
+            };
+            fixes.push(InsecureKeyFix {
+                path: key.path,
+                result,
+            });
+        }
+        Ok(fixes)
+    }
+
+    /// The keys among `paths` that fail `check_key_permissions`
+    async fn insecure_keys(paths: &[PathBuf]) -> Vec<InsecureKey> {
+        let mut insecure = Vec::new();
+        for path in paths {
+            let path_str = path.to_string_lossy().to_string();
+            match Self::check_key_permissions(&path_str).await {
+                Ok(check) if !check.is_valid => insecure.push(InsecureKey {
+                    name: path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    path: path_str,
+                    current_mode: check.current_mode,
+                }),
+                Ok(_) => {}
+                Err(e) => log::warn!("[permission_service] Could not check {}: {}", path_str, e),
+            }
+        }
+        insecure
+    }
+
+    /// Resolved target of a symlinked key, None for regular files
+    fn symlink_target(path: &Path) -> Option<String> {
+        let is_symlink = std::fs::symlink_metadata(path)
+            .map(|metadata| metadata.file_type().is_symlink())
+            .unwrap_or(false);
+        if !is_symlink {
+            return None;
+        }
+        std::fs::canonicalize(path)
+            .ok()
+            .map(|target| target.to_string_lossy().to_string())
+    }
+
+    fn with_symlink_note(message: String, symlink_target: &Option<String>) -> String {
+        match symlink_target {
+            Some(target) => format!(
+                "{} (symlink to {}; the target's permissions were checked)",
+                message, target
+            ),
+            None => message,
+        }
+    }
+
+    /// Changing permissions through a link would modify a file outside ~/.ssh
+    /// (e.g. on a password manager mount), so that is left to the user
+    fn refuse_symlink_outside_ssh_dir(path: &Path) -> Option<PermissionFixResult> {
+        let ssh_dir = ssh_dir().ok()?;
+        Self::symlink_fix_refusal(path, &ssh_dir)
+    }
+
+    fn symlink_fix_refusal(path: &Path, ssh_dir: &Path) -> Option<PermissionFixResult> {
+        let target = Self::symlink_target(path)?;
+        let inside_ssh_dir = canonical_ssh_dir(ssh_dir)
+            .map(|ssh_dir| Path::new(&target).starts_with(ssh_dir))
+            .unwrap_or(false);
+        if inside_ssh_dir {
+            return None;
+        }
+
+        log::warn!(
+            "[permission_service] Not changing permissions of {} through symlink {}",
+            target,
+            path.display()
+        );
+        Some(PermissionFixResult {
+            success: false,
+            message: format!(
+                "{} is a symlink to {}, which is outside ~/.ssh. Restrict the target's permissions where it is managed.",
+                path.display(),
+                target
+            ),
+            new_mode: None,
+        })
+    }
+
     /// Check SSH directory permissions
     #[cfg(unix)]
     pub async fn check_ssh_dir_permissions() -> SshResult<PermissionCheckResult> {
-        let ssh_dir = dirs::home_dir()
-            .ok_or(SshBuddyError::HomeDirNotFound)?
-            .join(".ssh");
+        let ssh_dir = ssh_dir()?;
 
         if !ssh_dir.exists() {
             return Ok(PermissionCheckResult {
@@ -239,6 +382,7 @@ impl PermissionService {
                 current_mode: None,
                 expected_mode: "700".to_string(),
                 message: "SSH directory does not exist".to_string(),
+                symlink_target: None,
             });
         }
 
@@ -265,14 +409,13 @@ impl PermissionService {
                     mode_str
                 )
             },
+            symlink_target: None,
         })
     }
 
     #[cfg(windows)]
     pub async fn check_ssh_dir_permissions() -> SshResult<PermissionCheckResult> {
-        let ssh_dir = dirs::home_dir()
-            .ok_or(SshBuddyError::HomeDirNotFound)?
-            .join(".ssh");
+        let ssh_dir = ssh_dir()?;
 
         if !ssh_dir.exists() {
             return Ok(PermissionCheckResult {
@@ -280,6 +423,7 @@ impl PermissionService {
                 current_mode: None,
                 expected_mode: "User only".to_string(),
                 message: "SSH directory does not exist".to_string(),
+                symlink_target: None,
             });
         }
 
@@ -299,6 +443,7 @@ impl PermissionService {
                 current_mode: None,
                 expected_mode: "User only".to_string(),
                 message: "Failed to check directory permissions".to_string(),
+                symlink_target: None,
             });
         }
 
@@ -309,15 +454,14 @@ impl PermissionService {
             current_mode: Some("ACL".to_string()),
             expected_mode: "User only".to_string(),
             message: "SSH directory exists with Windows ACL permissions".to_string(),
+            symlink_target: None,
         })
     }
 
     /// Fix SSH directory permissions
     #[cfg(unix)]
     pub async fn fix_ssh_dir_permissions() -> SshResult<PermissionFixResult> {
-        let ssh_dir = dirs::home_dir()
-            .ok_or(SshBuddyError::HomeDirNotFound)?
-            .join(".ssh");
+        let ssh_dir = ssh_dir()?;
 
         if !ssh_dir.exists() {
             // Create directory
@@ -341,9 +485,7 @@ impl PermissionService {
 
     #[cfg(windows)]
     pub async fn fix_ssh_dir_permissions() -> SshResult<PermissionFixResult> {
-        let ssh_dir = dirs::home_dir()
-            .ok_or(SshBuddyError::HomeDirNotFound)?
-            .join(".ssh");
+        let ssh_dir = ssh_dir()?;
 
         // Create directory if it doesn't exist
         if !ssh_dir.exists() {
@@ -395,3 +537,82 @@ impl PermissionService {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    // ============================================
+    // Symlinked keys
+    // ============================================
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinked_key_outside_ssh_dir() {
+        let temp = TempDir::new().unwrap();
+        let ssh_dir = temp.path().join(".ssh");
+        let vault = temp.path().join("vault");
+        std::fs::create_dir_all(&ssh_dir).unwrap();
+        std::fs::create_dir_all(&vault).unwrap();
+
+        let target = vault.join("id_ed25519");
+        std::fs::write(&target, "key").unwrap();
+        std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let link = ssh_dir.join("id_ed25519");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let check = PermissionService::check_key_permissions(&link.to_string_lossy())
+            .await
+            .unwrap();
+        assert!(!check.is_valid);
+        assert_eq!(check.current_mode.as_deref(), Some("644"));
+        let resolved = target.canonicalize().unwrap().to_string_lossy().to_string();
+        assert_eq!(check.symlink_target.as_deref(), Some(resolved.as_str()));
+        assert!(check.message.contains("symlink to"));
+
+        let refusal = PermissionService::symlink_fix_refusal(&link, &ssh_dir).unwrap();
+        assert!(!refusal.success);
+        assert!(refusal.message.contains("outside ~/.ssh"));
+
+        // A link to another file inside ~/.ssh may be fixed through the link
+        let inner = ssh_dir.join("id_alias");
+        std::fs::write(ssh_dir.join("id_real"), "key").unwrap();
+        std::os::unix::fs::symlink(ssh_dir.join("id_real"), &inner).unwrap();
+        assert!(PermissionService::symlink_fix_refusal(&inner, &ssh_dir).is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_insecure_keys() {
+        let temp = TempDir::new().unwrap();
+        let mut paths = Vec::new();
+        for (name, mode) in [("id_open", 0o644), ("id_ok", 0o600), ("id_group", 0o640)] {
+            let path = temp.path().join(name);
+            std::fs::write(&path, "key").unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+            paths.push(path);
+        }
+        paths.push(temp.path().join("id_missing"));
+
+        let insecure = PermissionService::insecure_keys(&paths).await;
+        let found: Vec<(&str, Option<&str>)> = insecure
+            .iter()
+            .map(|key| (key.name.as_str(), key.current_mode.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![("id_open", Some("644")), ("id_group", Some("640"))]
+        );
+    }
+
+    #[test]
+    fn test_regular_file_is_not_symlink() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("id_ed25519");
+        std::fs::write(&path, "key").unwrap();
+
+        assert_eq!(PermissionService::symlink_target(&path), None);
+        assert!(PermissionService::symlink_fix_refusal(&path, temp.path()).is_none());
+    }
+}
@@ -1,15 +1,23 @@
-use crate::models::{SshBuddyError, SshResult};
-use crate::utils::{HostConfig, SshConfigParser};
+use crate::models::{CertInfo, SshBuddyError, SshResult};
+use crate::services::known_hosts::{KnownHostKeys, KnownHostStatus};
+use crate::services::{AgentService, KeyManager, KeyUsageStore, KnownHostsService, SystemTools};
+use crate::utils::{
+    atomic_write, expand_path, ssh_dir, validate_hostname, validate_key_name,
+    validate_path_in_ssh_dir, ConfigConflict, EffectiveValue, HostConfig, OpenSshKeyEnvelope,
+    SshConfigParser,
+};
 use async_trait::async_trait;
-use russh::keys::key::PublicKey;
-use russh::{client, ChannelMsg};
+use russh::keys::key::{self, KeyPair, PublicKey};
+use russh::{client, compression, kex, ChannelMsg, Preferred};
 use russh_keys::agent::client::AgentClient;
 use russh_keys::PublicKeyBase64;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::fs;
 use tokio::sync::Mutex;
 use tokio::time::timeout;
@@ -34,6 +42,9 @@ pub enum SshErrorType {
     DnsFailed,
     IdentityFileNotFound,
     PublicKeyMissing,
+    CertificateExpired,
+    CertificatePrincipalMismatch,
+    RateLimited,
     Unknown,
 }
 
@@ -48,11 +59,208 @@ pub struct SshErrorDetails {
     pub can_auto_fix: bool,
     pub fix_type: Option<String>,
     pub fix_params: Option<std::collections::HashMap<String, String>>,
+    /// Follow-up actions the UI can render as buttons, identified by stable IDs
+    pub suggested_actions: Vec<SuggestedAction>,
 }
 
-/// SSH connection test result
+/// Suggested follow-up action for an SSH error
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SuggestedAction {
+    /// Stable action identifier (e.g. "open-docs", "copy-public-key")
+    pub id: String,
+    /// Default English label (the frontend may localize by id)
+    pub label: String,
+    pub params: Option<HashMap<String, String>>,
+}
+
+impl SuggestedAction {
+    fn new(id: &str, label: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            label: label.to_string(),
+            params: None,
+        }
+    }
+
+    fn with_param(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.params
+            .get_or_insert_with(HashMap::new)
+            .insert(key.to_string(), value.into());
+        self
+    }
+}
+
+/// Where an offered key came from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthKeySource {
+    File,
+    Agent,
+}
+
+/// What the server made of an offered key
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthOutcome {
+    Accepted,
+    Rejected,
+    Error,
+    /// Not offered: encrypted, or excluded by PubkeyAcceptedAlgorithms
+    Skipped,
+}
+
+impl AuthOutcome {
+    /// Outcome of an authentication call, with the error message if it failed
+    fn of<E: std::fmt::Display>(result: &Result<bool, E>) -> (Self, Option<String>) {
+        match result {
+            Ok(true) => (Self::Accepted, None),
+            Ok(false) => (Self::Rejected, None),
+            Err(e) => (Self::Error, Some(e.to_string())),
+        }
+    }
+}
+
+/// A key offered during a connection test, like the "Offering public key" lines of `ssh -v`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthAttempt {
+    /// SHA256 fingerprint of the offered key
+    pub fingerprint: String,
+    pub source: AuthKeySource,
+    pub outcome: AuthOutcome,
+    /// Error message when the outcome is Error
+    pub message: Option<String>,
+}
+
+impl AuthAttempt {
+    fn new<E: std::fmt::Display>(
+        public_key: &PublicKey,
+        source: AuthKeySource,
+        result: &Result<bool, E>,
+    ) -> Self {
+        let (outcome, message) = AuthOutcome::of(result);
+        Self {
+            fingerprint: format!("SHA256:{}", public_key.fingerprint()),
+            source,
+            outcome,
+            message,
+        }
+    }
+}
+
+/// How a host responded to one local key
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyProbe {
+    /// Private key file name
+    pub name: String,
+    pub path: String,
+    /// SHA256 fingerprint, when the key could be loaded
+    pub fingerprint: Option<String>,
+    pub outcome: AuthOutcome,
+    /// Why the key was skipped or failed
+    pub message: Option<String>,
+}
+
+/// Which local key a host accepts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkingKeyResult {
+    pub hostname: String,
+    pub user: String,
+    /// First key the host accepted; None if none worked
+    pub working_key: Option<KeyProbe>,
+    /// Every key tried, in order
+    pub attempts: Vec<KeyProbe>,
+}
+
+/// Authentication methods a server offers, before any credential is sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthMethodsProbe {
+    pub hostname: String,
+    pub port: u16,
+    pub user: String,
+    /// As the server names them, e.g. "publickey", "password", "keyboard-interactive";
+    /// just "none" if the server let the user in without authenticating
+    pub methods: Vec<String>,
+}
+
+/// How many keys a connection to a host would offer, checked before connecting
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionPreflight {
+    pub hostname: String,
+    pub port: u16,
+    /// Identities in the agent; None when the agent couldn't be reached
+    pub agent_key_count: Option<usize>,
+    /// Key files the config would offer: the IdentityFile, or the default keys that exist
+    pub config_key_count: usize,
+    pub identities_only: bool,
+    /// Upper bound on the keys offered; agent keys are left out with IdentitiesOnly
+    pub offered_key_count: usize,
+    pub likely_too_many_keys: bool,
+    pub suggestion: Option<String>,
+}
+
+/// Result of installing a public key on a remote host
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct InstallKeyResult {
+    /// The key was appended to authorized_keys
+    pub added: bool,
+    /// The key was already in authorized_keys
+    pub already_present: bool,
+    pub message: String,
+}
+
+/// Output read from a login shell right after connecting, shown verbatim
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Motd {
+    pub motd: String,
+    /// Output was cut at MOTD_MAX_BYTES
+    pub truncated: bool,
+}
+
+/// Most MOTD output kept; anything after is dropped
+const MOTD_MAX_BYTES: usize = 64 * 1024;
+/// The MOTD is taken as complete once the shell is silent this long
+const MOTD_QUIET_PERIOD: Duration = Duration::from_millis(1500);
+/// Upper limit on reading the MOTD, for shells that never go quiet
+const MOTD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Markers printed by the remote install command
+const KEY_ADDED_MARKER: &str = "SSH_BUDDY_KEY_ADDED";
+const KEY_PRESENT_MARKER: &str = "SSH_BUDDY_KEY_PRESENT";
+
+/// At most RATE_LIMIT_MAX_TESTS tests per host within RATE_LIMIT_WINDOW, so repeated
+/// clicks don't get the user temporarily blocked by the server
+const RATE_LIMIT_MAX_TESTS: usize = 5;
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Start times of recent tests, per host:port
+static RECENT_TESTS: std::sync::OnceLock<std::sync::Mutex<HashMap<String, VecDeque<Instant>>>> =
+    std::sync::OnceLock::new();
+
+/// Keys offered per connection when searching for a working key; stays below
+/// sshd's default MaxAuthTries of 6, after which the server disconnects
+const KEYS_PER_CONNECTION: usize = 5;
+
+/// Limit for the `ssh` run that probes a server's authentication methods
+const AUTH_PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// sshd's default MaxAuthTries: offering more keys than this before the right one
+/// ends in "Too many authentication failures"
+const TYPICAL_MAX_AUTH_TRIES: usize = 6;
+
+/// Keys tried, in order, when a host has no IdentityFile
+const DEFAULT_IDENTITY_FILES: [&str; 3] = ["id_ed25519", "id_rsa", "id_ecdsa"];
+
+/// SSH connection test result
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ConnectionTestResult {
     pub success: bool,
     pub output: String,
@@ -60,20 +268,32 @@ pub struct ConnectionTestResult {
     pub error_type: Option<SshErrorType>,
     pub error_details: Option<SshErrorDetails>,
     pub host_to_remove: Option<String>,
+    /// Port of the entries `host_to_remove` names, so only that port's entries are removed
+    pub port_to_remove: Option<u16>,
     pub host_to_add: Option<String>,
+    /// known_hosts file that holds (or should hold) the host's key, for the auto-fix commands
+    pub known_hosts_file: Option<String>,
     pub identity_file: Option<String>,
+    /// Randomart of the server's host key (SHA256), for visual comparison
+    pub host_key_randomart: Option<String>,
+    /// Keys offered to the server, in order
+    pub auth_attempts: Vec<AuthAttempt>,
     pub debug_log: Option<String>,
+    /// SSH user the server or platform seems to expect, when the one used looks wrong
+    pub suggested_user: Option<String>,
 }
 
-/// Known hosts check result
-#[derive(Debug, Clone, PartialEq)]
-enum KnownHostStatus {
-    /// Host is in known_hosts and key matches
-    Matched,
-    /// Host is not in known_hosts (first connection)
-    Unknown,
-    /// Host is in known_hosts but key is different (possible attack or server reinstall)
-    Changed,
+impl ConnectionTestResult {
+    /// A failed test showing `output`, with the error behind it
+    fn failure(output: String, error_details: SshErrorDetails) -> Self {
+        Self {
+            success: false,
+            output,
+            error_type: Some(error_details.error_type.clone()),
+            error_details: Some(error_details),
+            ..Self::default()
+        }
+    }
 }
 
 /// Shared Host Key check state
@@ -92,6 +312,196 @@ impl Default for SharedHostKeyState {
     }
 }
 
+/// Why a connection could not be established
+enum ConnectFailure {
+    Timeout,
+    Failed(String),
+}
+
+/// A host's HostKeyAlgorithms / PubkeyAcceptedAlgorithms, resolved for russh
+struct AlgorithmPreferences {
+    /// Passed to `client::Config`
+    preferred: Preferred,
+    /// russh has no client-side list for this, so the identity key is checked against it
+    accepted_pubkey: Option<Vec<key::Name>>,
+    /// Requested algorithms that cannot be honored
+    notes: Vec<String>,
+}
+
+impl AlgorithmPreferences {
+    fn from_host_config(host_config: &HostConfig) -> Self {
+        let mut preferred = Preferred::DEFAULT;
+        let mut notes = Vec::new();
+
+        if let Some(ref spec) = host_config.host_key_algorithms {
+            if let Some(names) = resolve_preference(
+                "HostKeyAlgorithms",
+                spec,
+                &Preferred::DEFAULT.key,
+                &mut notes,
+            ) {
+                preferred.key = Cow::Owned(names);
+            }
+        }
+        if let Some(ref spec) = host_config.ciphers {
+            if let Some(names) =
+                resolve_preference("Ciphers", spec, &Preferred::DEFAULT.cipher, &mut notes)
+            {
+                preferred.cipher = Cow::Owned(names);
+            }
+        }
+        if let Some(ref spec) = host_config.macs {
+            if let Some(names) =
+                resolve_preference("MACs", spec, &Preferred::DEFAULT.mac, &mut notes)
+            {
+                preferred.mac = Cow::Owned(names);
+            }
+        }
+        if let Some(ref spec) = host_config.kex_algorithms {
+            if let Some(mut names) =
+                resolve_preference("KexAlgorithms", spec, &Preferred::DEFAULT.kex, &mut notes)
+            {
+                // Protocol extension markers ride in the kex list; OpenSSH adds them too
+                for marker in [
+                    kex::EXTENSION_SUPPORT_AS_CLIENT,
+                    kex::EXTENSION_OPENSSH_STRICT_KEX_AS_CLIENT,
+                ] {
+                    if !names.contains(&marker) {
+                        names.push(marker);
+                    }
+                }
+                preferred.kex = Cow::Owned(names);
+            }
+        }
+        match host_config
+            .compression
+            .as_deref()
+            .map(str::to_lowercase)
+            .as_deref()
+        {
+            Some("yes") => {
+                preferred.compression = Cow::Owned(vec![
+                    compression::ZLIB_LEGACY,
+                    compression::ZLIB,
+                    compression::NONE,
+                ])
+            }
+            Some("no") => preferred.compression = Cow::Owned(vec![compression::NONE]),
+            Some(other) => notes.push(format!("Compression {} not understood, skipped", other)),
+            None => {}
+        }
+
+        let accepted_pubkey = host_config.pubkey_accepted_algorithms.as_ref().map(|spec| {
+            let (names, unsupported) = resolve_algorithm_list(spec, &Preferred::DEFAULT.key);
+            if !unsupported.is_empty() {
+                notes.push(format!(
+                    "PubkeyAcceptedAlgorithms not supported, skipped: {}",
+                    unsupported.join(",")
+                ));
+            }
+            names
+        });
+
+        Self {
+            preferred,
+            accepted_pubkey,
+            notes,
+        }
+    }
+
+    /// Whether OpenSSH would offer this key; RSA keys match any RSA signature algorithm
+    fn accepts_key(&self, key_pair: &KeyPair) -> bool {
+        let is_rsa = |name: &str| {
+            [key::SSH_RSA, key::RSA_SHA2_256, key::RSA_SHA2_512]
+                .iter()
+                .any(|rsa| rsa.0 == name)
+        };
+        let name = key_pair.name();
+
+        match self.accepted_pubkey {
+            Some(ref accepted) => accepted
+                .iter()
+                .any(|n| n.0 == name || (is_rsa(n.0) && is_rsa(name))),
+            None => true,
+        }
+    }
+}
+
+/// Resolve one algorithm directive for `client::Config`, noting the names that are skipped
+/// None when nothing supported is left, so russh's defaults stay in place
+fn resolve_preference<N>(
+    directive: &str,
+    spec: &str,
+    defaults: &[N],
+    notes: &mut Vec<String>,
+) -> Option<Vec<N>>
+where
+    N: Copy + PartialEq + AsRef<str> + for<'a> TryFrom<&'a str>,
+{
+    let (names, unsupported) = resolve_algorithm_list(spec, defaults);
+    if !unsupported.is_empty() {
+        notes.push(format!(
+            "{} not supported, skipped: {}",
+            directive,
+            unsupported.join(",")
+        ));
+    }
+    if names.is_empty() {
+        notes.push(format!("No supported {} left; using defaults", directive));
+        None
+    } else {
+        Some(names)
+    }
+}
+
+/// Resolve an OpenSSH algorithm list against defaults: "a,b" replaces them,
+/// "+a" appends, "-a" removes and "^a" moves to the front
+/// Returns the resolved names and the requested ones russh does not implement
+/// "none" and "clear" are never accepted, so a config can't turn off encryption
+fn resolve_algorithm_list<N>(spec: &str, defaults: &[N]) -> (Vec<N>, Vec<String>)
+where
+    N: Copy + PartialEq + AsRef<str> + for<'a> TryFrom<&'a str>,
+{
+    let (modifier, list) = match spec.chars().next() {
+        Some(c @ ('+' | '-' | '^')) => (Some(c), &spec[1..]),
+        _ => (None, spec),
+    };
+
+    let mut requested = Vec::new();
+    let mut unsupported = Vec::new();
+    for name in list.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        match N::try_from(name) {
+            Ok(n) if !matches!(n.as_ref(), "none" | "clear") => requested.push(n),
+            _ => unsupported.push(name.to_string()),
+        }
+    }
+
+    let resolved = match modifier {
+        Some('+') => {
+            let mut names = defaults.to_vec();
+            names.extend(requested.into_iter().filter(|n| !defaults.contains(n)));
+            names
+        }
+        Some('-') => {
+            // Removing an algorithm russh lacks anyway is not worth a note
+            unsupported.clear();
+            defaults
+                .iter()
+                .filter(|n| !requested.contains(n))
+                .copied()
+                .collect()
+        }
+        Some('^') => {
+            let mut names = requested.clone();
+            names.extend(defaults.iter().filter(|n| !requested.contains(n)));
+            names
+        }
+        _ => requested,
+    };
+
+    (resolved, unsupported)
+}
+
 /// SSH client handler
 struct ClientHandler {
     server_public_key: Option<PublicKey>,
@@ -101,7 +511,7 @@ struct ClientHandler {
     /// Port
     port: u16,
     /// Pre-loaded keys from known_hosts
-    known_host_keys: HashMap<String, Vec<String>>,
+    known_host_keys: KnownHostKeys,
     /// Shared state (readable from outside)
     shared_state: Arc<Mutex<SharedHostKeyState>>,
 }
@@ -110,7 +520,7 @@ impl ClientHandler {
     fn new(
         hostname: &str,
         port: u16,
-        known_host_keys: HashMap<String, Vec<String>>,
+        known_host_keys: KnownHostKeys,
         shared_state: Arc<Mutex<SharedHostKeyState>>,
     ) -> Self {
         Self {
@@ -150,88 +560,30 @@ impl client::Handler for ClientHandler {
             &server_key_base64[..server_key_base64.len().min(50)]
         );
 
-        // Construct possible host key names
-        let host_variants = if self.port == 22 {
-            vec![self.hostname.clone()]
-        } else {
-            vec![
-                format!("[{}]:{}", self.hostname, self.port),
-                self.hostname.clone(),
-            ]
-        };
-
-        log::info!(
-            "[ssh_connection] Looking for host variants: {:?}",
-            host_variants
-        );
         log::info!(
             "[ssh_connection] Known hosts keys count: {}",
             self.known_host_keys.len()
         );
 
-        // Check if in known_hosts
-        let mut found_host = false;
-        let mut key_matched = false;
+        let status = KnownHostsService::check_host_key(
+            &self.known_host_keys,
+            &self.hostname,
+            self.port,
+            &server_key_base64,
+        );
 
-        for variant in &host_variants {
-            log::info!("[ssh_connection] Checking variant: {}", variant);
-            if let Some(known_keys) = self.known_host_keys.get(variant) {
-                found_host = true;
-                log::info!(
-                    "[ssh_connection] Found {} keys for host {}",
-                    known_keys.len(),
-                    variant
-                );
-                // Check if key matches
-                for (idx, known_key) in known_keys.iter().enumerate() {
-                    log::info!(
-                        "[ssh_connection] Comparing with known key {}: {}...",
-                        idx,
-                        &known_key[..known_key.len().min(80)]
-                    );
-                    log::info!(
-                        "[ssh_connection] Server key base64 (first 80): {}...",
-                        &server_key_base64[..server_key_base64.len().min(80)]
-                    );
-
-                    let contains_base64 = known_key.contains(&server_key_base64);
-                    let exact_match = server_key_full == *known_key;
-                    let type_and_base64 = known_key.starts_with(&format!("{} ", server_key_type))
-                        && known_key.contains(&server_key_base64);
-
-                    log::info!(
-                        "[ssh_connection] Check results - contains_base64: {}, exact_match: {}, type_and_base64: {}",
-                        contains_base64, exact_match, type_and_base64
-                    );
-
-                    if contains_base64 || exact_match || type_and_base64 {
-                        log::info!("[ssh_connection] Key MATCHED!");
-                        key_matched = true;
-                        break;
-                    }
-                }
-                if key_matched {
-                    break;
-                }
-            } else {
-                log::info!("[ssh_connection] No keys found for variant: {}", variant);
+        match status {
+            KnownHostStatus::Matched => {
+                log::info!("[ssh_connection] Host key matched for {}", self.hostname)
             }
-        }
-
-        // Update shared state
-        let status = if key_matched {
-            log::info!("[ssh_connection] Host key matched for {}", self.hostname);
-            KnownHostStatus::Matched
-        } else if found_host {
-            log::warn!("[ssh_connection] Host key CHANGED for {}!", self.hostname);
-            KnownHostStatus::Changed
-        } else {
-            log::info!(
+            KnownHostStatus::Changed => {
+                log::warn!("[ssh_connection] Host key CHANGED for {}!", self.hostname)
+            }
+            KnownHostStatus::Unknown => log::info!(
                 "[ssh_connection] Host key unknown for {} (first time)",
                 self.hostname
-            );
-            KnownHostStatus::Unknown
-        };
+            ),
+        }
 
         // Store state in shared Arc
         {
@@ -260,109 +612,293 @@ pub struct SshConnectionService;
 impl SshConnectionService {
     /// Get SSH directory path
     fn get_ssh_dir() -> PathBuf {
-        dirs::home_dir()
-            .map(|h| h.join(".ssh"))
-            .unwrap_or_else(|| PathBuf::from("~/.ssh"))
+        ssh_dir().unwrap_or_else(|_| PathBuf::from("~/.ssh"))
     }
 
-    /// Load known_hosts file
-    async fn load_known_hosts() -> HashMap<String, Vec<String>> {
-        let mut known_hosts: HashMap<String, Vec<String>> = HashMap::new();
-        let known_hosts_path = Self::get_ssh_dir().join("known_hosts");
+    /// Parse ~/.ssh/config; a missing or unreadable file has no hosts
+    async fn read_config_hosts() -> Vec<HostConfig> {
+        let config_path = Self::get_ssh_dir().join("config");
 
-        log::info!(
-            "[ssh_connection] Loading known_hosts from: {:?}",
-            known_hosts_path
-        );
+        let config = if config_path.exists() {
+            fs::read_to_string(&config_path).await.unwrap_or_default()
+        } else {
+            String::new()
+        };
+
+        SshConfigParser::parse(&config)
+    }
 
-        if !known_hosts_path.exists() {
-            log::warn!("[ssh_connection] known_hosts file does not exist");
-            return known_hosts;
+    /// Replace ~/.ssh/config with `content`, refusing content with lines that don't parse
+    /// The current config is first copied to `config.<unix time>.bak`, whose path is
+    /// returned; there is no backup when there was no config
+    pub async fn write_config(content: &str) -> SshResult<Option<String>> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self::write_config_in(&ssh_dir()?, content, now).await
+    }
+
+    async fn write_config_in(ssh_dir: &Path, content: &str, now: u64) -> SshResult<Option<String>> {
+        let (_, warnings) = SshConfigParser::parse_with_diagnostics(content);
+        if let Some(error) = warnings.iter().find(|w| w.is_hard_error()) {
+            return Err(SshBuddyError::InvalidConfig {
+                message: format!("line {}: {}", error.line, error.message),
+            });
         }
 
-        let content = match fs::read_to_string(&known_hosts_path).await {
-            Ok(c) => c,
-            Err(e) => {
-                log::error!("[ssh_connection] Failed to read known_hosts: {}", e);
-                return known_hosts;
-            }
+        let config_path = ssh_dir.join("config");
+        let backup_path = if config_path.exists() {
+            // A symlinked config is written through (by atomic_write), as long as it stays in ~/.ssh
+            validate_path_in_ssh_dir(&config_path, ssh_dir)?;
+
+            let backup_path = ssh_dir.join(format!("config.{}.bak", now));
+            fs::copy(&config_path, &backup_path).await?;
+            log::info!("[ssh_connection] Backed up SSH config to {:?}", backup_path);
+            Some(backup_path.to_string_lossy().to_string())
+        } else {
+            None
         };
 
-        log::info!(
-            "[ssh_connection] known_hosts content length: {} bytes",
-            content.len()
-        );
+        atomic_write(&config_path, content.as_bytes(), 0o600).await?;
+        log::info!("[ssh_connection] Wrote SSH config: {:?}", config_path);
+        Ok(backup_path)
+    }
 
-        let mut line_count = 0;
-        let mut parsed_count = 0;
+    /// Options of the `Host *` block in ~/.ssh/config
+    pub async fn global_options() -> SshResult<BTreeMap<String, String>> {
+        let config_path = ssh_dir()?.join("config");
+        let content = if config_path.exists() {
+            fs::read_to_string(&config_path).await?
+        } else {
+            String::new()
+        };
+        Ok(SshConfigParser::global_options(&content))
+    }
 
-        for line in content.lines() {
-            line_count += 1;
-            let line = line.trim();
-            // Skip empty lines and comments
-            if line.is_empty() || line.starts_with('#') {
-                continue;
-            }
+    /// Set one option in the `Host *` block of ~/.ssh/config, creating the block if needed
+    /// Written like `write_config`, so the previous config is backed up and its path returned
+    pub async fn set_global_option(key: &str, value: &str) -> SshResult<Option<String>> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self::set_global_option_in(&ssh_dir()?, key, value, now).await
+    }
+
+    async fn set_global_option_in(
+        ssh_dir: &Path,
+        key: &str,
+        value: &str,
+        now: u64,
+    ) -> SshResult<Option<String>> {
+        SshConfigParser::validate_global_option(key, value)
+            .map_err(|message| SshBuddyError::InvalidConfig { message })?;
+
+        let config_path = ssh_dir.join("config");
+        let content = if config_path.exists() {
+            fs::read_to_string(&config_path).await?
+        } else {
+            String::new()
+        };
+        let updated = SshConfigParser::set_global_option(&content, key, value.trim());
+        Self::write_config_in(ssh_dir, &updated, now).await
+    }
+
+    /// Aliases in ~/.ssh/config whose IdentityFile is ~/.ssh/<key_name>
+    pub async fn hosts_using_key(key_name: &str) -> SshResult<Vec<String>> {
+        validate_key_name(key_name)?;
+        let key_path = Self::get_ssh_dir().join(key_name);
+        let hosts = Self::read_config_hosts().await;
+        Ok(SshConfigParser::hosts_using_key(&hosts, &key_path))
+    }
+
+    /// Read SSH config and resolve host
+    async fn resolve_host(host_alias: &str) -> SshResult<HostConfig> {
+        let hosts = Self::read_config_hosts().await;
+        if !SshConfigParser::has_host(&hosts, host_alias) {
+            return Self::parse_target(host_alias, &hosts);
+        }
+        let merged = SshConfigParser::merge_configs(&hosts, host_alias);
+
+        Ok(merged)
+    }
+
+    /// Config for a target with no Host entry, written as `host`, `user@host` or
+    /// `user@host:port` (IPv6 addresses in brackets when a port follows)
+    /// Config matching the host part still applies, as with `ssh user@host`
+    fn parse_target(target: &str, hosts: &[HostConfig]) -> SshResult<HostConfig> {
+        let invalid = |message: &str| SshBuddyError::InvalidPath {
+            message: format!("{}: {}", message, target),
+        };
 
-            // Skip hashed format (starts with |1|)
-            if line.starts_with("|1|") {
-                log::debug!(
-                    "[ssh_connection] Skipping hashed entry at line {}",
-                    line_count
+        let (user, host_port) = match target.rsplit_once('@') {
+            Some(("", _)) => return Err(invalid("Missing user before @")),
+            Some((user, rest)) => (Some(user), rest),
+            None => (None, target),
+        };
+
+        let (host, port) = match host_port.strip_prefix('[') {
+            Some(bracketed) => match bracketed.split_once(']') {
+                Some((addr, "")) => (addr, None),
+                Some((addr, rest)) => match rest.strip_prefix(':') {
+                    Some(port) => (addr, Some(port)),
+                    None => return Err(invalid("Unexpected text after IPv6 address")),
+                },
+                None => return Err(invalid("Unclosed [ in IPv6 address")),
+            },
+            // More than one colon is a bare IPv6 address, which cannot carry a port
+            None => match host_port.split_once(':') {
+                Some((host, port)) if !port.contains(':') => (host, Some(port)),
+                _ => (host_port, None),
+            },
+        };
+
+        validate_hostname(host)?;
+        let port = match port {
+            Some(port) => Some(
+                port.parse::<u16>()
+                    .map_err(|_| invalid("Invalid port in target"))?,
+            ),
+            None => None,
+        };
+
+        let mut config = SshConfigParser::merge_configs(hosts, host);
+        if config.hostname.is_none() {
+            config.hostname = Some(host.to_string());
+        }
+        if let Some(user) = user {
+            config.user = Some(user.to_string());
+        }
+        if port.is_some() {
+            config.port = port;
+        }
+        Ok(config)
+    }
+
+    /// Per-host settings of ~/.ssh/config that a later, broader Host block overrides
+    pub async fn config_conflicts() -> Vec<ConfigConflict> {
+        let hosts = Self::read_config_hosts().await;
+        let conflicts = SshConfigParser::conflicts(&hosts);
+        log::info!("[ssh_connection] {} config conflict(s)", conflicts.len());
+        conflicts
+    }
+
+    /// Effective configuration of a host as `test_connection` sees it, like `ssh -G`
+    /// Includes the defaults it falls back to for User and IdentityFile
+    pub async fn effective_config(host_alias: &str) -> SshResult<BTreeMap<String, EffectiveValue>> {
+        let host_config = Self::resolve_host(host_alias).await?;
+        let mut options = host_config.effective_options();
+
+        options
+            .entry("user".to_string())
+            .or_insert_with(|| EffectiveValue {
+                value: "git".to_string(),
+                line: None,
+            });
+        if !options.contains_key("identityfile") {
+            if let Ok(path) = Self::resolve_identity_file(&host_config) {
+                options.insert(
+                    "identityfile".to_string(),
+                    EffectiveValue {
+                        value: path.to_string_lossy().to_string(),
+                        line: None,
+                    },
                 );
-                continue;
             }
+        }
 
-            // Format: hostname[,hostname2,...] key-type key [comment]
-            let parts: Vec<&str> = line.splitn(3, ' ').collect();
-            if parts.len() < 2 {
-                log::debug!("[ssh_connection] Skipping malformed line {}", line_count);
-                continue;
-            }
+        Ok(options)
+    }
 
-            let hostnames = parts[0];
-            let key_data = if parts.len() >= 2 {
-                parts[1..].join(" ")
-            } else {
-                continue;
-            };
+    /// Resolve an explicitly requested identity file
+    /// Accepts a bare file name (relative to ~/.ssh), a "~/" path or an absolute path,
+    /// and rejects anything that does not exist inside the SSH directory
+    fn resolve_identity_override(identity_file: &str, ssh_dir: &Path) -> SshResult<PathBuf> {
+        let path = expand_path(identity_file)?;
+        let path = if path.is_absolute() {
+            path
+        } else {
+            ssh_dir.join(path)
+        };
 
-            // There may be multiple hostnames
-            for hostname in hostnames.split(',') {
-                let hostname = hostname.trim();
-                known_hosts
-                    .entry(hostname.to_string())
-                    .or_default()
-                    .push(key_data.clone());
-                parsed_count += 1;
+        validate_path_in_ssh_dir(&path, ssh_dir)?;
+        Ok(path)
+    }
+
+    /// Key a host authenticates with: its IdentityFile, else the first existing default key
+    /// Fails with KeyNotFound for a missing IdentityFile, or for ~/.ssh when no default key exists
+    fn resolve_identity_file(host_config: &HostConfig) -> SshResult<PathBuf> {
+        match host_config.identity_file {
+            Some(ref path) if path.exists() => Ok(path.clone()),
+            Some(ref path) => Err(SshBuddyError::KeyNotFound {
+                path: path.to_string_lossy().to_string(),
+            }),
+            None => {
+                let ssh_dir = Self::get_ssh_dir();
+                DEFAULT_IDENTITY_FILES
+                    .iter()
+                    .map(|k| ssh_dir.join(k))
+                    .find(|p| p.exists())
+                    .ok_or_else(|| SshBuddyError::KeyNotFound {
+                        path: ssh_dir.to_string_lossy().to_string(),
+                    })
             }
         }
+    }
 
+    /// Connect to a host and check its key against known_hosts, before any authentication
+    /// The key is looked up under `host_key_alias` when the host has one
+    async fn connect_and_check_host_key(
+        hostname: &str,
+        port: u16,
+        host_key_alias: Option<&str>,
+        preferred: Preferred,
+    ) -> Result<(client::Handle<ClientHandler>, SharedHostKeyState), ConnectFailure> {
+        let known_host_keys = KnownHostsService::load_known_hosts().await;
+        let (lookup_host, lookup_port) =
+            KnownHostsService::entry_target(hostname, port, host_key_alias);
         log::info!(
-            "[ssh_connection] Parsed {} entries from {} lines. Hosts: {:?}",
-            parsed_count,
-            line_count,
-            known_hosts.keys().collect::<Vec<_>>()
+            "[ssh_connection] Loaded {} known hosts. Looking for: {}",
+            known_host_keys.len(),
+            lookup_host
         );
+        // List some known hostnames (for debugging)
+        for (host, _) in known_host_keys.iter().take(5) {
+            log::debug!("[ssh_connection] Known host: {}", host);
+        }
 
-        known_hosts
-    }
-
-    /// Read SSH config and resolve host
-    async fn resolve_host(host_alias: &str) -> SshResult<HostConfig> {
-        let ssh_dir = Self::get_ssh_dir();
-        let config_path = ssh_dir.join("config");
+        // Create shared state
+        let shared_state = Arc::new(Mutex::new(SharedHostKeyState::default()));
 
-        let config = if config_path.exists() {
-            fs::read_to_string(&config_path).await.unwrap_or_default()
-        } else {
-            String::new()
+        // SSH client configuration
+        let config = client::Config {
+            inactivity_timeout: Some(Duration::from_secs(10)),
+            preferred,
+            ..Default::default()
         };
 
-        let hosts = SshConfigParser::parse(&config);
-        let merged = SshConfigParser::merge_configs(&hosts, host_alias);
-
-        Ok(merged)
+        // Establish connection (with timeout)
+        let addr = format!("{}:{}", hostname, port);
+        let handler = ClientHandler::new(
+            &lookup_host,
+            lookup_port,
+            known_host_keys,
+            shared_state.clone(),
+        );
+        match timeout(
+            Duration::from_secs(10),
+            client::connect(Arc::new(config), &addr, handler),
+        )
+        .await
+        {
+            Ok(Ok(session)) => {
+                let host_key_state = shared_state.lock().await.clone();
+                Ok((session, host_key_state))
+            }
+            Ok(Err(e)) => Err(ConnectFailure::Failed(e.to_string())),
+            Err(_) => Err(ConnectFailure::Timeout),
+        }
     }
 
     /// Detect Git platform
@@ -389,6 +925,209 @@ impl SshConnectionService {
             || lower.contains("welcome")
     }
 
+    /// Output to show after authenticating: Git banners verbatim (known platforms,
+    /// or servers that print one and close the channel), a clean message otherwise
+    /// so a login shell's prompt or MOTD isn't shown as the result
+    fn success_output(output: &str, platform: Option<&str>, channel_closed: bool) -> String {
+        let is_banner = platform.is_some() || (channel_closed && Self::is_auth_success(output));
+        if is_banner && !output.trim().is_empty() {
+            output.to_string()
+        } else if channel_closed {
+            "Authenticated successfully (no banner)".to_string()
+        } else {
+            "Authentication successful".to_string()
+        }
+    }
+
+    /// User a server names in its banner when the login user is wrong, e.g.
+    /// `Please login as the user "ubuntu" rather than the user "root".`
+    fn expected_user_from_banner(output: &str) -> Option<String> {
+        let rest = output.split("login as the user \"").nth(1)?;
+        let user = rest.split('"').next()?.trim();
+        if user.is_empty() {
+            None
+        } else {
+            Some(user.to_string())
+        }
+    }
+
+    /// Account a Git platform's banner says the key resolved to
+    fn banner_account(output: &str) -> Option<String> {
+        let trimmed = |s: &str| {
+            let account = s
+                .split(|c: char| c == '!' || c == ',' || c.is_whitespace())
+                .next()?
+                .trim_start_matches('@');
+            (!account.is_empty()).then(|| account.to_string())
+        };
+        if let Some(rest) = output.split("Hi ").nth(1) {
+            return trimmed(rest);
+        }
+        if let Some(rest) = output.split("Welcome to GitLab, ").nth(1) {
+            return trimmed(rest);
+        }
+        output.split("logged in as ").nth(1).and_then(trimmed)
+    }
+
+    /// Git platforms only accept the shared `git` user; the account is picked by key
+    fn wrong_user_hint(platform: Option<&str>, user: &str) -> Option<String> {
+        match platform {
+            Some(_) if user != "git" => Some("git".to_string()),
+            _ => None,
+        }
+    }
+
+    /// Suggestion text for a failed login, naming the likely user when it looks wrong
+    fn permission_denied_suggestion(user: &str, suggested_user: Option<&str>) -> String {
+        match suggested_user {
+            Some(expected) => format!(
+                "This host expects the user \"{}\", not \"{}\". Set User {} in the host configuration.",
+                expected, user, expected
+            ),
+            None => "Check that your public key is added to the server.".to_string(),
+        }
+    }
+
+    /// Certificate to present with `key_path`: CertificateFile from the config,
+    /// otherwise `<key>-cert.pub` next to the key, as OpenSSH does
+    fn resolve_certificate_file(host_config: &HostConfig, key_path: &Path) -> Option<PathBuf> {
+        if let Some(ref configured) = host_config.certificate_file {
+            if configured.exists() {
+                return Some(configured.clone());
+            }
+            log::warn!(
+                "[ssh_connection] CertificateFile not found: {}",
+                configured.display()
+            );
+            return None;
+        }
+
+        let mut default = key_path.as_os_str().to_owned();
+        default.push("-cert.pub");
+        let default = PathBuf::from(default);
+        if default.exists() {
+            Some(default)
+        } else {
+            None
+        }
+    }
+
+    /// Why the server would refuse a certificate for `user`, checked before connecting with it
+    fn certificate_problem(cert: &CertInfo, user: &str) -> Option<(SshErrorType, String)> {
+        if cert.expired {
+            return Some((
+                SshErrorType::CertificateExpired,
+                format!("Certificate {} has expired", cert.name),
+            ));
+        }
+        if cert.not_yet_valid {
+            return Some((
+                SshErrorType::CertificateExpired,
+                format!("Certificate {} is not valid yet", cert.name),
+            ));
+        }
+        // An empty principal list is valid for any user
+        if !cert.principals.is_empty() && !cert.principals.iter().any(|p| p == user) {
+            return Some((
+                SshErrorType::CertificatePrincipalMismatch,
+                format!(
+                    "Certificate {} is not valid for user {} (principals: {})",
+                    cert.name,
+                    user,
+                    cert.principals.join(", ")
+                ),
+            ));
+        }
+        None
+    }
+
+    /// Whether a server or library message says the client is being throttled
+    fn is_rate_limited(message: &str) -> bool {
+        let lower = message.to_lowercase();
+        [
+            "too many authentication failures",
+            "rate limit",
+            "too many requests",
+            "too many connections",
+            "try again later",
+        ]
+        .iter()
+        .any(|pattern| lower.contains(pattern))
+    }
+
+    /// Record a test against `host`, or return how long to wait if the limit is reached
+    fn check_test_rate(host: &str) -> Result<(), Duration> {
+        let recent = RECENT_TESTS.get_or_init(Default::default);
+        let mut recent = recent.lock().unwrap_or_else(|e| e.into_inner());
+        Self::admit_host_test(&mut recent, host, Instant::now())
+    }
+
+    /// Hosts with no test left in the window are dropped, so the map only holds
+    /// hosts tested within the last RATE_LIMIT_WINDOW
+    fn admit_host_test(
+        recent: &mut HashMap<String, VecDeque<Instant>>,
+        host: &str,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        recent.retain(|_, history| {
+            Self::prune_tests(history, now);
+            !history.is_empty()
+        });
+        Self::admit_test(recent.entry(host.to_string()).or_default(), now)
+    }
+
+    /// Drop tests that started more than RATE_LIMIT_WINDOW ago
+    fn prune_tests(history: &mut VecDeque<Instant>, now: Instant) {
+        while history
+            .front()
+            .is_some_and(|started| now.duration_since(*started) >= RATE_LIMIT_WINDOW)
+        {
+            history.pop_front();
+        }
+    }
+
+    fn admit_test(history: &mut VecDeque<Instant>, now: Instant) -> Result<(), Duration> {
+        Self::prune_tests(history, now);
+
+        if history.len() >= RATE_LIMIT_MAX_TESTS {
+            let oldest = history.front().copied().unwrap_or(now);
+            return Err(RATE_LIMIT_WINDOW.saturating_sub(now.duration_since(oldest)));
+        }
+
+        history.push_back(now);
+        Ok(())
+    }
+
+    fn rate_limited_details(raw_message: String, suggestion: String) -> SshErrorDetails {
+        SshErrorDetails {
+            error_type: SshErrorType::RateLimited,
+            raw_message,
+            suggestion,
+            can_auto_fix: false,
+            fix_type: None,
+            fix_params: None,
+            suggested_actions: vec![SuggestedAction::new("retry", "Test again")],
+        }
+    }
+
+    /// Suggested actions when the server rejects the key
+    fn permission_denied_actions(
+        key_path: &std::path::Path,
+        platform: Option<&str>,
+    ) -> Vec<SuggestedAction> {
+        let mut actions = vec![SuggestedAction::new("copy-public-key", "Copy public key")
+            .with_param("keyPath", key_path.to_string_lossy())];
+
+        if let Some(platform) = platform {
+            actions.push(
+                SuggestedAction::new("open-docs", "Open SSH key settings")
+                    .with_param("platform", platform),
+            );
+        }
+
+        actions
+    }
+
     /// Load private key
     async fn load_private_key(key_path: &PathBuf) -> SshResult<russh_keys::key::KeyPair> {
         let key_content =
@@ -398,34 +1137,68 @@ impl SshConnectionService {
                     path: key_path.to_string_lossy().to_string(),
                 })?;
 
-        // Try loading without password
-        russh_keys::decode_secret_key(&key_content, None).map_err(|e| {
-            if e.to_string().contains("passphrase") || e.to_string().contains("decrypt") {
-                SshBuddyError::Unknown {
-                    message: "Key requires passphrase".to_string(),
-                }
-            } else {
-                SshBuddyError::InvalidKeyFormat {
-                    message: e.to_string(),
-                }
+        let passphrase_required = || SshBuddyError::PassphraseRequired {
+            path: key_path.to_string_lossy().to_string(),
+        };
+
+        // OpenSSH keys say whether they are encrypted in their unencrypted envelope
+        if let Ok(envelope) = OpenSshKeyEnvelope::parse(&key_content) {
+            if envelope.is_encrypted() {
+                return Err(passphrase_required());
             }
+        }
+
+        // Try loading without password
+        russh_keys::decode_secret_key(&key_content, None).map_err(|e| match e {
+            russh_keys::Error::KeyIsEncrypted => passphrase_required(),
+            e => SshBuddyError::InvalidKeyFormat {
+                message: e.to_string(),
+            },
         })
     }
 
+    /// Public key blob (base64) of a private key, used to pick its identity in the agent
+    /// Read from the `.pub` file, or from the private key itself when there is none
+    #[cfg(unix)]
+    async fn target_public_key_base64(key_path: &Path) -> SshResult<String> {
+        let pub_key_path = PathBuf::from(format!("{}.pub", key_path.to_string_lossy()));
+        let public_key = match fs::read_to_string(&pub_key_path).await {
+            Ok(content) => ssh_key::PublicKey::from_openssh(&content)?,
+            Err(_) => KeyManager::public_from_private_key_file(key_path).await?,
+        };
+
+        Ok(public_key
+            .to_openssh()?
+            .split_whitespace()
+            .nth(1)
+            .unwrap_or_default()
+            .to_string())
+    }
+
     /// Authenticate using SSH agent (Unix version)
+    /// Only the agent identity matching `key_path` is offered
     #[cfg(unix)]
     async fn authenticate_with_agent(
         session: &mut client::Handle<ClientHandler>,
         user: &str,
         key_path: &std::path::Path,
+        host_config: &HostConfig,
+        attempts: &mut Vec<AuthAttempt>,
     ) -> Result<bool, String> {
-        // Connect to SSH agent
-        let agent_path = std::env::var("SSH_AUTH_SOCK")
-            .map_err(|_| "SSH_AUTH_SOCK not set. SSH agent may not be running.".to_string())?;
+        // Connect to the host's IdentityAgent, or SSH_AUTH_SOCK
+        let agent_path = host_config.agent_socket()?;
+        log::info!(
+            "[ssh_connection] Using SSH agent at {}",
+            agent_path.display()
+        );
 
-        let stream = UnixStream::connect(&agent_path)
-            .await
-            .map_err(|e| format!("Failed to connect to SSH agent: {}", e))?;
+        let stream = UnixStream::connect(&agent_path).await.map_err(|e| {
+            format!(
+                "Failed to connect to SSH agent at {}: {}",
+                agent_path.display(),
+                e
+            )
+        })?;
 
         let mut agent = AgentClient::connect(stream);
 
@@ -435,90 +1208,725 @@ impl SshConnectionService {
             .await
             .map_err(|e| format!("Failed to request identities from agent: {}", e))?;
 
-        log::info!("[ssh_connection] Agent has {} identities", identities.len());
+        log::info!("[ssh_connection] Agent has {} identities", identities.len());
+
+        if identities.is_empty() {
+            return Err("No keys in SSH agent".to_string());
+        }
+
+        // Read target key's public key for comparison
+        let target_pubkey = Self::target_public_key_base64(key_path)
+            .await
+            .map_err(|e| format!("Could not read public key of {}: {}", key_path.display(), e))?;
+
+        // Offer only the identity of the requested key
+        let identity = identities
+            .into_iter()
+            .find(|identity| identity.public_key_base64() == target_pubkey)
+            .ok_or_else(|| format!("{} is not loaded in the SSH agent", key_path.display()))?;
+
+        log::info!("[ssh_connection] Trying agent key for authentication");
+
+        // Use authenticate_future with agent for authentication
+        let offered = identity.clone();
+        let (_, auth_result) = session.authenticate_future(user, identity, agent).await;
+        attempts.push(AuthAttempt::new(
+            &offered,
+            AuthKeySource::Agent,
+            &auth_result,
+        ));
+
+        match auth_result {
+            Ok(authenticated) => {
+                log::info!(
+                    "[ssh_connection] Agent authentication result: {}",
+                    authenticated
+                );
+                Ok(authenticated)
+            }
+            Err(e) => {
+                log::warn!("[ssh_connection] Agent auth error: {}", e);
+                Err(format!("Agent auth error: {}", e))
+            }
+        }
+    }
+
+    /// Authenticate using SSH agent (Windows version)
+    #[cfg(windows)]
+    async fn authenticate_with_agent(
+        _session: &mut client::Handle<ClientHandler>,
+        _user: &str,
+        _key_path: &std::path::Path,
+        _host_config: &HostConfig,
+        _attempts: &mut Vec<AuthAttempt>,
+    ) -> Result<bool, String> {
+        // Windows: russh_keys AgentClient requires tokio AsyncRead/AsyncWrite
+        // Windows named pipes don't implement these traits directly
+        // For now, return an error indicating agent auth is not supported
+        // Users should add their key to the agent and use direct key auth
+        Err(
+            "SSH agent authentication via named pipe is not yet fully supported on Windows. \
+             Please ensure your key is loaded in the agent and try again."
+                .to_string(),
+        )
+    }
+
+    /// Honor AddKeysToAgent after a key read from disk was accepted
+    /// ssh-add only reaches SSH_AUTH_SOCK, so keys for another IdentityAgent are left alone
+    async fn add_used_key_to_agent(
+        host_config: &HostConfig,
+        key_path: &Path,
+        lifetime_secs: Option<u32>,
+        debug_log: &mut Vec<String>,
+    ) {
+        let default_socket = std::env::var_os("SSH_AUTH_SOCK").map(PathBuf::from);
+        if host_config.agent_socket().ok() != default_socket {
+            debug_log.push(
+                "AddKeysToAgent skipped: IdentityAgent is not the SSH_AUTH_SOCK agent".to_string(),
+            );
+            return;
+        }
+
+        match AgentService::add_key(&key_path.to_string_lossy(), None, lifetime_secs).await {
+            Ok(result) => debug_log.push(format!("AddKeysToAgent: {}", result.message)),
+            Err(e) => {
+                log::warn!("[ssh_connection] AddKeysToAgent failed: {}", e);
+                debug_log.push(format!("AddKeysToAgent failed: {}", e));
+            }
+        }
+    }
+
+    /// Connect to a host whose key matches known_hosts
+    /// Refuses hosts whose key is unknown or has changed
+    async fn connect_verified(
+        hostname: &str,
+        port: u16,
+        host_key_alias: Option<&str>,
+        preferred: Preferred,
+    ) -> SshResult<client::Handle<ClientHandler>> {
+        let (session, host_key_state) =
+            Self::connect_and_check_host_key(hostname, port, host_key_alias, preferred)
+                .await
+                .map_err(|failure| match failure {
+                    ConnectFailure::Timeout => SshBuddyError::ConnectionTimeout,
+                    ConnectFailure::Failed(message) => SshBuddyError::ConnectionRefused { message },
+                })?;
+
+        match host_key_state.status {
+            KnownHostStatus::Matched => Ok(session),
+            KnownHostStatus::Unknown => Err(SshBuddyError::HostKeyUnknown {
+                hostname: hostname.to_string(),
+            }),
+            KnownHostStatus::Changed => Err(SshBuddyError::HostKeyChanged {
+                hostname: hostname.to_string(),
+            }),
+        }
+    }
+
+    /// Connect to a host and authenticate with its configured (or default) key
+    /// Refuses hosts whose key is unknown or has changed
+    async fn open_authenticated_session(
+        host_alias: &str,
+    ) -> SshResult<client::Handle<ClientHandler>> {
+        let host_config = Self::resolve_host(host_alias).await?;
+        let hostname = host_config.get_hostname().to_string();
+        let port = host_config.get_port();
+        let user = host_config.get_user().unwrap_or("git").to_string();
+
+        let key_path = Self::resolve_identity_file(&host_config)?;
+
+        let algorithms = AlgorithmPreferences::from_host_config(&host_config);
+        for note in &algorithms.notes {
+            log::debug!("[ssh_connection] {}", note);
+        }
+
+        let mut session = Self::connect_verified(
+            &hostname,
+            port,
+            host_config.host_key_alias.as_deref(),
+            algorithms.preferred,
+        )
+        .await?;
+
+        let authenticated = match Self::load_private_key(&key_path).await {
+            Ok(key_pair) => session
+                .authenticate_publickey(&user, Arc::new(key_pair))
+                .await
+                .map_err(|e| SshBuddyError::PermissionDenied {
+                    reason: e.to_string(),
+                })?,
+            // Only an encrypted key may be served by the agent instead
+            Err(SshBuddyError::PassphraseRequired { .. }) => Self::authenticate_with_agent(
+                &mut session,
+                &user,
+                &key_path,
+                &host_config,
+                &mut Vec::new(),
+            )
+            .await
+            .map_err(|_| SshBuddyError::PassphraseRequired {
+                path: key_path.to_string_lossy().to_string(),
+            })?,
+            Err(e) => return Err(e),
+        };
+
+        if !authenticated {
+            return Err(SshBuddyError::PermissionDenied {
+                reason: format!("{}@{} rejected {}", user, hostname, key_path.display()),
+            });
+        }
+
+        Ok(session)
+    }
+
+    /// Count the keys a connection to `host_alias` would offer, without connecting
+    /// Warns when that is more than a typical server's MaxAuthTries
+    pub async fn connection_preflight(host_alias: &str) -> SshResult<ConnectionPreflight> {
+        let host_config = Self::resolve_host(host_alias).await?;
+
+        let agent_key_count = if host_config.identity_agent.as_deref() == Some("none") {
+            Some(0)
+        } else {
+            match AgentService::list_keys().await {
+                Ok(keys) => Some(keys.len()),
+                Err(e) => {
+                    log::warn!("[ssh_connection] Agent keys not counted: {}", e);
+                    None
+                }
+            }
+        };
+
+        let config_key_count = match host_config.identity_file {
+            Some(ref path) => usize::from(path.exists()),
+            None => {
+                let ssh_dir = Self::get_ssh_dir();
+                DEFAULT_IDENTITY_FILES
+                    .iter()
+                    .filter(|k| ssh_dir.join(k).exists())
+                    .count()
+            }
+        };
+
+        let identities_only = host_config
+            .options
+            .get("identitiesonly")
+            .is_some_and(|value| value.eq_ignore_ascii_case("yes"));
+
+        let offered_key_count = Self::offered_key_count(
+            agent_key_count.unwrap_or(0),
+            config_key_count,
+            identities_only,
+        );
+        let likely_too_many_keys = offered_key_count > TYPICAL_MAX_AUTH_TRIES;
+        let suggestion = likely_too_many_keys.then(|| {
+            format!(
+                "Up to {} keys may be offered, more than the {} attempts most servers allow. Add IdentitiesOnly yes with an IdentityFile to this host.",
+                offered_key_count, TYPICAL_MAX_AUTH_TRIES
+            )
+        });
+
+        log::info!(
+            "[ssh_connection] Preflight for {}: agent={:?}, config={}, identities_only={}",
+            host_alias,
+            agent_key_count,
+            config_key_count,
+            identities_only
+        );
+
+        Ok(ConnectionPreflight {
+            hostname: host_config.get_hostname().to_string(),
+            port: host_config.get_port(),
+            agent_key_count,
+            config_key_count,
+            identities_only,
+            offered_key_count,
+            likely_too_many_keys,
+            suggestion,
+        })
+    }
+
+    /// Keys offered at most: agent keys first, then key files
+    /// With IdentitiesOnly only the key files are offered
+    fn offered_key_count(agent_keys: usize, config_keys: usize, identities_only: bool) -> usize {
+        if identities_only {
+            config_keys
+        } else {
+            agent_keys + config_keys
+        }
+    }
+
+    /// Ask a server which authentication methods it accepts for the host's user
+    /// Connects as every other check does, refusing hosts whose key is unknown or has
+    /// changed, and sends a "none" request. russh doesn't expose the method list of a
+    /// failed "none" request, so the list comes from the OpenSSH client limited to
+    /// "none" auth and checking the same known_hosts file (in SSH_BUDDY_SSH_DIR when
+    /// set); no key or password is ever sent
+    pub async fn probe_auth_methods(host_alias: &str) -> SshResult<AuthMethodsProbe> {
+        let host_config = Self::resolve_host(host_alias).await?;
+        let hostname = host_config.get_hostname().to_string();
+        let port = host_config.get_port();
+        let user = host_config.get_user().unwrap_or("git").to_string();
+        validate_hostname(&hostname)?;
+        let algorithms = AlgorithmPreferences::from_host_config(&host_config);
+        let known_hosts_file =
+            KnownHostsService::default_known_hosts_file().ok_or(SshBuddyError::HomeDirNotFound)?;
+
+        log::info!(
+            "[ssh_connection] Probing auth methods of {}@{}:{}",
+            user,
+            hostname,
+            port
+        );
+        let mut session = Self::connect_verified(
+            &hostname,
+            port,
+            host_config.host_key_alias.as_deref(),
+            algorithms.preferred,
+        )
+        .await?;
+        let accepted = session.authenticate_none(&user).await.map_err(|e| {
+            SshBuddyError::ConnectionRefused {
+                message: e.to_string(),
+            }
+        })?;
+        drop(session);
+
+        let methods = if accepted {
+            vec!["none".to_string()]
+        } else {
+            Self::list_auth_methods(
+                &hostname,
+                port,
+                &user,
+                host_config.host_key_alias.as_deref(),
+                &known_hosts_file,
+            )
+            .await?
+        };
+
+        log::info!(
+            "[ssh_connection] {} offers: {}",
+            hostname,
+            methods.join(", ")
+        );
+        Ok(AuthMethodsProbe {
+            hostname,
+            port,
+            user,
+            methods,
+        })
+    }
+
+    /// Methods the server lists when a "none" request from `ssh` is refused
+    /// The user's config is not read; the host key is checked against `known_hosts_file` only
+    async fn list_auth_methods(
+        hostname: &str,
+        port: u16,
+        user: &str,
+        host_key_alias: Option<&str>,
+        known_hosts_file: &str,
+    ) -> SshResult<Vec<String>> {
+        let mut command = tokio::process::Command::new("ssh");
+        command
+            .args(["-F", "none"])
+            .args(["-o", "BatchMode=yes"])
+            .args(["-o", "PreferredAuthentications=none"])
+            .args(["-o", "StrictHostKeyChecking=yes"])
+            .args(["-o", "ConnectTimeout=10"]);
+        // Only the file the app checks, not the system-wide ones
+        for option in ["UserKnownHostsFile", "GlobalKnownHostsFile"] {
+            command.args(["-o", &format!("{}=\"{}\"", option, known_hosts_file)]);
+        }
+        if let Some(alias) = host_key_alias {
+            command.args(["-o", &format!("HostKeyAlias={}", alias)]);
+        }
+        command
+            .args(["-p", &port.to_string()])
+            .args(["-l", user])
+            .args(["-T", "--", hostname, "true"])
+            .stdin(std::process::Stdio::null())
+            // The ssh process goes with the future when the probe times out
+            .kill_on_drop(true);
+
+        let output = match timeout(AUTH_PROBE_TIMEOUT, command.output()).await {
+            Ok(result) => result.map_err(|e| SystemTools::spawn_error("ssh", e))?,
+            Err(_) => return Err(SshBuddyError::ConnectionTimeout),
+        };
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        match Self::parse_auth_methods(&stderr) {
+            Some(methods) => Ok(methods),
+            None if output.status.success() => Ok(vec!["none".to_string()]),
+            None => {
+                let message = stderr
+                    .lines()
+                    .rev()
+                    .find(|line| !line.trim().is_empty())
+                    .unwrap_or("ssh exited without listing authentication methods")
+                    .trim()
+                    .to_string();
+                Err(SshBuddyError::ConnectionRefused { message })
+            }
+        }
+    }
+
+    /// Methods from ssh's "Permission denied (publickey,password)." line
+    fn parse_auth_methods(stderr: &str) -> Option<Vec<String>> {
+        let line = stderr
+            .lines()
+            .rev()
+            .find(|line| line.contains("Permission denied ("))?;
+        let start = line.find("Permission denied (")? + "Permission denied (".len();
+        let end = start + line[start..].find(')')?;
+        Some(
+            line[start..end]
+                .split(',')
+                .map(str::trim)
+                .filter(|method| !method.is_empty())
+                .map(str::to_string)
+                .collect(),
+        )
+    }
+
+    /// Try every local private key against a host, stopping at the first one it accepts
+    /// Keys are offered one at a time, reconnecting every KEYS_PER_CONNECTION keys so the
+    /// server's MaxAuthTries is never hit; encrypted keys are skipped, as there is no
+    /// passphrase to unlock them with. A search counts as one test against the
+    /// per-host rate limit of `test_connection`
+    pub async fn find_working_key(host_alias: &str) -> SshResult<WorkingKeyResult> {
+        let host_config = Self::resolve_host(host_alias).await?;
+        let hostname = host_config.get_hostname().to_string();
+        let port = host_config.get_port();
+        let user = host_config.get_user().unwrap_or("git").to_string();
+        let algorithms = AlgorithmPreferences::from_host_config(&host_config);
+
+        Self::check_test_rate(&format!("{}:{}", hostname, port)).map_err(|wait| {
+            log::warn!(
+                "[ssh_connection] Not searching keys of {} again yet",
+                hostname
+            );
+            SshBuddyError::RateLimited {
+                host: hostname.clone(),
+                wait_secs: wait.as_secs().max(1),
+            }
+        })?;
+
+        let key_paths = KeyManager::new()?.list_private_key_paths().await?;
+        log::info!(
+            "[ssh_connection] Trying {} key(s) against {}@{}:{}",
+            key_paths.len(),
+            user,
+            hostname,
+            port
+        );
+
+        let mut result = WorkingKeyResult {
+            hostname: hostname.clone(),
+            user: user.clone(),
+            working_key: None,
+            attempts: Vec::new(),
+        };
+        let mut session: Option<client::Handle<ClientHandler>> = None;
+        let mut offered = 0;
+
+        for key_path in key_paths {
+            let mut probe = KeyProbe {
+                name: key_path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                path: key_path.to_string_lossy().to_string(),
+                fingerprint: None,
+                outcome: AuthOutcome::Skipped,
+                message: None,
+            };
+
+            let key_pair = match Self::load_private_key(&key_path).await {
+                Ok(key_pair) => key_pair,
+                Err(e) => {
+                    probe.message = Some(e.to_string());
+                    result.attempts.push(probe);
+                    continue;
+                }
+            };
+            probe.fingerprint = key_pair
+                .clone_public_key()
+                .ok()
+                .map(|public_key| format!("SHA256:{}", public_key.fingerprint()));
+
+            if !algorithms.accepts_key(&key_pair) {
+                probe.message = Some("Not allowed by PubkeyAcceptedAlgorithms".to_string());
+                result.attempts.push(probe);
+                continue;
+            }
+
+            if offered == KEYS_PER_CONNECTION {
+                session = None;
+                offered = 0;
+            }
+            let handle = match session {
+                Some(ref mut handle) => handle,
+                None => session.insert(
+                    Self::connect_verified(
+                        &hostname,
+                        port,
+                        host_config.host_key_alias.as_deref(),
+                        algorithms.preferred.clone(),
+                    )
+                    .await?,
+                ),
+            };
+
+            let key_pair = Arc::new(key_pair);
+            offered += 1;
+            let mut auth = handle.authenticate_publickey(&user, key_pair.clone()).await;
+            if auth.is_err() {
+                // The server may have dropped the connection; give the key a fresh one
+                let handle = session.insert(
+                    Self::connect_verified(
+                        &hostname,
+                        port,
+                        host_config.host_key_alias.as_deref(),
+                        algorithms.preferred.clone(),
+                    )
+                    .await?,
+                );
+                offered = 1;
+                auth = handle.authenticate_publickey(&user, key_pair).await;
+            }
 
-        if identities.is_empty() {
-            return Err("No keys in SSH agent".to_string());
-        }
+            let (outcome, message) = AuthOutcome::of(&auth);
+            probe.outcome = outcome;
+            probe.message = message;
+            result.attempts.push(probe.clone());
 
-        // Read target key's public key for comparison
-        let pub_key_path = format!("{}.pub", key_path.to_string_lossy());
-        let target_pubkey = match fs::read_to_string(&pub_key_path).await {
-            Ok(content) => {
-                // Parse public key to get fingerprint or base64
-                let parts: Vec<&str> = content.split_whitespace().collect();
-                if parts.len() >= 2 {
-                    Some(parts[1].to_string()) // base64 part
-                } else {
-                    None
-                }
+            if outcome == AuthOutcome::Accepted {
+                log::info!("[ssh_connection] {} accepted {}", hostname, probe.name);
+                result.working_key = Some(probe);
+                break;
             }
-            Err(_) => None,
-        };
+        }
 
-        // Try to find a matching key
-        for identity in identities {
-            let identity_base64 = identity.public_key_base64();
-            log::debug!(
-                "[ssh_connection] Agent identity: {}...",
-                &identity_base64[..identity_base64.len().min(50)]
-            );
+        Ok(result)
+    }
 
-            // If target public key exists, check if it matches
-            let should_try = match &target_pubkey {
-                Some(target) => identity_base64 == *target,
-                None => true, // Try all keys when no target
-            };
+    /// Log in to a host and return what its shell prints first (MOTD, notices, prompt)
+    /// The output is not interpreted; `test_connection` decides whether auth worked
+    pub async fn fetch_motd(host_alias: &str) -> SshResult<Motd> {
+        let session = Self::open_authenticated_session(host_alias).await?;
+        let channel_error = |e: russh::Error| SshBuddyError::IoError {
+            message: format!("Failed to open shell: {}", e),
+        };
+        let mut channel = session
+            .channel_open_session()
+            .await
+            .map_err(channel_error)?;
+        channel
+            .request_pty(false, "xterm", 80, 24, 0, 0, &[])
+            .await
+            .map_err(channel_error)?;
+        channel.request_shell(false).await.map_err(channel_error)?;
+
+        let mut output = Vec::new();
+        let mut truncated = false;
+        let _ = timeout(MOTD_TIMEOUT, async {
+            while let Ok(Some(msg)) = timeout(MOTD_QUIET_PERIOD, channel.wait()).await {
+                match msg {
+                    ChannelMsg::Data { data } | ChannelMsg::ExtendedData { data, .. }
+                        if !Self::append_bounded(&mut output, &data, MOTD_MAX_BYTES) =>
+                    {
+                        truncated = true;
+                        break;
+                    }
+                    ChannelMsg::Eof | ChannelMsg::Close => break,
+                    _ => {}
+                }
+            }
+        })
+        .await;
+        let _ = channel.close().await;
 
-            if should_try {
-                log::info!("[ssh_connection] Trying agent key for authentication");
+        log::info!(
+            "[ssh_connection] Read {} bytes of MOTD from {} (truncated: {})",
+            output.len(),
+            host_alias,
+            truncated
+        );
+        Ok(Motd {
+            motd: String::from_utf8_lossy(&output).to_string(),
+            truncated,
+        })
+    }
 
-                // Use authenticate_future with agent for authentication
-                let (returned_agent, auth_result) =
-                    session.authenticate_future(user, identity, agent).await;
+    /// Append `data` to `buffer` up to `limit` bytes; false once data had to be dropped
+    fn append_bounded(buffer: &mut Vec<u8>, data: &[u8], limit: usize) -> bool {
+        let room = limit.saturating_sub(buffer.len());
+        buffer.extend_from_slice(&data[..data.len().min(room)]);
+        data.len() <= room
+    }
 
-                // Take back agent ownership for subsequent use
-                agent = returned_agent;
+    /// Run a command on an authenticated session
+    /// Returns the exit status (if reported) and combined stdout/stderr
+    async fn exec_remote(
+        session: &client::Handle<ClientHandler>,
+        command: &str,
+    ) -> SshResult<(Option<u32>, String)> {
+        let mut channel =
+            session
+                .channel_open_session()
+                .await
+                .map_err(|e| SshBuddyError::IoError {
+                    message: format!("Failed to open channel: {}", e),
+                })?;
 
-                match auth_result {
-                    Ok(true) => {
-                        log::info!("[ssh_connection] Agent authentication successful");
-                        return Ok(true);
-                    }
-                    Ok(false) => {
-                        log::debug!("[ssh_connection] Agent key not accepted, trying next...");
+        channel
+            .exec(true, command)
+            .await
+            .map_err(|e| SshBuddyError::IoError {
+                message: format!("Failed to run remote command: {}", e),
+            })?;
+
+        let mut output = String::new();
+        let mut exit_status = None;
+        timeout(Duration::from_secs(15), async {
+            while let Some(msg) = channel.wait().await {
+                match msg {
+                    ChannelMsg::Data { data } => {
+                        output.push_str(&String::from_utf8_lossy(&data));
                     }
-                    Err(e) => {
-                        log::warn!("[ssh_connection] Agent auth error: {}", e);
-                        return Err(format!("Agent auth error: {}", e));
+                    ChannelMsg::ExtendedData { data, .. } => {
+                        output.push_str(&String::from_utf8_lossy(&data));
                     }
+                    ChannelMsg::ExitStatus {
+                        exit_status: status,
+                    } => exit_status = Some(status),
+                    ChannelMsg::Eof | ChannelMsg::Close => break,
+                    _ => {}
                 }
             }
-        }
+        })
+        .await
+        .map_err(|_| SshBuddyError::ConnectionTimeout)?;
 
-        Err("No matching key found in SSH agent".to_string())
+        Ok((exit_status, output))
     }
 
-    /// Authenticate using SSH agent (Windows version)
-    #[cfg(windows)]
-    async fn authenticate_with_agent(
-        _session: &mut client::Handle<ClientHandler>,
-        _user: &str,
-        _key_path: &std::path::Path,
-    ) -> Result<bool, String> {
-        // Windows: russh_keys AgentClient requires tokio AsyncRead/AsyncWrite
-        // Windows named pipes don't implement these traits directly
-        // For now, return an error indicating agent auth is not supported
-        // Users should add their key to the agent and use direct key auth
-        Err(
-            "SSH agent authentication via named pipe is not yet fully supported on Windows. \
-             Please ensure your key is loaded in the agent and try again."
-                .to_string(),
+    /// Quote a string for a POSIX shell
+    fn shell_quote(value: &str) -> String {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+
+    /// Remote shell command that appends a key to ~/.ssh/authorized_keys unless present
+    /// `key_material` ("<type> <base64>") is what gets matched, so a changed comment
+    /// doesn't cause a duplicate entry
+    fn install_key_command(key_line: &str, key_material: &str) -> String {
+        let file = "~/.ssh/authorized_keys";
+        format!(
+            "umask 077 && mkdir -p ~/.ssh && chmod 700 ~/.ssh && touch {file} && chmod 600 {file} && \
+             if grep -qF -- {material} {file}; then echo {present}; else \
+             if [ -s {file} ] && [ -n \"$(tail -c1 {file})\" ]; then echo >> {file}; fi && \
+             echo {line} >> {file} && echo {added}; fi",
+            file = file,
+            material = Self::shell_quote(key_material),
+            line = Self::shell_quote(key_line),
+            present = KEY_PRESENT_MARKER,
+            added = KEY_ADDED_MARKER,
+        )
+    }
+
+    /// Install a local public key in a host's ~/.ssh/authorized_keys (like ssh-copy-id)
+    /// Authenticates with the host's configured key; does nothing if the key is already there
+    pub async fn install_public_key(
+        host_alias: &str,
+        key_name: &str,
+    ) -> SshResult<InstallKeyResult> {
+        let content = KeyManager::new()?.read_public_key(key_name).await?;
+        let public_key = ssh_key::PublicKey::from_openssh(&content)?;
+        let key_line = public_key.to_openssh()?;
+        let key_material = key_line
+            .split_whitespace()
+            .take(2)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let session = Self::open_authenticated_session(host_alias).await?;
+        let (exit_status, output) = Self::exec_remote(
+            &session,
+            &Self::install_key_command(&key_line, &key_material),
         )
+        .await?;
+
+        log::info!(
+            "[ssh_connection] install_public_key exit status: {:?}",
+            exit_status
+        );
+
+        if output.contains(KEY_PRESENT_MARKER) {
+            Ok(InstallKeyResult {
+                added: false,
+                already_present: true,
+                message: format!("{} is already authorized on {}", key_name, host_alias),
+            })
+        } else if output.contains(KEY_ADDED_MARKER) {
+            Ok(InstallKeyResult {
+                added: true,
+                already_present: false,
+                message: format!("Added {} to authorized_keys on {}", key_name, host_alias),
+            })
+        } else {
+            Err(SshBuddyError::Unknown {
+                message: format!(
+                    "Failed to update authorized_keys on {}: {}",
+                    host_alias,
+                    output.trim()
+                ),
+            })
+        }
     }
 
     /// Test SSH connection
-    pub async fn test_connection(host_alias: &str) -> SshResult<ConnectionTestResult> {
+    /// When `identity_override` is set, that key is used instead of the config's
+    /// IdentityFile and default key discovery
+    /// On success the key used is recorded in the key usage store (best effort)
+    pub async fn test_connection(
+        host_alias: &str,
+        identity_override: Option<&str>,
+        fetch_banner: Option<bool>,
+        prefer_agent: Option<bool>,
+    ) -> SshResult<ConnectionTestResult> {
+        let result =
+            Self::run_connection_test(host_alias, identity_override, fetch_banner, prefer_agent)
+                .await?;
+
+        if result.success {
+            if let Some(identity_file) = &result.identity_file {
+                Self::record_key_use(identity_file).await;
+            }
+        }
+        Ok(result)
+    }
+
+    /// Note a successful use of a key; failures are only logged
+    async fn record_key_use(identity_file: &str) {
+        let recorded = match KeyUsageStore::new() {
+            Ok(store) => store.record_now(identity_file).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = recorded {
+            log::warn!(
+                "[ssh_connection] Could not record use of {}: {}",
+                identity_file,
+                e
+            );
+        }
+    }
+
+    async fn run_connection_test(
+        host_alias: &str,
+        identity_override: Option<&str>,
+        fetch_banner: Option<bool>,
+        prefer_agent: Option<bool>,
+    ) -> SshResult<ConnectionTestResult> {
         let mut debug_log = Vec::new();
         debug_log.push(format!("Testing connection to: {}", host_alias));
 
@@ -532,61 +1940,108 @@ impl SshConnectionService {
 
         let platform = Self::detect_platform(&hostname);
 
+        if let Err(wait) = Self::check_test_rate(&format!("{}:{}", hostname, port)) {
+            let message = format!(
+                "{} tests of {} in the last {} seconds",
+                RATE_LIMIT_MAX_TESTS,
+                hostname,
+                RATE_LIMIT_WINDOW.as_secs()
+            );
+            log::warn!("[ssh_connection] Not testing again: {}", message);
+            debug_log.push(format!("Skipped: {}", message));
+            return Ok(ConnectionTestResult {
+                platform,
+                debug_log: Some(debug_log.join("\n")),
+                ..ConnectionTestResult::failure(
+                    "Too many connection tests, please wait".to_string(),
+                    Self::rate_limited_details(
+                        message,
+                        format!(
+                            "Wait {} seconds before testing this host again, so the server doesn't block you.",
+                            wait.as_secs().max(1)
+                        ),
+                    ),
+                )
+            });
+        }
+
+        // Git platforms answer a shell request with a greeting; other servers would start
+        // a login session, so stop after authentication unless asked otherwise
+        let fetch_banner = fetch_banner.unwrap_or(platform.is_some());
+        debug_log.push(if fetch_banner {
+            "Will open a session to read the welcome banner".to_string()
+        } else {
+            "Skipping welcome banner: authentication alone decides success".to_string()
+        });
+
         // Determine which key to use
-        let identity_file = if let Some(ref path) = host_config.identity_file {
-            if path.exists() {
-                Some(path.clone())
-            } else {
-                return Ok(ConnectionTestResult {
-                    success: false,
-                    output: format!("Identity file not found: {}", path.display()),
-                    platform,
-                    error_type: Some(SshErrorType::IdentityFileNotFound),
-                    error_details: Some(SshErrorDetails {
-                        error_type: SshErrorType::IdentityFileNotFound,
-                        raw_message: format!("Identity file not found: {}", path.display()),
-                        suggestion: "Check your SSH config and ensure the key file exists."
-                            .to_string(),
-                        can_auto_fix: false,
-                        fix_type: None,
-                        fix_params: None,
-                    }),
-                    host_to_remove: None,
-                    host_to_add: None,
-                    identity_file: Some(path.to_string_lossy().to_string()),
-                    debug_log: Some(debug_log.join("\n")),
-                });
-            }
+        let identity_file = if let Some(override_path) = identity_override {
+            let path = Self::resolve_identity_override(override_path, &Self::get_ssh_dir())?;
+            debug_log.push(format!(
+                "Identity override: {} (ignoring config IdentityFile and default keys)",
+                path.display()
+            ));
+            Some(path)
         } else {
-            // Try default keys
-            let ssh_dir = Self::get_ssh_dir();
-            let default_keys = ["id_ed25519", "id_rsa", "id_ecdsa"];
-            default_keys
-                .iter()
-                .map(|k| ssh_dir.join(k))
-                .find(|p| p.exists())
+            match Self::resolve_identity_file(&host_config) {
+                Ok(path) => Some(path),
+                // Configured IdentityFile is missing
+                Err(_) if host_config.identity_file.is_some() => {
+                    let path = host_config.identity_file.clone().unwrap_or_default();
+                    return Ok(ConnectionTestResult {
+                        platform,
+                        identity_file: Some(path.to_string_lossy().to_string()),
+                        debug_log: Some(debug_log.join("\n")),
+                        ..ConnectionTestResult::failure(
+                            format!("Identity file not found: {}", path.display()),
+                            SshErrorDetails {
+                                error_type: SshErrorType::IdentityFileNotFound,
+                                raw_message: format!("Identity file not found: {}", path.display()),
+                                suggestion: "Check your SSH config and ensure the key file exists."
+                                    .to_string(),
+                                can_auto_fix: false,
+                                fix_type: None,
+                                fix_params: None,
+                                suggested_actions: vec![
+                                    SuggestedAction::new(
+                                        "edit-host-config",
+                                        "Edit host configuration",
+                                    )
+                                    .with_param("hostAlias", host_alias),
+                                    SuggestedAction::new("generate-key", "Generate a new key")
+                                        .with_param("keyPath", path.to_string_lossy()),
+                                ],
+                            },
+                        )
+                    });
+                }
+                // No default key exists
+                Err(_) => None,
+            }
         };
 
         let key_path = match identity_file {
             Some(path) => path,
             None => {
                 return Ok(ConnectionTestResult {
-                    success: false,
-                    output: "No SSH key found".to_string(),
                     platform,
-                    error_type: Some(SshErrorType::IdentityFileNotFound),
-                    error_details: Some(SshErrorDetails {
-                        error_type: SshErrorType::IdentityFileNotFound,
-                        raw_message: "No SSH key found in ~/.ssh directory".to_string(),
-                        suggestion: "Generate an SSH key using 'ssh-keygen' or configure IdentityFile in your SSH config.".to_string(),
-                        can_auto_fix: false,
-                        fix_type: None,
-                        fix_params: None,
-                    }),
-                    host_to_remove: None,
-                    host_to_add: None,
-                    identity_file: None,
                     debug_log: Some(debug_log.join("\n")),
+                    ..ConnectionTestResult::failure(
+                        "No SSH key found".to_string(),
+                        SshErrorDetails {
+                            error_type: SshErrorType::IdentityFileNotFound,
+                            raw_message: "No SSH key found in ~/.ssh directory".to_string(),
+                            suggestion: "Generate an SSH key using 'ssh-keygen' or configure IdentityFile in your SSH config.".to_string(),
+                            can_auto_fix: false,
+                            fix_type: None,
+                            fix_params: None,
+                            suggested_actions: vec![
+                                SuggestedAction::new("generate-key", "Generate a new key"),
+                                SuggestedAction::new("edit-host-config", "Edit host configuration")
+                                    .with_param("hostAlias", host_alias),
+                            ],
+                        },
+                    )
                 });
             }
         };
@@ -596,47 +2051,32 @@ impl SshConnectionService {
         // === Step 1: Connect and check host key first, before loading private key ===
         // This allows detecting unknown/changed host before any key issues
 
-        // Load known_hosts
-        let known_host_keys = Self::load_known_hosts().await;
-        debug_log.push(format!(
-            "Loaded {} known hosts entries",
-            known_host_keys.len()
-        ));
-        log::info!(
-            "[ssh_connection] Loaded {} known hosts. Looking for: {}",
-            known_host_keys.len(),
-            hostname
-        );
-        // List some known hostnames (for debugging)
-        for (host, _) in known_host_keys.iter().take(5) {
-            log::debug!("[ssh_connection] Known host: {}", host);
-        }
-
-        // Create shared state
-        let shared_state = Arc::new(Mutex::new(SharedHostKeyState::default()));
-
-        // SSH client configuration
-        let config = client::Config {
-            inactivity_timeout: Some(Duration::from_secs(10)),
-            ..Default::default()
-        };
-
         let addr = format!("{}:{}", hostname, port);
         debug_log.push(format!("Connecting to {}", addr));
 
-        // Establish connection (with timeout)
-        let handler = ClientHandler::new(&hostname, port, known_host_keys, shared_state.clone());
-        let connect_result = timeout(
-            Duration::from_secs(10),
-            client::connect(Arc::new(config), &addr, handler),
+        let algorithms = AlgorithmPreferences::from_host_config(&host_config);
+        for note in &algorithms.notes {
+            log::warn!("[ssh_connection] {}", note);
+            debug_log.push(note.clone());
+        }
+
+        let connect_result = Self::connect_and_check_host_key(
+            &hostname,
+            port,
+            host_config.host_key_alias.as_deref(),
+            algorithms.preferred.clone(),
         )
         .await;
 
-        let mut session = match connect_result {
-            Ok(Ok(session)) => session,
-            Ok(Err(e)) => {
-                let error_msg = e.to_string();
-                let (error_type, suggestion) = if error_msg.contains("Connection refused") {
+        let (mut session, host_key_state) = match connect_result {
+            Ok(connected) => connected,
+            Err(ConnectFailure::Failed(error_msg)) => {
+                let (error_type, suggestion) = if Self::is_rate_limited(&error_msg) {
+                    (
+                        SshErrorType::RateLimited,
+                        "The server is limiting connections. Wait a few minutes before testing again.".to_string(),
+                    )
+                } else if error_msg.contains("Connection refused") {
                     (
                         SshErrorType::ConnectionRefused,
                         "Connection refused. The SSH server may not be running or a firewall is blocking.".to_string(),
@@ -653,102 +2093,147 @@ impl SshConnectionService {
                     )
                 };
 
+                let suggested_actions = match error_type {
+                    SshErrorType::DnsFailed => {
+                        vec![
+                            SuggestedAction::new("edit-host-config", "Edit host configuration")
+                                .with_param("hostAlias", host_alias),
+                        ]
+                    }
+                    _ => vec![SuggestedAction::new("retry", "Test again")],
+                };
+
                 return Ok(ConnectionTestResult {
-                    success: false,
-                    output: error_msg.clone(),
                     platform,
-                    error_type: Some(error_type.clone()),
-                    error_details: Some(SshErrorDetails {
-                        error_type,
-                        raw_message: error_msg,
-                        suggestion,
-                        can_auto_fix: false,
-                        fix_type: None,
-                        fix_params: None,
-                    }),
-                    host_to_remove: None,
-                    host_to_add: None,
                     identity_file: Some(key_path.to_string_lossy().to_string()),
                     debug_log: Some(debug_log.join("\n")),
+                    ..ConnectionTestResult::failure(
+                        error_msg.clone(),
+                        SshErrorDetails {
+                            error_type,
+                            raw_message: error_msg,
+                            suggestion,
+                            can_auto_fix: false,
+                            fix_type: None,
+                            fix_params: None,
+                            suggested_actions,
+                        },
+                    )
                 });
             }
-            Err(_) => {
+            Err(ConnectFailure::Timeout) => {
                 return Ok(ConnectionTestResult {
-                    success: false,
-                    output: "Connection timed out".to_string(),
                     platform,
-                    error_type: Some(SshErrorType::Timeout),
-                    error_details: Some(SshErrorDetails {
-                        error_type: SshErrorType::Timeout,
-                        raw_message: "Connection timed out after 10 seconds".to_string(),
-                        suggestion: "Check your network connection and firewall settings."
-                            .to_string(),
-                        can_auto_fix: false,
-                        fix_type: None,
-                        fix_params: None,
-                    }),
-                    host_to_remove: None,
-                    host_to_add: None,
                     identity_file: Some(key_path.to_string_lossy().to_string()),
                     debug_log: Some(debug_log.join("\n")),
+                    ..ConnectionTestResult::failure(
+                        "Connection timed out".to_string(),
+                        SshErrorDetails {
+                            error_type: SshErrorType::Timeout,
+                            raw_message: "Connection timed out after 10 seconds".to_string(),
+                            suggestion: "Check your network connection and firewall settings."
+                                .to_string(),
+                            can_auto_fix: false,
+                            fix_type: None,
+                            fix_params: None,
+                            suggested_actions: vec![SuggestedAction::new("retry", "Test again")],
+                        },
+                    )
                 });
             }
         };
 
         debug_log.push("Connected, checking host key...".to_string());
 
+        let host_key_randomart = host_key_state
+            .server_key_fingerprint
+            .as_deref()
+            .and_then(|key| ssh_key::PublicKey::from_openssh(key).ok())
+            .map(|key| KeyManager::randomart_for(&key, ssh_key::HashAlg::Sha256));
+
+        // Name the host's key is stored under, which the known_hosts fixes must use
+        let host_key_alias = host_config.host_key_alias.clone();
+        let (known_hosts_name, known_hosts_port) =
+            KnownHostsService::entry_target(&hostname, port, host_key_alias.as_deref());
+
         // Check host key status
-        let host_key_state = shared_state.lock().await.clone();
         match host_key_state.status {
             KnownHostStatus::Unknown => {
                 debug_log.push("Host key is unknown (first time connection)".to_string());
                 return Ok(ConnectionTestResult {
-                    success: false,
-                    output: "This is the first time connecting to this server. SSH needs to verify the server's identity.".to_string(),
                     platform,
-                    error_type: Some(SshErrorType::HostKeyUnknown),
-                    error_details: Some(SshErrorDetails {
-                        error_type: SshErrorType::HostKeyUnknown,
-                        raw_message: "Host key verification failed".to_string(),
-                        suggestion: "Add this host to your known_hosts file to continue.".to_string(),
-                        can_auto_fix: true,
-                        fix_type: Some("add-known-host".to_string()),
-                        fix_params: Some({
-                            let mut params = std::collections::HashMap::new();
-                            params.insert("hostname".to_string(), hostname.clone());
-                            params.insert("port".to_string(), port.to_string());
-                            params
-                        }),
-                    }),
-                    host_to_remove: None,
                     host_to_add: Some(hostname.clone()),
+                    known_hosts_file: KnownHostsService::default_known_hosts_file(),
                     identity_file: Some(key_path.to_string_lossy().to_string()),
+                    host_key_randomart: host_key_randomart.clone(),
                     debug_log: Some(debug_log.join("\n")),
+                    ..ConnectionTestResult::failure(
+                        "This is the first time connecting to this server. SSH needs to verify the server's identity.".to_string(),
+                        SshErrorDetails {
+                            error_type: SshErrorType::HostKeyUnknown,
+                            raw_message: "Host key verification failed".to_string(),
+                            suggestion: "Add this host to your known_hosts file to continue."
+                                .to_string(),
+                            can_auto_fix: true,
+                            fix_type: Some("add-known-host".to_string()),
+                            fix_params: Some({
+                                let mut params = std::collections::HashMap::new();
+                                params.insert("hostname".to_string(), hostname.clone());
+                                params.insert("port".to_string(), port.to_string());
+                                if let Some(ref alias) = host_key_alias {
+                                    params.insert("hostKeyAlias".to_string(), alias.clone());
+                                }
+                                params
+                            }),
+                            suggested_actions: vec![{
+                                let action =
+                                    SuggestedAction::new("add-known-host", "Trust this host")
+                                        .with_param("hostname", hostname.clone())
+                                        .with_param("port", port.to_string());
+                                match host_key_alias {
+                                    Some(ref alias) => {
+                                        action.with_param("hostKeyAlias", alias.clone())
+                                    }
+                                    None => action,
+                                }
+                            }],
+                        },
+                    )
                 });
             }
             KnownHostStatus::Changed => {
                 debug_log.push("Host key has CHANGED!".to_string());
                 return Ok(ConnectionTestResult {
-                    success: false,
-                    output: "WARNING: REMOTE HOST IDENTIFICATION HAS CHANGED!".to_string(),
                     platform,
-                    error_type: Some(SshErrorType::HostKeyChanged),
-                    error_details: Some(SshErrorDetails {
-                        error_type: SshErrorType::HostKeyChanged,
-                        raw_message: "Host key verification failed. The server key has changed.".to_string(),
-                        suggestion: "If this is expected (server reinstall), remove the old key from known_hosts.".to_string(),
-                        can_auto_fix: true,
-                        fix_type: Some("remove-known-host".to_string()),
-                        fix_params: Some({
-                            let mut params = std::collections::HashMap::new();
-                            params.insert("hostname".to_string(), hostname.clone());
-                            params
-                        }),
-                    }),
-                    host_to_remove: Some(hostname.clone()),
-                    host_to_add: None,
+                    host_to_remove: Some(known_hosts_name.clone()),
+                    port_to_remove: Some(known_hosts_port),
+                    known_hosts_file: KnownHostsService::default_known_hosts_file(),
                     identity_file: Some(key_path.to_string_lossy().to_string()),
+                    host_key_randomart: host_key_randomart.clone(),
                     debug_log: Some(debug_log.join("\n")),
+                    ..ConnectionTestResult::failure(
+                        "WARNING: REMOTE HOST IDENTIFICATION HAS CHANGED!".to_string(),
+                        SshErrorDetails {
+                            error_type: SshErrorType::HostKeyChanged,
+                            raw_message: "Host key verification failed. The server key has changed.".to_string(),
+                            suggestion: "If this is expected (server reinstall), remove the old key from known_hosts.".to_string(),
+                            can_auto_fix: true,
+                            fix_type: Some("remove-known-host".to_string()),
+                            fix_params: Some({
+                                let mut params = std::collections::HashMap::new();
+                                params.insert("hostname".to_string(), known_hosts_name.clone());
+                                params.insert("port".to_string(), known_hosts_port.to_string());
+                                params
+                            }),
+                            suggested_actions: vec![
+                                SuggestedAction::new("remove-known-host", "Remove old host key")
+                                    .with_param("hostname", known_hosts_name.clone())
+                                    .with_param("port", known_hosts_port.to_string()),
+                                SuggestedAction::new("open-docs", "Learn about host key changes")
+                                    .with_param("topic", "host-key-changed"),
+                            ],
+                        },
+                    )
                 });
             }
             KnownHostStatus::Matched => {
@@ -758,34 +2243,150 @@ impl SshConnectionService {
 
         // === Step 2: After host key verification, try authentication ===
         // Strategy: Try loading key directly first, use SSH agent if encrypted
+        // With prefer_agent, the agent goes first and the key file is the fallback
+
+        let mut auth_attempts = Vec::new();
+        let mut agent_first = None;
+        if prefer_agent.unwrap_or(false) {
+            debug_log.push("Auth order: SSH agent, then key file".to_string());
+            let result = Self::authenticate_with_agent(
+                &mut session,
+                &user,
+                &key_path,
+                &host_config,
+                &mut auth_attempts,
+            )
+            .await;
+            match &result {
+                Ok(true) => debug_log.push("Authenticated with SSH agent".to_string()),
+                Ok(false) => {
+                    debug_log.push("SSH agent keys were rejected, trying key file".to_string())
+                }
+                Err(e) => debug_log.push(format!("SSH agent unavailable ({}), trying key file", e)),
+            }
+            agent_first = Some(result);
+        } else {
+            debug_log
+                .push("Auth order: key file, then SSH agent if the key is encrypted".to_string());
+        }
+        let agent_authenticated = matches!(agent_first, Some(Ok(true)));
 
         debug_log.push("Loading private key...".to_string());
 
         // Try loading key directly
         let direct_key_result = Self::load_private_key(&key_path).await;
+        let loaded_from_file = !agent_authenticated && direct_key_result.is_ok();
 
         let auth_result = match direct_key_result {
+            _ if agent_authenticated => Ok(true),
             Ok(key_pair) => {
                 // Key can be loaded directly, use it for authentication
+                if !algorithms.accepts_key(&key_pair) {
+                    debug_log.push(format!(
+                        "Key algorithm {} is not in PubkeyAcceptedAlgorithms; OpenSSH would not offer this key",
+                        key_pair.name()
+                    ));
+                }
                 debug_log.push("Key loaded directly, authenticating...".to_string());
-                session
-                    .authenticate_publickey(&user, Arc::new(key_pair))
-                    .await
+
+                let certificate = match Self::resolve_certificate_file(&host_config, &key_path) {
+                    Some(cert_path) => match KeyManager::load_certificate(&cert_path).await {
+                        Ok(loaded) => Some(loaded),
+                        Err(e) => {
+                            debug_log.push(format!(
+                                "Ignoring certificate {}: {}",
+                                cert_path.display(),
+                                e
+                            ));
+                            None
+                        }
+                    },
+                    None => None,
+                };
+
+                let public_key = key_pair.clone_public_key();
+                let result = match certificate {
+                    Some((cert, cert_info)) => {
+                        if let Some((error_type, message)) =
+                            Self::certificate_problem(&cert_info, &user)
+                        {
+                            debug_log.push(message.clone());
+                            let suggested_user = match error_type {
+                                SshErrorType::CertificatePrincipalMismatch => {
+                                    cert_info.principals.first().cloned()
+                                }
+                                _ => None,
+                            };
+                            return Ok(ConnectionTestResult {
+                                platform,
+                                identity_file: Some(key_path.to_string_lossy().to_string()),
+                                host_key_randomart: host_key_randomart.clone(),
+                                debug_log: Some(debug_log.join("\n")),
+                                suggested_user,
+                                ..ConnectionTestResult::failure(
+                                    message.clone(),
+                                    SshErrorDetails {
+                                        error_type,
+                                        raw_message: message,
+                                        suggestion: "Ask your certificate authority for a new certificate for this user.".to_string(),
+                                        can_auto_fix: false,
+                                        fix_type: None,
+                                        fix_params: None,
+                                        suggested_actions: vec![SuggestedAction::new(
+                                            "edit-host-config",
+                                            "Edit host configuration",
+                                        )
+                                        .with_param("hostAlias", host_alias)],
+                                    },
+                                )
+                            });
+                        }
+
+                        debug_log.push(format!("Presenting certificate {}", cert_info.path));
+                        session
+                            .authenticate_openssh_cert(&user, Arc::new(key_pair), cert)
+                            .await
+                    }
+                    None => {
+                        session
+                            .authenticate_publickey(&user, Arc::new(key_pair))
+                            .await
+                    }
+                };
+                if let Ok(ref public_key) = public_key {
+                    auth_attempts.push(AuthAttempt::new(public_key, AuthKeySource::File, &result));
+                }
+                result
             }
             Err(e) => {
                 let error_msg = e.to_string();
-                let is_encrypted = error_msg.contains("passphrase")
-                    || error_msg.contains("encrypted")
-                    || error_msg.contains("decrypt");
-
-                if is_encrypted {
-                    // Key is encrypted, try using SSH agent
-                    debug_log.push("Key is encrypted, trying SSH agent...".to_string());
-                    log::info!(
-                        "[ssh_connection] Key is encrypted, attempting SSH agent authentication"
-                    );
-
-                    match Self::authenticate_with_agent(&mut session, &user, &key_path).await {
+
+                if matches!(e, SshBuddyError::PassphraseRequired { .. }) {
+                    // Key is encrypted, try using SSH agent unless it was already tried
+                    let agent_result = match agent_first.take() {
+                        Some(result) => {
+                            debug_log.push(
+                                "Key is encrypted and the SSH agent was already tried".to_string(),
+                            );
+                            result
+                        }
+                        None => {
+                            debug_log.push("Key is encrypted, trying SSH agent...".to_string());
+                            log::info!(
+                                "[ssh_connection] Key is encrypted, attempting SSH agent authentication"
+                            );
+                            Self::authenticate_with_agent(
+                                &mut session,
+                                &user,
+                                &key_path,
+                                &host_config,
+                                &mut auth_attempts,
+                            )
+                            .await
+                        }
+                    };
+
+                    match agent_result {
                         Ok(authenticated) => Ok(authenticated),
                         Err(agent_err) => {
                             // Agent authentication failed, return original encryption error
@@ -793,55 +2394,65 @@ impl SshConnectionService {
                             debug_log.push(format!("Agent auth failed: {}", agent_err));
 
                             return Ok(ConnectionTestResult {
-                                success: false,
-                                output: "Key requires passphrase and is not in SSH agent"
-                                    .to_string(),
                                 platform,
-                                error_type: Some(SshErrorType::PermissionDeniedPassphrase),
-                                error_details: Some(SshErrorDetails {
-                                    error_type: SshErrorType::PermissionDeniedPassphrase,
-                                    raw_message: format!(
-                                        "Key encrypted: {}. Agent error: {}",
-                                        error_msg, agent_err
-                                    ),
-                                    suggestion: "Add your key to the SSH agent first.".to_string(),
-                                    can_auto_fix: true,
-                                    fix_type: Some("ssh-add".to_string()),
-                                    fix_params: Some({
-                                        let mut params = std::collections::HashMap::new();
-                                        params.insert(
-                                            "keyPath".to_string(),
-                                            key_path.to_string_lossy().to_string(),
-                                        );
-                                        params
-                                    }),
-                                }),
-                                host_to_remove: None,
-                                host_to_add: None,
                                 identity_file: Some(key_path.to_string_lossy().to_string()),
+                                host_key_randomart: host_key_randomart.clone(),
+                                auth_attempts: auth_attempts.clone(),
                                 debug_log: Some(debug_log.join("\n")),
+                                ..ConnectionTestResult::failure(
+                                    "Key requires passphrase and is not in SSH agent".to_string(),
+                                    SshErrorDetails {
+                                        error_type: SshErrorType::PermissionDeniedPassphrase,
+                                        raw_message: format!(
+                                            "Key encrypted: {}. Agent error: {}",
+                                            error_msg, agent_err
+                                        ),
+                                        suggestion: "Add your key to the SSH agent first."
+                                            .to_string(),
+                                        can_auto_fix: true,
+                                        fix_type: Some("ssh-add".to_string()),
+                                        fix_params: Some({
+                                            let mut params = std::collections::HashMap::new();
+                                            params.insert(
+                                                "keyPath".to_string(),
+                                                key_path.to_string_lossy().to_string(),
+                                            );
+                                            params
+                                        }),
+                                        suggested_actions: vec![SuggestedAction::new(
+                                            "ssh-add",
+                                            "Add key to SSH agent",
+                                        )
+                                        .with_param("keyPath", key_path.to_string_lossy())],
+                                    },
+                                )
                             });
                         }
                     }
                 } else {
                     // Other errors (not encryption related)
                     return Ok(ConnectionTestResult {
-                        success: false,
-                        output: error_msg.clone(),
                         platform,
-                        error_type: Some(SshErrorType::PermissionDenied),
-                        error_details: Some(SshErrorDetails {
-                            error_type: SshErrorType::PermissionDenied,
-                            raw_message: error_msg,
-                            suggestion: "Failed to load private key.".to_string(),
-                            can_auto_fix: false,
-                            fix_type: None,
-                            fix_params: None,
-                        }),
-                        host_to_remove: None,
-                        host_to_add: None,
                         identity_file: Some(key_path.to_string_lossy().to_string()),
+                        host_key_randomart: host_key_randomart.clone(),
+                        auth_attempts: auth_attempts.clone(),
                         debug_log: Some(debug_log.join("\n")),
+                        ..ConnectionTestResult::failure(
+                            error_msg.clone(),
+                            SshErrorDetails {
+                                error_type: SshErrorType::PermissionDenied,
+                                raw_message: error_msg,
+                                suggestion: "Failed to load private key.".to_string(),
+                                can_auto_fix: false,
+                                fix_type: None,
+                                fix_params: None,
+                                suggested_actions: vec![SuggestedAction::new(
+                                    "regenerate-key",
+                                    "Generate a replacement key",
+                                )
+                                .with_param("keyPath", key_path.to_string_lossy())],
+                            },
+                        )
                     });
                 }
             }
@@ -852,75 +2463,145 @@ impl SshConnectionService {
                 if authenticated {
                     debug_log.push("Authentication successful".to_string());
 
+                    if loaded_from_file {
+                        if let Some(lifetime_secs) = host_config.add_keys_to_agent_lifetime() {
+                            Self::add_used_key_to_agent(
+                                &host_config,
+                                &key_path,
+                                lifetime_secs,
+                                &mut debug_log,
+                            )
+                            .await;
+                        }
+                    }
+
                     // Try opening channel to get welcome message
-                    let output = match session.channel_open_session().await {
-                        Ok(mut channel) => {
-                            // For Git platforms, requesting shell returns welcome message
-                            let _ = channel.request_shell(false).await;
-
-                            // Wait for response (with timeout)
-                            let mut output = String::new();
-                            let wait_result = timeout(Duration::from_secs(3), async {
-                                while let Some(msg) = channel.wait().await {
-                                    match msg {
-                                        ChannelMsg::Data { data } => {
-                                            output.push_str(&String::from_utf8_lossy(&data));
-                                        }
-                                        ChannelMsg::ExtendedData { data, .. } => {
-                                            output.push_str(&String::from_utf8_lossy(&data));
+                    let (output, channel_closed) = if fetch_banner {
+                        match session.channel_open_session().await {
+                            Ok(mut channel) => {
+                                // For Git platforms, requesting shell returns welcome message
+                                let _ = channel.request_shell(false).await;
+
+                                // Wait for response (with timeout)
+                                let mut output = String::new();
+                                let mut closed = false;
+                                let wait_result = timeout(Duration::from_secs(3), async {
+                                    while let Some(msg) = channel.wait().await {
+                                        match msg {
+                                            ChannelMsg::Data { data } => {
+                                                output.push_str(&String::from_utf8_lossy(&data));
+                                            }
+                                            ChannelMsg::ExtendedData { data, .. } => {
+                                                output.push_str(&String::from_utf8_lossy(&data));
+                                            }
+                                            ChannelMsg::Eof | ChannelMsg::Close => {
+                                                closed = true;
+                                                break;
+                                            }
+                                            _ => {}
                                         }
-                                        ChannelMsg::Eof | ChannelMsg::Close => break,
-                                        _ => {}
                                     }
-                                }
-                            })
-                            .await;
-
-                            // Ignore timeout error, as some servers don't close connection
-                            let _ = wait_result;
-                            output
+                                })
+                                .await;
+
+                                // Ignore timeout error, as some servers don't close connection
+                                let _ = wait_result;
+                                debug_log.push(if closed {
+                                    "Server closed the channel".to_string()
+                                } else {
+                                    "Channel still open after waiting for a banner".to_string()
+                                });
+                                (output, closed)
+                            }
+                            Err(_) => ("Authentication successful".to_string(), false),
                         }
-                        Err(_) => "Authentication successful".to_string(),
+                    } else {
+                        (String::new(), false)
                     };
 
+                    if let Some(account) = Self::banner_account(&output) {
+                        debug_log.push(format!("Key resolved to account: {}", account));
+                    }
+                    if let Some(expected) = Self::expected_user_from_banner(&output)
+                        .filter(|expected| *expected != user)
+                    {
+                        debug_log.push(format!("Server expects user: {}", expected));
+                        let message = output.trim().to_string();
+                        let suggested_actions =
+                            Self::permission_denied_actions(&key_path, platform.as_deref());
+                        return Ok(ConnectionTestResult {
+                            platform,
+                            identity_file: Some(key_path.to_string_lossy().to_string()),
+                            host_key_randomart: host_key_randomart.clone(),
+                            auth_attempts: auth_attempts.clone(),
+                            debug_log: Some(debug_log.join("\n")),
+                            suggested_user: Some(expected),
+                            ..ConnectionTestResult::failure(
+                                message.clone(),
+                                SshErrorDetails {
+                                    error_type: SshErrorType::PermissionDenied,
+                                    raw_message: message,
+                                    suggestion: Self::permission_denied_suggestion(
+                                        &user,
+                                        Some(&expected),
+                                    ),
+                                    can_auto_fix: false,
+                                    fix_type: None,
+                                    fix_params: None,
+                                    suggested_actions,
+                                },
+                            )
+                        });
+                    }
+
                     let success = Self::is_auth_success(&output) || authenticated;
+                    let display_output =
+                        Self::success_output(&output, platform.as_deref(), channel_closed);
+                    if display_output != output && !output.trim().is_empty() {
+                        debug_log.push(format!(
+                            "Shell output not shown: {}",
+                            output.chars().take(200).collect::<String>()
+                        ));
+                    }
 
                     Ok(ConnectionTestResult {
                         success,
-                        output: if output.is_empty() {
-                            "Authentication successful".to_string()
-                        } else {
-                            output
-                        },
+                        output: display_output,
                         platform,
-                        error_type: None,
-                        error_details: None,
-                        host_to_remove: None,
-                        host_to_add: None,
                         identity_file: Some(key_path.to_string_lossy().to_string()),
+                        host_key_randomart: host_key_randomart.clone(),
+                        auth_attempts: auth_attempts.clone(),
                         debug_log: Some(debug_log.join("\n")),
+                        ..ConnectionTestResult::default()
                     })
                 } else {
                     debug_log.push("Authentication failed".to_string());
+                    let suggested_actions =
+                        Self::permission_denied_actions(&key_path, platform.as_deref());
+                    let suggested_user = Self::wrong_user_hint(platform.as_deref(), &user);
 
                     Ok(ConnectionTestResult {
-                        success: false,
-                        output: "Permission denied (publickey)".to_string(),
                         platform,
-                        error_type: Some(SshErrorType::PermissionDenied),
-                        error_details: Some(SshErrorDetails {
-                            error_type: SshErrorType::PermissionDenied,
-                            raw_message: "Authentication failed".to_string(),
-                            suggestion: "Check that your public key is added to the server."
-                                .to_string(),
-                            can_auto_fix: false,
-                            fix_type: None,
-                            fix_params: None,
-                        }),
-                        host_to_remove: None,
-                        host_to_add: None,
                         identity_file: Some(key_path.to_string_lossy().to_string()),
+                        host_key_randomart: host_key_randomart.clone(),
+                        auth_attempts: auth_attempts.clone(),
                         debug_log: Some(debug_log.join("\n")),
+                        suggested_user,
+                        ..ConnectionTestResult::failure(
+                            "Permission denied (publickey)".to_string(),
+                            SshErrorDetails {
+                                error_type: SshErrorType::PermissionDenied,
+                                raw_message: "Authentication failed".to_string(),
+                                suggestion: Self::permission_denied_suggestion(
+                                    &user,
+                                    suggested_user.as_deref(),
+                                ),
+                                can_auto_fix: false,
+                                fix_type: None,
+                                fix_params: None,
+                                suggested_actions,
+                            },
+                        )
                     })
                 }
             }
@@ -928,24 +2609,49 @@ impl SshConnectionService {
                 let error_msg = e.to_string();
                 debug_log.push(format!("Authentication error: {}", error_msg));
 
+                if Self::is_rate_limited(&error_msg) {
+                    return Ok(ConnectionTestResult {
+                        platform,
+                        identity_file: Some(key_path.to_string_lossy().to_string()),
+                        host_key_randomart: host_key_randomart.clone(),
+                        auth_attempts: auth_attempts.clone(),
+                        debug_log: Some(debug_log.join("\n")),
+                        ..ConnectionTestResult::failure(
+                            format!("Rate limited: {}", error_msg),
+                            Self::rate_limited_details(
+                                error_msg,
+                                "The server refused further attempts for now. Wait a few minutes, and check that only the right key is offered (IdentitiesOnly).".to_string(),
+                            ),
+                        )
+                    });
+                }
+
+                let suggested_actions =
+                    Self::permission_denied_actions(&key_path, platform.as_deref());
+                let suggested_user = Self::wrong_user_hint(platform.as_deref(), &user);
+
                 Ok(ConnectionTestResult {
-                    success: false,
-                    output: format!("Authentication failed: {}", error_msg),
                     platform,
-                    error_type: Some(SshErrorType::PermissionDenied),
-                    error_details: Some(SshErrorDetails {
-                        error_type: SshErrorType::PermissionDenied,
-                        raw_message: error_msg,
-                        suggestion: "Check that your public key is added to the server."
-                            .to_string(),
-                        can_auto_fix: false,
-                        fix_type: None,
-                        fix_params: None,
-                    }),
-                    host_to_remove: None,
-                    host_to_add: None,
                     identity_file: Some(key_path.to_string_lossy().to_string()),
+                    host_key_randomart: host_key_randomart.clone(),
+                    auth_attempts: auth_attempts.clone(),
                     debug_log: Some(debug_log.join("\n")),
+                    suggested_user,
+                    ..ConnectionTestResult::failure(
+                        format!("Authentication failed: {}", error_msg),
+                        SshErrorDetails {
+                            error_type: SshErrorType::PermissionDenied,
+                            raw_message: error_msg,
+                            suggestion: Self::permission_denied_suggestion(
+                                &user,
+                                suggested_user.as_deref(),
+                            ),
+                            can_auto_fix: false,
+                            fix_type: None,
+                            fix_params: None,
+                            suggested_actions,
+                        },
+                    )
                 })
             }
         }
@@ -955,98 +2661,23 @@ impl SshConnectionService {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::ConfigWarningKind;
     use tempfile::TempDir;
-    use tokio::fs;
-
-    /// Helper to create a temporary known_hosts file
-    async fn create_temp_known_hosts(content: &str) -> (TempDir, PathBuf) {
-        let temp_dir = TempDir::new().expect("Failed to create temp dir");
-        let ssh_dir = temp_dir.path().join(".ssh");
-        fs::create_dir_all(&ssh_dir)
-            .await
-            .expect("Failed to create .ssh dir");
-        let known_hosts_path = ssh_dir.join("known_hosts");
-        fs::write(&known_hosts_path, content)
-            .await
-            .expect("Failed to write known_hosts");
-        (temp_dir, known_hosts_path)
-    }
-
-    /// Parse known_hosts content directly for testing
-    fn parse_known_hosts_content(content: &str) -> HashMap<String, Vec<String>> {
-        let mut known_hosts: HashMap<String, Vec<String>> = HashMap::new();
-
-        for line in content.lines() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with('#') {
-                continue;
-            }
-            if line.starts_with("|1|") {
-                continue;
-            }
-
-            let parts: Vec<&str> = line.splitn(3, ' ').collect();
-            if parts.len() < 2 {
-                continue;
-            }
-
-            let hostnames = parts[0];
-            let key_data = if parts.len() >= 2 {
-                parts[1..].join(" ")
-            } else {
-                continue;
-            };
-
-            for hostname in hostnames.split(',') {
-                let hostname = hostname.trim();
-                known_hosts
-                    .entry(hostname.to_string())
-                    .or_default()
-                    .push(key_data.clone());
-            }
-        }
-
-        known_hosts
-    }
-
-    /// Check host key status for testing
-    fn check_host_key_status(
-        hostname: &str,
-        port: u16,
-        server_key_base64: &str,
-        known_hosts: &HashMap<String, Vec<String>>,
-    ) -> KnownHostStatus {
-        let host_variants = if port == 22 {
-            vec![hostname.to_string()]
-        } else {
-            vec![format!("[{}]:{}", hostname, port), hostname.to_string()]
-        };
-
-        let mut found_host = false;
-        let mut key_matched = false;
-
-        for variant in &host_variants {
-            if let Some(known_keys) = known_hosts.get(variant) {
-                found_host = true;
-                for known_key in known_keys {
-                    if known_key.contains(server_key_base64) {
-                        key_matched = true;
-                        break;
-                    }
-                }
-                if key_matched {
-                    break;
-                }
-            }
-        }
+    use tokio::fs;
 
-        if key_matched {
-            KnownHostStatus::Matched
-        } else if found_host {
-            KnownHostStatus::Changed
-        } else {
-            KnownHostStatus::Unknown
-        }
+    /// Parse known_hosts content directly for testing
+    fn parse_known_hosts_content(content: &str) -> KnownHostKeys {
+        KnownHostsService::parse_known_hosts(content)
+    }
+
+    /// Check host key status for testing
+    fn check_host_key_status(
+        hostname: &str,
+        port: u16,
+        server_key_base64: &str,
+        known_hosts: &KnownHostKeys,
+    ) -> KnownHostStatus {
+        KnownHostsService::check_host_key(known_hosts, hostname, port, server_key_base64)
     }
 
     // ========================================
@@ -1081,15 +2712,17 @@ gitlab.com ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABgQCexample
     }
 
     #[test]
-    fn test_parse_known_hosts_skip_hashed() {
+    fn test_parse_known_hosts_hashed_kept_separately() {
         let content = r#"|1|HwVWh3VnvQS3+5ZVq7YlL6C3Z1o=|kVnJxQEyMeZF5rC0= ssh-ed25519 AAAAC3NzaC1
 github.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnk
 "#;
         let hosts = parse_known_hosts_content(content);
 
-        // Should only have github.com, hashed entries are skipped
-        assert_eq!(hosts.len(), 1);
+        // Hashed entries stay under their hashed field, never under a host name
+        assert_eq!(hosts.len(), 2);
         assert!(hosts.contains_key("github.com"));
+        assert_eq!(hosts.get("github.com").unwrap().len(), 1);
+        assert!(hosts.contains_key("|1|HwVWh3VnvQS3+5ZVq7YlL6C3Z1o=|kVnJxQEyMeZF5rC0="));
     }
 
     #[test]
@@ -1129,6 +2762,98 @@ github.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnk
         assert!(hosts.contains_key("github.com"));
     }
 
+    #[tokio::test]
+    async fn test_write_config_rejects_hard_errors() {
+        let temp = TempDir::new().unwrap();
+        let ssh_dir = temp.path().join(".ssh");
+        std::fs::create_dir_all(&ssh_dir).unwrap();
+        let config_path = ssh_dir.join("config");
+        let original = "Host github\n    HostName github.com\n";
+        std::fs::write(&config_path, original).unwrap();
+
+        let result = SshConnectionService::write_config_in(
+            &ssh_dir,
+            "Host github\n    Port abc\n",
+            1_700_000_000,
+        )
+        .await;
+        assert!(matches!(result, Err(SshBuddyError::InvalidConfig { .. })));
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), original);
+        assert!(!ssh_dir.join("config.1700000000.bak").exists());
+
+        // Unsupported but valid directives don't block saving
+        let updated = "Include ~/.ssh/config.d/*\nHost github\n    HostName github.com\n";
+        let backup = SshConnectionService::write_config_in(&ssh_dir, updated, 1_700_000_000)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), original);
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), updated);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&config_path)
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_config_allows_unexpanded_paths() {
+        let temp = TempDir::new().unwrap();
+        let ssh_dir = temp.path().join(".ssh");
+        std::fs::create_dir_all(&ssh_dir).unwrap();
+        let config_path = ssh_dir.join("config");
+
+        // ~user paths are valid for OpenSSH even though SSH Buddy can't expand them
+        let content = "Host corp\n    CertificateFile ~other/cert\n";
+        let (_, warnings) = SshConfigParser::parse_with_diagnostics(content);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, ConfigWarningKind::PathExpansion);
+
+        SshConnectionService::write_config_in(&ssh_dir, content, 1_700_000_000)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), content);
+    }
+
+    #[tokio::test]
+    async fn test_set_global_option_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let ssh_dir = temp.path().join(".ssh");
+        std::fs::create_dir_all(&ssh_dir).unwrap();
+        let config_path = ssh_dir.join("config");
+        std::fs::write(&config_path, "Host github\n    HostName github.com\n").unwrap();
+
+        let backup =
+            SshConnectionService::set_global_option_in(&ssh_dir, "IdentitiesOnly", "yes", 1)
+                .await
+                .unwrap();
+        assert!(backup.is_some());
+        SshConnectionService::set_global_option_in(&ssh_dir, "IdentitiesOnly", "no", 2)
+            .await
+            .unwrap();
+
+        let content = std::fs::read_to_string(&config_path).unwrap();
+        let options = SshConfigParser::global_options(&content);
+        assert_eq!(
+            options.get("IdentitiesOnly").map(String::as_str),
+            Some("no")
+        );
+        assert_eq!(content.matches("Host *").count(), 1);
+        assert!(content.starts_with("Host *\n"));
+        assert!(content.ends_with("Host github\n    HostName github.com\n"));
+
+        let result =
+            SshConnectionService::set_global_option_in(&ssh_dir, "IdentitiesOnly", "maybe", 3)
+                .await;
+        assert!(matches!(result, Err(SshBuddyError::InvalidConfig { .. })));
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), content);
+    }
+
     // ========================================
     // check_server_key logic tests
     // ========================================
@@ -1277,4 +3002,587 @@ github.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnk
         assert!(!SshConnectionService::is_auth_success("not authenticated"));
         assert!(!SshConnectionService::is_auth_success("Connection refused"));
     }
+
+    #[test]
+    fn test_append_bounded() {
+        let mut buffer = Vec::new();
+        assert!(SshConnectionService::append_bounded(
+            &mut buffer,
+            b"Welcome\r\n",
+            16
+        ));
+        assert!(SshConnectionService::append_bounded(
+            &mut buffer,
+            b"Maint",
+            16
+        ));
+        assert!(!SshConnectionService::append_bounded(
+            &mut buffer,
+            b"enance at 2am",
+            16
+        ));
+        assert_eq!(buffer, b"Welcome\r\nMainten");
+        assert!(!SshConnectionService::append_bounded(
+            &mut buffer,
+            b"more",
+            16
+        ));
+        assert_eq!(buffer.len(), 16);
+    }
+
+    #[test]
+    fn test_offered_key_count() {
+        assert_eq!(SshConnectionService::offered_key_count(6, 1, false), 7);
+        assert!(SshConnectionService::offered_key_count(6, 1, false) > TYPICAL_MAX_AUTH_TRIES);
+        assert_eq!(SshConnectionService::offered_key_count(6, 1, true), 1);
+        assert_eq!(SshConnectionService::offered_key_count(2, 3, false), 5);
+    }
+
+    #[test]
+    fn test_expected_user_from_banner() {
+        assert_eq!(
+            SshConnectionService::expected_user_from_banner(
+                "Please login as the user \"ubuntu\" rather than the user \"root\".\r\n"
+            ),
+            Some("ubuntu".to_string())
+        );
+        assert_eq!(
+            SshConnectionService::expected_user_from_banner(
+                "Please login as the user \"ec2-user\" rather than the user \"root\"."
+            ),
+            Some("ec2-user".to_string())
+        );
+        assert_eq!(
+            SshConnectionService::expected_user_from_banner(
+                "Hi octocat! You've successfully authenticated, but GitHub does not provide shell access."
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_banner_account() {
+        assert_eq!(
+            SshConnectionService::banner_account(
+                "Hi octocat! You've successfully authenticated, but GitHub does not provide shell access."
+            ),
+            Some("octocat".to_string())
+        );
+        assert_eq!(
+            SshConnectionService::banner_account("Welcome to GitLab, @jdoe!"),
+            Some("jdoe".to_string())
+        );
+        assert_eq!(
+            SshConnectionService::banner_account(
+                "authenticated via ssh key.\n\nYou can use git to connect to Bitbucket. Shell access is disabled\nlogged in as team-bot\n"
+            ),
+            Some("team-bot".to_string())
+        );
+        assert_eq!(
+            SshConnectionService::banner_account("Last login: Mon"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_wrong_user_hint() {
+        assert_eq!(
+            SshConnectionService::wrong_user_hint(Some("github"), "octocat"),
+            Some("git".to_string())
+        );
+        assert_eq!(
+            SshConnectionService::wrong_user_hint(Some("github"), "git"),
+            None
+        );
+        assert_eq!(SshConnectionService::wrong_user_hint(None, "alice"), None);
+    }
+
+    #[test]
+    fn test_github_success_as_git_is_not_wrong_user() {
+        let banner = "Hi octocat! You've successfully authenticated, but GitHub does not provide shell access.";
+        assert!(SshConnectionService::is_auth_success(banner));
+        assert_eq!(
+            SshConnectionService::expected_user_from_banner(banner),
+            None
+        );
+        assert_eq!(
+            SshConnectionService::wrong_user_hint(Some("github"), "git"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_permission_denied_suggestion_names_user() {
+        let suggestion = SshConnectionService::permission_denied_suggestion("octocat", Some("git"));
+        assert!(suggestion.contains("\"git\""));
+        assert!(suggestion.contains("\"octocat\""));
+        assert_eq!(
+            SshConnectionService::permission_denied_suggestion("git", None),
+            "Check that your public key is added to the server."
+        );
+    }
+
+    #[test]
+    fn test_parse_auth_methods() {
+        let stderr =
+            "Warning: Permanently added 'example.com' (ED25519) to the list of known hosts.\r\n\
+                      git@example.com: Permission denied (publickey,gssapi-with-mic,password).\r\n";
+        assert_eq!(
+            SshConnectionService::parse_auth_methods(stderr),
+            Some(vec![
+                "publickey".to_string(),
+                "gssapi-with-mic".to_string(),
+                "password".to_string()
+            ])
+        );
+        assert_eq!(
+            SshConnectionService::parse_auth_methods(
+                "ssh: connect to host example.com port 22: Connection refused\n"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_success_output() {
+        let github = "Hi user! You've successfully authenticated, but GitHub does not provide shell access.\n";
+        assert_eq!(
+            SshConnectionService::success_output(github, Some("github"), true),
+            github
+        );
+        // Self-hosted Git servers print a banner and close the channel
+        assert_eq!(
+            SshConnectionService::success_output(github, None, true),
+            github
+        );
+
+        // A plain server closing the channel, or a shell that stays open
+        assert_eq!(
+            SshConnectionService::success_output("", None, true),
+            "Authenticated successfully (no banner)"
+        );
+        assert_eq!(
+            SshConnectionService::success_output("stty: not a tty\n", None, true),
+            "Authenticated successfully (no banner)"
+        );
+        assert_eq!(
+            SshConnectionService::success_output("Welcome to Ubuntu\n$ ", None, false),
+            "Authentication successful"
+        );
+    }
+
+    fn cert_info(principals: &[&str], expired: bool) -> CertInfo {
+        CertInfo {
+            name: "id_ed25519-cert".to_string(),
+            path: "/home/user/.ssh/id_ed25519-cert.pub".to_string(),
+            key_type: crate::models::KeyType::Ed25519,
+            cert_type: "user".to_string(),
+            key_id: "user".to_string(),
+            serial: 1,
+            principals: principals.iter().map(|p| p.to_string()).collect(),
+            valid_after: 0,
+            valid_before: u64::MAX,
+            expired,
+            not_yet_valid: false,
+            fingerprint: "SHA256:key".to_string(),
+            ca_fingerprint: "SHA256:ca".to_string(),
+            comment: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_certificate_problem() {
+        assert!(SshConnectionService::certificate_problem(&cert_info(&[], false), "git").is_none());
+        assert!(SshConnectionService::certificate_problem(
+            &cert_info(&["git", "deploy"], false),
+            "git"
+        )
+        .is_none());
+
+        let (error_type, _) =
+            SshConnectionService::certificate_problem(&cert_info(&["git"], true), "git").unwrap();
+        assert_eq!(error_type, SshErrorType::CertificateExpired);
+
+        let (error_type, message) =
+            SshConnectionService::certificate_problem(&cert_info(&["alice"], false), "git")
+                .unwrap();
+        assert_eq!(error_type, SshErrorType::CertificatePrincipalMismatch);
+        assert!(message.contains("principals: alice"));
+    }
+
+    #[test]
+    fn test_is_rate_limited() {
+        assert!(SshConnectionService::is_rate_limited(
+            "Received disconnect from 140.82.112.3: Too many authentication failures"
+        ));
+        assert!(SshConnectionService::is_rate_limited(
+            "rate limit exceeded, try again later"
+        ));
+        assert!(!SshConnectionService::is_rate_limited("Permission denied"));
+    }
+
+    #[test]
+    fn test_admit_test_limits_per_window() {
+        let start = Instant::now();
+        let mut history = VecDeque::new();
+        for i in 0..RATE_LIMIT_MAX_TESTS {
+            assert!(SshConnectionService::admit_test(
+                &mut history,
+                start + Duration::from_secs(i as u64)
+            )
+            .is_ok());
+        }
+
+        let wait = SshConnectionService::admit_test(&mut history, start + Duration::from_secs(10))
+            .unwrap_err();
+        assert_eq!(wait, RATE_LIMIT_WINDOW - Duration::from_secs(10));
+
+        // Once the first test leaves the window there is room again
+        assert!(SshConnectionService::admit_test(&mut history, start + RATE_LIMIT_WINDOW).is_ok());
+    }
+
+    #[test]
+    fn test_admit_host_test_forgets_idle_hosts() {
+        let start = Instant::now();
+        let mut recent = HashMap::new();
+        assert!(SshConnectionService::admit_host_test(&mut recent, "one:22", start).is_ok());
+        assert!(SshConnectionService::admit_host_test(
+            &mut recent,
+            "two:22",
+            start + Duration::from_secs(30)
+        )
+        .is_ok());
+        assert_eq!(recent.len(), 2);
+
+        // one:22 has no test left in the window and is removed, two:22 is kept
+        let later = start + RATE_LIMIT_WINDOW + Duration::from_secs(1);
+        assert!(SshConnectionService::admit_host_test(&mut recent, "three:22", later).is_ok());
+        let mut hosts: Vec<&str> = recent.keys().map(String::as_str).collect();
+        hosts.sort();
+        assert_eq!(hosts, vec!["three:22", "two:22"]);
+    }
+
+    // ========================================
+    // Suggested action tests
+    // ========================================
+
+    #[test]
+    fn test_suggested_action_params() {
+        let action = SuggestedAction::new("copy-public-key", "Copy public key")
+            .with_param("keyPath", "/home/user/.ssh/id_ed25519");
+
+        assert_eq!(action.id, "copy-public-key");
+        assert_eq!(
+            action.params.unwrap().get("keyPath").map(String::as_str),
+            Some("/home/user/.ssh/id_ed25519")
+        );
+    }
+
+    #[test]
+    fn test_permission_denied_actions_platform_docs() {
+        let key_path = PathBuf::from("/home/user/.ssh/id_ed25519");
+
+        let actions = SshConnectionService::permission_denied_actions(&key_path, Some("github"));
+        let ids: Vec<&str> = actions.iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(ids, vec!["copy-public-key", "open-docs"]);
+
+        let actions = SshConnectionService::permission_denied_actions(&key_path, None);
+        assert_eq!(actions.len(), 1);
+    }
+
+    // ========================================
+    // install_public_key command tests
+    // ========================================
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(SshConnectionService::shell_quote("plain"), "'plain'");
+        assert_eq!(SshConnectionService::shell_quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn test_install_key_command_quotes_key() {
+        let command = SshConnectionService::install_key_command(
+            "ssh-ed25519 AAAAKEY o'brien@host",
+            "ssh-ed25519 AAAAKEY",
+        );
+
+        assert!(command.contains("grep -qF -- 'ssh-ed25519 AAAAKEY' ~/.ssh/authorized_keys"));
+        assert!(command
+            .contains("echo 'ssh-ed25519 AAAAKEY o'\\''brien@host' >> ~/.ssh/authorized_keys"));
+        assert!(command.contains("chmod 700 ~/.ssh"));
+        assert!(command.contains("chmod 600 ~/.ssh/authorized_keys"));
+        assert!(command.contains(KEY_ADDED_MARKER));
+        assert!(command.contains(KEY_PRESENT_MARKER));
+    }
+
+    // ========================================
+    // Identity override tests
+    // ========================================
+
+    #[tokio::test]
+    async fn test_resolve_identity_override_in_ssh_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let ssh_dir = temp_dir.path().join(".ssh");
+        fs::create_dir_all(&ssh_dir).await.unwrap();
+        fs::write(ssh_dir.join("id_work"), "key").await.unwrap();
+
+        let by_name = SshConnectionService::resolve_identity_override("id_work", &ssh_dir).unwrap();
+        assert_eq!(by_name, ssh_dir.join("id_work"));
+
+        let absolute = ssh_dir.join("id_work");
+        let by_path =
+            SshConnectionService::resolve_identity_override(absolute.to_str().unwrap(), &ssh_dir)
+                .unwrap();
+        assert_eq!(by_path, absolute);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_identity_override_rejects_outside_ssh_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let ssh_dir = temp_dir.path().join(".ssh");
+        fs::create_dir_all(&ssh_dir).await.unwrap();
+        let outside = temp_dir.path().join("id_outside");
+        fs::write(&outside, "key").await.unwrap();
+
+        assert!(SshConnectionService::resolve_identity_override(
+            outside.to_str().unwrap(),
+            &ssh_dir
+        )
+        .is_err());
+        assert!(
+            SshConnectionService::resolve_identity_override("../id_outside", &ssh_dir).is_err()
+        );
+        assert!(SshConnectionService::resolve_identity_override("missing", &ssh_dir).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_load_private_key_encrypted_requires_passphrase() {
+        use rand::rngs::OsRng;
+        use ssh_key::{Algorithm, LineEnding, PrivateKey};
+
+        let temp_dir = TempDir::new().unwrap();
+        let key_path = temp_dir.path().join("id_encrypted");
+        let encrypted = PrivateKey::random(&mut OsRng, Algorithm::Ed25519)
+            .unwrap()
+            .encrypt(&mut OsRng, "secret")
+            .unwrap();
+        fs::write(
+            &key_path,
+            encrypted.to_openssh(LineEnding::LF).unwrap().as_bytes(),
+        )
+        .await
+        .unwrap();
+
+        assert!(matches!(
+            SshConnectionService::load_private_key(&key_path).await,
+            Err(SshBuddyError::PassphraseRequired { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_load_private_key_missing_is_not_passphrase_error() {
+        let result =
+            SshConnectionService::load_private_key(&PathBuf::from("/nonexistent/id_encrypted"))
+                .await;
+
+        assert!(matches!(result, Err(SshBuddyError::KeyNotFound { .. })));
+    }
+
+    #[test]
+    fn test_auth_attempt_outcomes() {
+        let public_key = KeyPair::generate_ed25519().clone_public_key().unwrap();
+
+        let accepted = AuthAttempt::new(&public_key, AuthKeySource::File, &Ok::<_, String>(true));
+        assert_eq!(accepted.outcome, AuthOutcome::Accepted);
+        assert_eq!(
+            accepted.fingerprint,
+            format!("SHA256:{}", public_key.fingerprint())
+        );
+
+        let rejected = AuthAttempt::new(&public_key, AuthKeySource::Agent, &Ok::<_, String>(false));
+        assert_eq!(rejected.outcome, AuthOutcome::Rejected);
+        assert_eq!(rejected.source, AuthKeySource::Agent);
+
+        let error = AuthAttempt::new(
+            &public_key,
+            AuthKeySource::File,
+            &Err::<bool, _>("connection reset"),
+        );
+        assert_eq!(error.outcome, AuthOutcome::Error);
+        assert_eq!(error.message.as_deref(), Some("connection reset"));
+    }
+
+    #[test]
+    fn test_resolve_algorithm_list_modifiers() {
+        let defaults = [key::ED25519, key::RSA_SHA2_256];
+
+        let (names, unsupported) = resolve_algorithm_list("rsa-sha2-512,ssh-dss", &defaults);
+        assert_eq!(names, vec![key::RSA_SHA2_512]);
+        assert_eq!(unsupported, vec!["ssh-dss".to_string()]);
+
+        let (names, _) = resolve_algorithm_list("+ssh-rsa", &defaults);
+        assert_eq!(names, vec![key::ED25519, key::RSA_SHA2_256, key::SSH_RSA]);
+
+        let (names, unsupported) = resolve_algorithm_list("-ssh-ed25519,ssh-dss", &defaults);
+        assert_eq!(names, vec![key::RSA_SHA2_256]);
+        assert!(unsupported.is_empty());
+
+        let (names, _) = resolve_algorithm_list("^rsa-sha2-256", &defaults);
+        assert_eq!(names, vec![key::RSA_SHA2_256, key::ED25519]);
+    }
+
+    #[test]
+    fn test_algorithm_preferences_from_host_config() {
+        let host_config = HostConfig {
+            host_key_algorithms: Some("ssh-ed25519".to_string()),
+            pubkey_accepted_algorithms: Some("rsa-sha2-512".to_string()),
+            ..Default::default()
+        };
+
+        let algorithms = AlgorithmPreferences::from_host_config(&host_config);
+        assert_eq!(algorithms.preferred.key.as_ref(), &[key::ED25519]);
+        assert!(!algorithms.accepts_key(&KeyPair::generate_ed25519()));
+        assert!(algorithms.notes.is_empty());
+    }
+
+    #[test]
+    fn test_algorithm_preferences_transport_directives() {
+        let hosts = SshConfigParser::parse(
+            "Host appliance\n    Ciphers aes128-cbc,rot13\n    MACs hmac-sha1\n    KexAlgorithms diffie-hellman-group14-sha1\n    Compression yes\n",
+        );
+        let host_config = SshConfigParser::merge_configs(&hosts, "appliance");
+
+        let algorithms = AlgorithmPreferences::from_host_config(&host_config);
+        let names = |list: &[&str]| list.join(",");
+        assert_eq!(
+            names(
+                &algorithms
+                    .preferred
+                    .cipher
+                    .iter()
+                    .map(|n| n.as_ref())
+                    .collect::<Vec<_>>()
+            ),
+            "aes128-cbc"
+        );
+        assert_eq!(
+            names(
+                &algorithms
+                    .preferred
+                    .mac
+                    .iter()
+                    .map(|n| n.as_ref())
+                    .collect::<Vec<_>>()
+            ),
+            "hmac-sha1"
+        );
+        assert_eq!(
+            algorithms.preferred.kex.first().map(|n| n.as_ref()),
+            Some("diffie-hellman-group14-sha1")
+        );
+        assert!(algorithms
+            .preferred
+            .kex
+            .contains(&kex::EXTENSION_SUPPORT_AS_CLIENT));
+        assert_eq!(
+            algorithms.preferred.compression.first(),
+            Some(&compression::ZLIB_LEGACY)
+        );
+        assert_eq!(
+            algorithms.notes,
+            vec!["Ciphers not supported, skipped: rot13".to_string()]
+        );
+
+        // Nothing usable is left, so russh's defaults stay
+        let host_config = HostConfig {
+            ciphers: Some("none".to_string()),
+            ..Default::default()
+        };
+        let algorithms = AlgorithmPreferences::from_host_config(&host_config);
+        assert_eq!(algorithms.preferred.cipher, Preferred::DEFAULT.cipher);
+        assert_eq!(algorithms.notes.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_target_host() {
+        let config = SshConnectionService::parse_target("example.com", &[]).unwrap();
+        assert_eq!(config.get_hostname(), "example.com");
+        assert_eq!(config.get_user(), None);
+        assert_eq!(config.get_port(), 22);
+    }
+
+    #[test]
+    fn test_parse_target_user_at_host() {
+        let config = SshConnectionService::parse_target("git@github.com", &[]).unwrap();
+        assert_eq!(config.get_hostname(), "github.com");
+        assert_eq!(config.get_user(), Some("git"));
+        assert_eq!(config.get_port(), 22);
+    }
+
+    #[test]
+    fn test_parse_target_user_at_host_port() {
+        let config = SshConnectionService::parse_target("git@github.com:2222", &[]).unwrap();
+        assert_eq!(config.get_hostname(), "github.com");
+        assert_eq!(config.get_user(), Some("git"));
+        assert_eq!(config.get_port(), 2222);
+
+        let config = SshConnectionService::parse_target("root@[2001:db8::1]:2200", &[]).unwrap();
+        assert_eq!(config.get_hostname(), "2001:db8::1");
+        assert_eq!(config.get_port(), 2200);
+
+        // Config for the host part still applies
+        let hosts = SshConfigParser::parse("Host github.com\n    IdentityFile /tmp/id_github\n");
+        let config = SshConnectionService::parse_target("git@github.com:2222", &hosts).unwrap();
+        assert_eq!(config.identity_file, Some(PathBuf::from("/tmp/id_github")));
+    }
+
+    #[test]
+    fn test_parse_target_invalid() {
+        for target in ["git@bad host", "@github.com", "git@github.com:ssh", "[::1"] {
+            assert!(
+                SshConnectionService::parse_target(target, &[]).is_err(),
+                "{} should be rejected",
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolve_identity_file_missing_config_key() {
+        let host_config = HostConfig {
+            identity_file: Some(PathBuf::from("/nonexistent/.ssh/id_missing")),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            SshConnectionService::resolve_identity_file(&host_config),
+            Err(SshBuddyError::KeyNotFound { path }) if path.ends_with("id_missing")
+        ));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_target_public_key_without_pub_file() {
+        use rand::rngs::OsRng;
+        use ssh_key::{Algorithm, LineEnding, PrivateKey};
+
+        let temp_dir = TempDir::new().unwrap();
+        let key_path = temp_dir.path().join("id_encrypted");
+        let private_key = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).unwrap();
+        let encrypted = private_key.encrypt(&mut OsRng, "secret").unwrap();
+        fs::write(
+            &key_path,
+            encrypted.to_openssh(LineEnding::LF).unwrap().as_bytes(),
+        )
+        .await
+        .unwrap();
+
+        let expected = private_key.public_key().to_openssh().unwrap();
+        let target = SshConnectionService::target_public_key_base64(&key_path)
+            .await
+            .unwrap();
+
+        assert_eq!(Some(target.as_str()), expected.split_whitespace().nth(1));
+    }
 }
@@ -0,0 +1,161 @@
+use crate::models::SshBuddyError;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+/// Probed once per process; installing tools requires an app restart to show up
+static CAPABILITIES: OnceLock<SshCapabilities> = OnceLock::new();
+
+/// One external tool SSH Buddy can fall back to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolInfo {
+    pub available: bool,
+    pub path: Option<String>,
+    /// First line of the version output, for tools that have a version flag
+    pub version: Option<String>,
+}
+
+/// External SSH tooling found on PATH
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SshCapabilities {
+    pub ssh: ToolInfo,
+    pub ssh_add: ToolInfo,
+    pub ssh_keygen: ToolInfo,
+    pub ssh_keyscan: ToolInfo,
+    pub puttygen: ToolInfo,
+}
+
+/// Detects which command line tools are installed
+pub struct SystemTools;
+
+impl SystemTools {
+    /// Capabilities of this machine, probed on first use
+    /// Runs the tools, so call it off the main thread
+    pub fn capabilities() -> SshCapabilities {
+        CAPABILITIES
+            .get_or_init(|| {
+                let capabilities = SshCapabilities {
+                    ssh: Self::probe("ssh", Some("-V")),
+                    // ssh-add, ssh-keygen and ssh-keyscan have no version flag
+                    ssh_add: Self::probe("ssh-add", None),
+                    ssh_keygen: Self::probe("ssh-keygen", None),
+                    ssh_keyscan: Self::probe("ssh-keyscan", None),
+                    puttygen: Self::probe("puttygen", Some("--version")),
+                };
+                log::info!("[system_tools] {:?}", capabilities);
+                capabilities
+            })
+            .clone()
+    }
+
+    /// Full path of an executable in one of the PATH directories
+    pub fn find_in_path(program: &str) -> Option<PathBuf> {
+        let file_name = if cfg!(windows) {
+            format!("{}.exe", program)
+        } else {
+            program.to_string()
+        };
+        let path = std::env::var_os("PATH")?;
+        std::env::split_paths(&path)
+            .map(|dir| dir.join(&file_name))
+            .find(|candidate| candidate.is_file())
+    }
+
+    /// Error for a failed spawn of `tool`; a missing executable gets its own type
+    /// so the UI can tell the user to install it
+    pub fn spawn_error(tool: &str, e: std::io::Error) -> SshBuddyError {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            log::warn!("[system_tools] {} not found on PATH", tool);
+            SshBuddyError::ExternalToolMissing {
+                tool: tool.to_string(),
+            }
+        } else {
+            SshBuddyError::IoError {
+                message: format!("Failed to run {}: {}", tool, e),
+            }
+        }
+    }
+
+    fn probe(program: &str, version_flag: Option<&str>) -> ToolInfo {
+        let path = match Self::find_in_path(program) {
+            Some(path) => path,
+            None => {
+                return ToolInfo {
+                    available: false,
+                    path: None,
+                    version: None,
+                }
+            }
+        };
+
+        let version = version_flag.and_then(|flag| {
+            let output = Command::new(&path)
+                .arg(flag)
+                .stdin(Stdio::null())
+                .output()
+                .ok()?;
+            // `ssh -V` prints to stderr
+            Self::first_line(&output.stdout).or_else(|| Self::first_line(&output.stderr))
+        });
+
+        ToolInfo {
+            available: true,
+            path: Some(path.to_string_lossy().to_string()),
+            version,
+        }
+    }
+
+    fn first_line(output: &[u8]) -> Option<String> {
+        String::from_utf8_lossy(output)
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .map(str::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_line_skips_blank_lines() {
+        assert_eq!(
+            SystemTools::first_line(b"\n  OpenSSH_9.6p1, OpenSSL 3.0.13\nmore\n"),
+            Some("OpenSSH_9.6p1, OpenSSL 3.0.13".to_string())
+        );
+        assert_eq!(SystemTools::first_line(b" \n"), None);
+    }
+
+    #[test]
+    fn test_spawn_error_for_missing_tool() {
+        // The child's PATH is searched, so an empty directory hides every tool
+        let empty = tempfile::TempDir::new().unwrap();
+        let e = Command::new("ssh-add")
+            .env("PATH", empty.path())
+            .stdin(Stdio::null())
+            .output()
+            .unwrap_err();
+        assert!(matches!(
+            SystemTools::spawn_error("ssh-add", e),
+            SshBuddyError::ExternalToolMissing { tool } if tool == "ssh-add"
+        ));
+
+        let other = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert!(matches!(
+            SystemTools::spawn_error("ssh-add", other),
+            SshBuddyError::IoError { .. }
+        ));
+    }
+
+    #[test]
+    fn test_probe_missing_tool() {
+        let info = SystemTools::probe("ssh-buddy-no-such-tool", Some("-V"));
+        assert!(!info.available);
+        assert_eq!(info.path, None);
+        assert_eq!(info.version, None);
+    }
+}
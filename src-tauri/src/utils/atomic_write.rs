@@ -0,0 +1,190 @@
+use crate::models::{SshBuddyError, SshResult};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Distinguishes temp files of concurrent writes within this process
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Replace a file so readers see either the old or the new content, never a partial write
+/// The content goes to a temp file in the same directory, which gets `mode` (Unix only),
+/// is synced to disk and then renamed over `path`
+/// A symlink at `path` is kept: the file it points to is the one replaced
+pub async fn atomic_write(path: &Path, contents: &[u8], mode: u32) -> SshResult<()> {
+    let path = path.to_path_buf();
+    let contents = contents.to_vec();
+    tokio::task::spawn_blocking(move || write_and_rename(&path, &contents, mode))
+        .await
+        .map_err(|e| SshBuddyError::IoError {
+            message: format!("Write task failed: {}", e),
+        })?
+}
+
+/// Permission bits of an existing file, or `default` if it doesn't exist
+/// Lets a rewrite keep whatever permissions the user gave the file
+pub fn existing_mode_or(path: &Path, default: u32) -> u32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Ok(metadata) = fs::metadata(path) {
+            return metadata.permissions().mode() & 0o777;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+
+    default
+}
+
+fn write_and_rename(path: &Path, contents: &[u8], mode: u32) -> SshResult<()> {
+    let target = resolve_symlink(path)?;
+    let path = target.as_path();
+    let temp_path = temp_path_for(path)?;
+
+    let result = write_synced(&temp_path, contents, mode).and_then(|()| {
+        fs::rename(&temp_path, path).map_err(|e| SshBuddyError::IoError {
+            message: format!("Failed to replace {}: {}", path.display(), e),
+        })
+    });
+
+    match result {
+        Ok(()) => {
+            sync_parent_dir(path);
+            Ok(())
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path);
+            Err(e)
+        }
+    }
+}
+
+/// The real file behind `path` if it is a symlink, so the rename doesn't replace the link
+fn resolve_symlink(path: &Path) -> SshResult<PathBuf> {
+    let is_symlink = fs::symlink_metadata(path)
+        .map(|metadata| metadata.file_type().is_symlink())
+        .unwrap_or(false);
+    if !is_symlink {
+        return Ok(path.to_path_buf());
+    }
+
+    fs::canonicalize(path).map_err(|e| SshBuddyError::IoError {
+        message: format!("Failed to resolve symlink {}: {}", path.display(), e),
+    })
+}
+
+/// Hidden temp file next to `path`, so the rename stays on one filesystem
+fn temp_path_for(path: &Path) -> SshResult<PathBuf> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| SshBuddyError::InvalidPath {
+            message: format!("Not a file path: {}", path.display()),
+        })?
+        .to_string_lossy();
+
+    Ok(path.with_file_name(format!(
+        ".{}.tmp-{}-{}",
+        file_name,
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    )))
+}
+
+fn write_synced(temp_path: &Path, contents: &[u8], mode: u32) -> SshResult<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+
+    // Create with the final mode so the content is never readable with looser permissions
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(mode);
+    }
+
+    let mut file = options.open(temp_path)?;
+
+    // The umask may have stripped bits from the requested mode
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(mode))?;
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+
+    file.write_all(contents)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Persist the rename itself; best effort, as not every platform can open directories
+fn sync_parent_dir(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = fs::File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_atomic_write_replaces_content() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("known_hosts");
+        fs::write(&path, "old content that is longer\n").unwrap();
+
+        atomic_write(&path, b"new\n", 0o644).await.unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+        // No temp files left behind
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_atomic_write_sets_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("id_test");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        atomic_write(&path, b"private", 0o600).await.unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600);
+        assert_eq!(existing_mode_or(&path, 0o644), 0o600);
+        assert_eq!(existing_mode_or(&temp.path().join("missing"), 0o644), 0o644);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_atomic_write_keeps_symlink() {
+        let temp = TempDir::new().unwrap();
+        let vault = temp.path().join("vault");
+        fs::create_dir_all(&vault).unwrap();
+        let target = vault.join("known_hosts");
+        fs::write(&target, "old\n").unwrap();
+        let link = temp.path().join("known_hosts");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        atomic_write(&link, b"new\n", 0o644).await.unwrap();
+
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new\n");
+        // The temp file was created beside the target and renamed away
+        assert_eq!(fs::read_dir(&vault).unwrap().count(), 1);
+    }
+}
@@ -1,5 +1,17 @@
+pub mod atomic_write;
+pub mod openssh_key;
+pub mod path_expand;
 pub mod path_validator;
+pub mod randomart;
 pub mod ssh_config;
+pub mod ssh_dir;
+pub mod sshfp;
 
+pub use atomic_write::*;
+pub use openssh_key::*;
+pub use path_expand::*;
 pub use path_validator::*;
+pub use randomart::*;
 pub use ssh_config::*;
+pub use ssh_dir::*;
+pub use sshfp::*;
@@ -0,0 +1,117 @@
+use crate::models::{SshBuddyError, SshResult};
+use std::path::{Component, Path, PathBuf};
+
+/// Expand a user-supplied path
+/// A leading `~` or `~/` resolves to the home directory; the result is then
+/// lexically normalized. `~user` is rejected, since another user's home can't be
+/// located reliably without the password database.
+/// IPC calls don't pass through a shell, so nothing else expands `~` for us.
+pub fn expand_path(input: &str) -> SshResult<PathBuf> {
+    let input = input.trim();
+
+    let expanded = match input.strip_prefix('~') {
+        Some(rest) => {
+            let (user, rest) = match rest.find(['/', '\\']) {
+                Some(idx) => (&rest[..idx], &rest[idx + 1..]),
+                None => (rest, ""),
+            };
+
+            if !user.is_empty() {
+                return Err(SshBuddyError::InvalidPath {
+                    message: format!("~{} paths are not supported: {}", user, input),
+                });
+            }
+
+            dirs::home_dir()
+                .ok_or(SshBuddyError::HomeDirNotFound)?
+                .join(rest)
+        }
+        None => PathBuf::from(input),
+    };
+
+    Ok(normalize_path(&expanded))
+}
+
+/// Remove `.` components and resolve `..` without touching the filesystem
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                let can_pop = matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                );
+                if can_pop {
+                    normalized.pop();
+                } else if !normalized.has_root() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_tilde() {
+        let home = dirs::home_dir().unwrap();
+
+        assert_eq!(expand_path("~").unwrap(), home);
+        assert_eq!(
+            expand_path("~/.ssh/id_rsa").unwrap(),
+            home.join(".ssh").join("id_rsa")
+        );
+    }
+
+    #[test]
+    fn test_expand_tilde_user_rejected() {
+        assert!(matches!(
+            expand_path("~alice/.ssh/id_rsa"),
+            Err(SshBuddyError::InvalidPath { .. })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_expand_absolute_path() {
+        assert_eq!(
+            expand_path("/home/user/.ssh/id_rsa").unwrap(),
+            PathBuf::from("/home/user/.ssh/id_rsa")
+        );
+        assert_eq!(
+            expand_path("/home/user/./.ssh/../.ssh/id_rsa").unwrap(),
+            PathBuf::from("/home/user/.ssh/id_rsa")
+        );
+        assert_eq!(expand_path("/../etc").unwrap(), PathBuf::from("/etc"));
+    }
+
+    #[test]
+    fn test_expand_already_expanded_is_stable() {
+        let once = expand_path("~/.ssh/id_ed25519").unwrap();
+        let twice = expand_path(once.to_str().unwrap()).unwrap();
+
+        assert_eq!(once, twice);
+    }
+
+    #[test]
+    fn test_expand_relative_path() {
+        assert_eq!(expand_path("id_rsa").unwrap(), PathBuf::from("id_rsa"));
+        assert_eq!(
+            expand_path("./keys/../id_rsa").unwrap(),
+            PathBuf::from("id_rsa")
+        );
+        assert_eq!(
+            expand_path("../id_rsa").unwrap(),
+            PathBuf::from("../id_rsa")
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use crate::models::{SshBuddyError, SshResult};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Validate SSH key name to prevent path traversal attacks
 pub fn validate_key_name(key_name: &str) -> SshResult<()> {
@@ -41,8 +41,45 @@ pub fn validate_key_name(key_name: &str) -> SshResult<()> {
     Ok(())
 }
 
+/// Validate and normalize a key comment before it is written to a key file
+/// Newlines, null bytes and other control characters would corrupt the
+/// single-line public key format, so they are rejected; surrounding whitespace is trimmed
+pub fn sanitize_key_comment(comment: &str) -> SshResult<String> {
+    if comment.contains('\n') || comment.contains('\r') {
+        return Err(SshBuddyError::InvalidKeyFormat {
+            message: "Key comment cannot contain line breaks".to_string(),
+        });
+    }
+
+    if comment.contains('\0') {
+        return Err(SshBuddyError::InvalidKeyFormat {
+            message: "Key comment contains null bytes".to_string(),
+        });
+    }
+
+    let comment = comment.trim();
+    if comment.chars().any(char::is_control) {
+        return Err(SshBuddyError::InvalidKeyFormat {
+            message: "Key comment contains control characters".to_string(),
+        });
+    }
+
+    Ok(comment.to_string())
+}
+
+/// The SSH directory with symlinks resolved, e.g. when ~/.ssh links to a synced folder
+/// Paths are compared against this, never against ~/.ssh as written
+pub fn canonical_ssh_dir(ssh_dir: &Path) -> SshResult<PathBuf> {
+    ssh_dir
+        .canonicalize()
+        .map_err(|_| SshBuddyError::InvalidPath {
+            message: format!("Cannot resolve SSH directory: {}", ssh_dir.display()),
+        })
+}
+
 /// Validate path is within SSH directory
-#[allow(dead_code)]
+/// Both sides are resolved, so a path written through a symlinked ~/.ssh and one
+/// written with the directory's real location are treated alike
 pub fn validate_path_in_ssh_dir(path: &Path, ssh_dir: &Path) -> SshResult<()> {
     // Canonicalize path
     let canonical_path = path
@@ -51,11 +88,7 @@ pub fn validate_path_in_ssh_dir(path: &Path, ssh_dir: &Path) -> SshResult<()> {
             message: format!("Cannot resolve path: {}", path.display()),
         })?;
 
-    let canonical_ssh_dir = ssh_dir
-        .canonicalize()
-        .map_err(|_| SshBuddyError::InvalidPath {
-            message: format!("Cannot resolve SSH directory: {}", ssh_dir.display()),
-        })?;
+    let canonical_ssh_dir = canonical_ssh_dir(ssh_dir)?;
 
     // Ensure path is within SSH directory
     if !canonical_path.starts_with(&canonical_ssh_dir) {
@@ -67,6 +100,45 @@ pub fn validate_path_in_ssh_dir(path: &Path, ssh_dir: &Path) -> SshResult<()> {
     Ok(())
 }
 
+/// Validate that a path *itself* lives in the SSH directory, without following it
+/// A symlinked key (e.g. into a password manager mount) passes as long as the link
+/// is in ~/.ssh; reading it then follows the link to its target
+pub fn validate_link_in_ssh_dir(path: &Path, ssh_dir: &Path) -> SshResult<()> {
+    std::fs::symlink_metadata(path).map_err(|_| SshBuddyError::InvalidPath {
+        message: format!("Cannot resolve path: {}", path.display()),
+    })?;
+
+    let file_name = match path.file_name() {
+        Some(name) => name,
+        None => {
+            return Err(SshBuddyError::PathTraversalDetected {
+                path: path.display().to_string(),
+            })
+        }
+    };
+
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    validate_path_in_ssh_dir(parent, ssh_dir).map_err(|e| match e {
+        SshBuddyError::PathTraversalDetected { .. } => SshBuddyError::PathTraversalDetected {
+            path: path.display().to_string(),
+        },
+        other => other,
+    })?;
+
+    // The link may not be the SSH directory itself
+    if file_name == ".." {
+        return Err(SshBuddyError::PathTraversalDetected {
+            path: path.display().to_string(),
+        });
+    }
+
+    Ok(())
+}
+
 /// Validate hostname to prevent command injection
 #[allow(dead_code)]
 pub fn validate_hostname(hostname: &str) -> SshResult<()> {
@@ -128,6 +200,23 @@ mod tests {
         assert!(validate_key_name("").is_err());
     }
 
+    #[test]
+    fn test_sanitize_key_comment() {
+        assert_eq!(
+            sanitize_key_comment("  user@example.com \t").unwrap(),
+            "user@example.com"
+        );
+        assert_eq!(sanitize_key_comment("").unwrap(), "");
+    }
+
+    #[test]
+    fn test_sanitize_key_comment_rejects_injection() {
+        assert!(sanitize_key_comment("user\nssh-ed25519 AAAA attacker").is_err());
+        assert!(sanitize_key_comment("user\r\n").is_err());
+        assert!(sanitize_key_comment("user\0").is_err());
+        assert!(sanitize_key_comment("user\x1b[31m").is_err());
+    }
+
     #[test]
     fn test_validate_hostname_valid() {
         assert!(validate_hostname("example.com").is_ok());
@@ -142,4 +231,57 @@ mod tests {
         assert!(validate_hostname("host; rm -rf /").is_err());
         assert!(validate_hostname("host`whoami`").is_err());
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_validate_link_in_ssh_dir() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let ssh_dir = temp.path().join(".ssh");
+        let vault = temp.path().join("vault");
+        std::fs::create_dir_all(&ssh_dir).unwrap();
+        std::fs::create_dir_all(&vault).unwrap();
+        std::fs::write(vault.join("id_rsa"), "key").unwrap();
+
+        let link = ssh_dir.join("id_rsa");
+        std::os::unix::fs::symlink(vault.join("id_rsa"), &link).unwrap();
+
+        // The link is in ~/.ssh even though its target is not
+        assert!(validate_link_in_ssh_dir(&link, &ssh_dir).is_ok());
+        assert!(validate_path_in_ssh_dir(&link, &ssh_dir).is_err());
+
+        // A link placed outside ~/.ssh is still rejected
+        let outside = vault.join("alias");
+        std::os::unix::fs::symlink(&link, &outside).unwrap();
+        assert!(matches!(
+            validate_link_in_ssh_dir(&outside, &ssh_dir),
+            Err(SshBuddyError::PathTraversalDetected { .. })
+        ));
+        assert!(validate_link_in_ssh_dir(&ssh_dir.join("missing"), &ssh_dir).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_validate_path_in_symlinked_ssh_dir() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let synced = temp.path().join("synced");
+        let ssh_dir = temp.path().join(".ssh");
+        std::fs::create_dir_all(&synced).unwrap();
+        std::os::unix::fs::symlink(&synced, &ssh_dir).unwrap();
+        std::fs::write(synced.join("id_ed25519"), "key").unwrap();
+
+        // Through the link and through the real location alike
+        assert!(validate_path_in_ssh_dir(&ssh_dir.join("id_ed25519"), &ssh_dir).is_ok());
+        assert!(validate_path_in_ssh_dir(&synced.join("id_ed25519"), &ssh_dir).is_ok());
+        assert!(validate_link_in_ssh_dir(&ssh_dir.join("id_ed25519"), &ssh_dir).is_ok());
+        assert_eq!(
+            canonical_ssh_dir(&ssh_dir).unwrap(),
+            synced.canonicalize().unwrap()
+        );
+
+        std::fs::write(temp.path().join("elsewhere"), "key").unwrap();
+        assert!(matches!(
+            validate_path_in_ssh_dir(&temp.path().join("elsewhere"), &ssh_dir),
+            Err(SshBuddyError::PathTraversalDetected { .. })
+        ));
+    }
 }
@@ -0,0 +1,87 @@
+/// Width and height of the randomart field (as in OpenSSH's sshkey.c)
+const FIELD_WIDTH: usize = 17;
+const FIELD_HEIGHT: usize = 9;
+
+/// Symbols by visit count; the last two mark the start and end positions
+const SYMBOLS: &[u8] = b" .o+=*BOX@%&#/^SE";
+
+/// Draw the "drunken bishop" randomart of a fingerprint digest, as `ssh-keygen -lv` does
+/// `title` goes in the top border (e.g. "ED25519 256"), `hash_name` in the bottom one
+pub fn randomart(digest: &[u8], title: &str, hash_name: &str) -> String {
+    let start_symbol = SYMBOLS.len() - 2;
+    let end_symbol = SYMBOLS.len() - 1;
+
+    let mut field = [[0usize; FIELD_HEIGHT]; FIELD_WIDTH];
+    let mut x = FIELD_WIDTH / 2;
+    let mut y = FIELD_HEIGHT / 2;
+
+    // Each byte is four moves of two bits, least significant first
+    for byte in digest {
+        let mut input = *byte;
+        for _ in 0..4 {
+            x = if input & 0x1 != 0 {
+                (x + 1).min(FIELD_WIDTH - 1)
+            } else {
+                x.saturating_sub(1)
+            };
+            y = if input & 0x2 != 0 {
+                (y + 1).min(FIELD_HEIGHT - 1)
+            } else {
+                y.saturating_sub(1)
+            };
+
+            if field[x][y] < start_symbol - 1 {
+                field[x][y] += 1;
+            }
+            input >>= 2;
+        }
+    }
+
+    field[FIELD_WIDTH / 2][FIELD_HEIGHT / 2] = start_symbol;
+    field[x][y] = end_symbol;
+
+    let mut lines = Vec::with_capacity(FIELD_HEIGHT + 2);
+    lines.push(border(title));
+    for row in 0..FIELD_HEIGHT {
+        let cells: String = (0..FIELD_WIDTH)
+            .map(|col| SYMBOLS[field[col][row]] as char)
+            .collect();
+        lines.push(format!("|{}|", cells));
+    }
+    lines.push(border(hash_name));
+
+    lines.join("\n")
+}
+
+/// Border line with a "[label]" centered in dashes
+fn border(label: &str) -> String {
+    let label = format!("[{}]", label);
+    if label.len() > FIELD_WIDTH {
+        return format!("+{}+", "-".repeat(FIELD_WIDTH));
+    }
+
+    let left = (FIELD_WIDTH - label.len()) / 2;
+    let right = FIELD_WIDTH - label.len() - left;
+    format!("+{}{}{}+", "-".repeat(left), label, "-".repeat(right))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_border_centering() {
+        assert_eq!(border("ED25519 256"), "+--[ED25519 256]--+");
+        assert_eq!(border("RSA 3072"), "+---[RSA 3072]----+");
+        assert_eq!(border("SHA256"), "+----[SHA256]-----+");
+    }
+
+    #[test]
+    fn test_empty_digest_marks_start_and_end() {
+        let art = randomart(&[], "X", "Y");
+        let middle_row = art.lines().nth(1 + FIELD_HEIGHT / 2).unwrap();
+
+        // Start and end coincide; the end symbol wins
+        assert_eq!(middle_row, "|        E        |");
+    }
+}
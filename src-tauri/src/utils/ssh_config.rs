@@ -1,5 +1,7 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
+use super::path_expand::expand_path;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 
 /// SSH Host configuration
 #[derive(Debug, Clone, Default)]
@@ -14,8 +16,52 @@ pub struct HostConfig {
     pub user: Option<String>,
     /// Identity file path
     pub identity_file: Option<PathBuf>,
+    /// Certificate presented with the identity (CertificateFile)
+    pub certificate_file: Option<PathBuf>,
+    /// HostKeyAlgorithms as written (may start with +, - or ^)
+    pub host_key_algorithms: Option<String>,
+    /// PubkeyAcceptedAlgorithms as written (may start with +, - or ^)
+    pub pubkey_accepted_algorithms: Option<String>,
+    /// Ciphers as written (may start with +, - or ^)
+    pub ciphers: Option<String>,
+    /// MACs as written (may start with +, - or ^)
+    pub macs: Option<String>,
+    /// KexAlgorithms as written (may start with +, - or ^)
+    pub kex_algorithms: Option<String>,
+    /// Compression: "yes" or "no"
+    pub compression: Option<String>,
+    /// IdentityAgent socket with `~` expanded; may also be "none", "SSH_AUTH_SOCK" or "$VAR"
+    pub identity_agent: Option<String>,
+    /// AddKeysToAgent as written (yes, no, ask, confirm or a lifetime)
+    pub add_keys_to_agent: Option<String>,
+    /// HostKeyAlias: name the host key is stored under in known_hosts instead of the hostname
+    pub host_key_alias: Option<String>,
     /// Other options
     pub options: HashMap<String, String>,
+    /// Config line each directive was set on, keyed by lowercase directive name
+    pub source_lines: HashMap<String, usize>,
+}
+
+/// A value of the effective configuration for a host
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveValue {
+    pub value: String,
+    /// Config line the value came from; None for built-in defaults
+    pub line: Option<usize>,
+}
+
+/// A setting of a specific Host block that a later, broader block overrides
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigConflict {
+    pub alias: String,
+    /// Lowercase directive name, as in the effective configuration
+    pub setting: String,
+    pub specific_value: String,
+    pub effective_value: String,
+    /// Host pattern of the block whose value wins
+    pub overriding_pattern: String,
 }
 
 impl HostConfig {
@@ -33,19 +79,258 @@ impl HostConfig {
     pub fn get_user(&self) -> Option<&str> {
         self.user.as_deref()
     }
+
+    /// Flat directive -> value view of this config, like `ssh -G`
+    /// Hostname and port are always present, falling back to their defaults
+    pub fn effective_options(&self) -> BTreeMap<String, EffectiveValue> {
+        let mut options = BTreeMap::new();
+        let mut set = |key: &str, value: String| {
+            let line = self.source_lines.get(key).copied();
+            options.insert(key.to_string(), EffectiveValue { value, line });
+        };
+
+        set("hostname", self.get_hostname().to_string());
+        set("port", self.get_port().to_string());
+        if let Some(ref user) = self.user {
+            set("user", user.clone());
+        }
+        if let Some(ref path) = self.identity_file {
+            set("identityfile", path.to_string_lossy().to_string());
+        }
+        if let Some(ref path) = self.certificate_file {
+            set("certificatefile", path.to_string_lossy().to_string());
+        }
+        if let Some(ref value) = self.host_key_algorithms {
+            set("hostkeyalgorithms", value.clone());
+        }
+        if let Some(ref value) = self.pubkey_accepted_algorithms {
+            set("pubkeyacceptedalgorithms", value.clone());
+        }
+        if let Some(ref value) = self.ciphers {
+            set("ciphers", value.clone());
+        }
+        if let Some(ref value) = self.macs {
+            set("macs", value.clone());
+        }
+        if let Some(ref value) = self.kex_algorithms {
+            set("kexalgorithms", value.clone());
+        }
+        if let Some(ref value) = self.compression {
+            set("compression", value.clone());
+        }
+        if let Some(ref value) = self.identity_agent {
+            set("identityagent", value.clone());
+        }
+        if let Some(ref value) = self.add_keys_to_agent {
+            set("addkeystoagent", value.clone());
+        }
+        if let Some(ref value) = self.host_key_alias {
+            set("hostkeyalias", value.clone());
+        }
+        for (key, value) in &self.options {
+            set(key, value.clone());
+        }
+
+        options
+    }
+
+    /// Agent socket to use: IdentityAgent if set, otherwise SSH_AUTH_SOCK
+    pub fn agent_socket(&self) -> Result<PathBuf, String> {
+        let variable = match self.identity_agent.as_deref() {
+            None => "SSH_AUTH_SOCK",
+            Some("none") => return Err("IdentityAgent is none: agent disabled".to_string()),
+            Some(value) => match value.strip_prefix('$') {
+                Some(variable) => variable,
+                None if value == "SSH_AUTH_SOCK" => value,
+                None => return Ok(PathBuf::from(value)),
+            },
+        };
+
+        std::env::var(variable)
+            .map(PathBuf::from)
+            .map_err(|_| format!("{} not set. SSH agent may not be running.", variable))
+    }
+
+    /// Whether keys loaded from files should be added to the agent, and for how long
+    /// None when AddKeysToAgent is unset, "no", or "ask" (which needs a prompt);
+    /// Some(None) adds the key without a lifetime
+    pub fn add_keys_to_agent_lifetime(&self) -> Option<Option<u32>> {
+        match self.add_keys_to_agent.as_deref()?.to_lowercase().as_str() {
+            "yes" | "confirm" => Some(None),
+            "no" | "ask" => None,
+            value => parse_time_interval(value).map(Some),
+        }
+    }
+}
+
+/// Parse an sshd_config(5) time format such as "3600", "10m" or "1h30m" into seconds
+fn parse_time_interval(value: &str) -> Option<u32> {
+    let mut total: u32 = 0;
+    let mut digits = String::new();
+
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let multiplier = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return None,
+        };
+        let amount: u32 = digits.parse().ok()?;
+        total = total.checked_add(amount.checked_mul(multiplier)?)?;
+        digits.clear();
+    }
+
+    if !digits.is_empty() {
+        total = total.checked_add(digits.parse().ok()?)?;
+    }
+
+    if total == 0 {
+        None
+    } else {
+        Some(total)
+    }
+}
+
+/// How much a config diagnostic matters
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigSeverity {
+    /// Valid for OpenSSH, but not interpreted by the parser (global defaults, Include)
+    Info,
+    /// Misread, or ignored in a way that changes what SSH Buddy shows
+    Warning,
+}
+
+/// What a config diagnostic is about
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConfigWarningKind {
+    /// The line could not be read as a directive and value
+    Syntax,
+    /// Valid OpenSSH syntax that SSH Buddy does not interpret
+    Unsupported,
+    /// A directive OpenSSH has deprecated or removed
+    Deprecated,
+    /// A Host alias already declared by an earlier block
+    DuplicateHost,
+    /// A path SSH Buddy could not expand, such as `~user/...`; OpenSSH still reads it
+    PathExpansion,
+}
+
+/// A config line that was ignored or misread while parsing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigWarning {
+    /// 1-based line number
+    pub line: usize,
+    /// The offending line, trimmed
+    pub text: String,
+    pub kind: ConfigWarningKind,
+    pub severity: ConfigSeverity,
+    pub message: String,
+}
+
+impl ConfigWarning {
+    /// A line that could not be read at all, so saving the config would lose or
+    /// change what it says
+    pub fn is_hard_error(&self) -> bool {
+        self.kind == ConfigWarningKind::Syntax
+    }
 }
 
+/// Directives OpenSSH no longer honours, with the reason shown to the user
+const DEPRECATED_DIRECTIVES: &[(&str, ConfigSeverity, &str)] = &[
+    (
+        "protocol",
+        ConfigSeverity::Warning,
+        "Protocol was removed in OpenSSH 7.6; only SSH protocol 2 is supported",
+    ),
+    (
+        "rhostsrsaauthentication",
+        ConfigSeverity::Warning,
+        "RhostsRSAAuthentication was removed with SSH protocol 1 in OpenSSH 7.4",
+    ),
+    (
+        "rsaauthentication",
+        ConfigSeverity::Warning,
+        "RSAAuthentication was removed with SSH protocol 1 in OpenSSH 7.4",
+    ),
+    (
+        "cipher",
+        ConfigSeverity::Warning,
+        "Cipher only applied to SSH protocol 1; use Ciphers instead",
+    ),
+    (
+        "compressionlevel",
+        ConfigSeverity::Warning,
+        "CompressionLevel was removed with SSH protocol 1 in OpenSSH 7.4",
+    ),
+    (
+        "useroaming",
+        ConfigSeverity::Warning,
+        "UseRoaming was removed in OpenSSH 7.2 after CVE-2016-0777",
+    ),
+    (
+        "useprivilegedport",
+        ConfigSeverity::Warning,
+        "UsePrivilegedPort was removed in OpenSSH 7.5",
+    ),
+    (
+        "fallbacktorsh",
+        ConfigSeverity::Warning,
+        "FallBackToRsh was removed in OpenSSH 3.7",
+    ),
+    (
+        "usersh",
+        ConfigSeverity::Warning,
+        "UseRsh was removed in OpenSSH 3.7",
+    ),
+    (
+        "challengeresponseauthentication",
+        ConfigSeverity::Info,
+        "ChallengeResponseAuthentication is a deprecated alias of KbdInteractiveAuthentication",
+    ),
+];
+
 /// SSH Config parser
 pub struct SshConfigParser;
 
 impl SshConfigParser {
     /// Parse SSH config file content
+    /// Lines that cannot be used are skipped; see `parse_with_diagnostics`
     pub fn parse(content: &str) -> Vec<HostConfig> {
+        Self::parse_with_diagnostics(content).0
+    }
+
+    /// Parse SSH config file content, reporting lines that were skipped
+    pub fn parse_with_diagnostics(content: &str) -> (Vec<HostConfig>, Vec<ConfigWarning>) {
         let mut hosts = Vec::new();
+        let mut warnings = Vec::new();
         let mut current_host: Option<HostConfig> = None;
+        // Inside a Match block, whose lines must not be credited to the previous Host
+        let mut in_match = false;
+        // First line declaring each Host alias
+        let mut alias_lines: HashMap<String, usize> = HashMap::new();
 
-        for line in content.lines() {
+        for (idx, line) in content.lines().enumerate() {
             let line = line.trim();
+            let diagnostic =
+                |kind: ConfigWarningKind, severity: ConfigSeverity, message: &str| ConfigWarning {
+                    line: idx + 1,
+                    text: line.to_string(),
+                    kind,
+                    severity,
+                    message: message.to_string(),
+                };
+            let warn = |message: &str| {
+                diagnostic(ConfigWarningKind::Syntax, ConfigSeverity::Warning, message)
+            };
 
             // Skip empty lines and comments
             if line.is_empty() || line.starts_with('#') {
@@ -58,11 +343,92 @@ impl SshConfigParser {
             } else if let Some((k, v)) = line.split_once(char::is_whitespace) {
                 (k.trim().to_lowercase(), v.trim().to_string())
             } else {
+                warnings.push(warn("Unparseable line: expected a directive and a value"));
                 continue;
             };
 
+            if key.is_empty() || value.is_empty() {
+                warnings.push(warn("Unparseable line: expected a directive and a value"));
+                continue;
+            }
+
+            if let Some((_, severity, message)) = DEPRECATED_DIRECTIVES
+                .iter()
+                .find(|(name, _, _)| *name == key)
+            {
+                warnings.push(diagnostic(
+                    ConfigWarningKind::Deprecated,
+                    *severity,
+                    message,
+                ));
+            }
+
+            if key == "host" {
+                for alias in value.split_whitespace() {
+                    if let Some(first) = alias_lines.get(alias) {
+                        warnings.push(diagnostic(
+                            ConfigWarningKind::DuplicateHost,
+                            ConfigSeverity::Warning,
+                            &format!(
+                                "Host {} is already declared on line {}; OpenSSH uses the first value set for each option",
+                                alias, first
+                            ),
+                        ));
+                    } else {
+                        alias_lines.insert(alias.to_string(), idx + 1);
+                    }
+                }
+            }
+
+            if key == "include" {
+                warnings.push(diagnostic(
+                    ConfigWarningKind::Unsupported,
+                    ConfigSeverity::Info,
+                    "Included files are not read by SSH Buddy",
+                ));
+                continue;
+            }
+
+            if key == "match" {
+                if let Some(host) = current_host.take() {
+                    hosts.push(host);
+                }
+                in_match = true;
+                warnings.push(diagnostic(
+                    ConfigWarningKind::Unsupported,
+                    ConfigSeverity::Warning,
+                    "Match blocks are not supported; lines up to the next Host are ignored",
+                ));
+                continue;
+            }
+
+            if key != "host" && in_match {
+                continue;
+            }
+
+            if key != "host" && current_host.is_none() {
+                warnings.push(diagnostic(
+                    ConfigWarningKind::Unsupported,
+                    ConfigSeverity::Info,
+                    "Global default before the first Host block; applies to all hosts in OpenSSH but is not shown per host",
+                ));
+                continue;
+            }
+
+            if key != "host" {
+                if let Some(ref mut host) = current_host {
+                    // Recorded under the current name so lookups don't need to know aliases
+                    let line_key = match key.as_str() {
+                        "pubkeyacceptedkeytypes" => "pubkeyacceptedalgorithms",
+                        other => other,
+                    };
+                    host.source_lines.insert(line_key.to_string(), idx + 1);
+                }
+            }
+
             match key.as_str() {
                 "host" => {
+                    in_match = false;
                     // Save previous host
                     if let Some(host) = current_host.take() {
                         hosts.push(host);
@@ -81,6 +447,10 @@ impl SshConfigParser {
                 "port" => {
                     if let Some(ref mut host) = current_host {
                         host.port = value.parse().ok();
+                        if host.port.is_none() {
+                            host.source_lines.remove("port");
+                            warnings.push(warn("Invalid port number"));
+                        }
                     }
                 }
                 "user" => {
@@ -103,6 +473,82 @@ impl SshConfigParser {
                         host.identity_file = Some(path);
                     }
                 }
+                "certificatefile" => {
+                    if let Some(ref mut host) = current_host {
+                        host.certificate_file = Some(match expand_path(&value) {
+                            Ok(path) => path,
+                            Err(e) => {
+                                warnings.push(diagnostic(
+                                    ConfigWarningKind::PathExpansion,
+                                    ConfigSeverity::Warning,
+                                    &e.to_string(),
+                                ));
+                                PathBuf::from(&value)
+                            }
+                        });
+                    }
+                }
+                "hostkeyalgorithms" => {
+                    if let Some(ref mut host) = current_host {
+                        host.host_key_algorithms = Some(value);
+                    }
+                }
+                "ciphers" => {
+                    if let Some(ref mut host) = current_host {
+                        host.ciphers = Some(value);
+                    }
+                }
+                "macs" => {
+                    if let Some(ref mut host) = current_host {
+                        host.macs = Some(value);
+                    }
+                }
+                "kexalgorithms" => {
+                    if let Some(ref mut host) = current_host {
+                        host.kex_algorithms = Some(value);
+                    }
+                }
+                "compression" => {
+                    if let Some(ref mut host) = current_host {
+                        host.compression = Some(value);
+                    }
+                }
+                "identityagent" => {
+                    if let Some(ref mut host) = current_host {
+                        let socket = if value.starts_with('~') {
+                            match expand_path(&value) {
+                                Ok(path) => path.to_string_lossy().to_string(),
+                                Err(e) => {
+                                    warnings.push(diagnostic(
+                                        ConfigWarningKind::PathExpansion,
+                                        ConfigSeverity::Warning,
+                                        &e.to_string(),
+                                    ));
+                                    value
+                                }
+                            }
+                        } else {
+                            value
+                        };
+                        host.identity_agent = Some(socket);
+                    }
+                }
+                "addkeystoagent" => {
+                    if let Some(ref mut host) = current_host {
+                        host.add_keys_to_agent = Some(value);
+                    }
+                }
+                "hostkeyalias" => {
+                    if let Some(ref mut host) = current_host {
+                        host.host_key_alias = Some(value);
+                    }
+                }
+                // PubkeyAcceptedKeyTypes is the pre-8.5 name
+                "pubkeyacceptedalgorithms" | "pubkeyacceptedkeytypes" => {
+                    if let Some(ref mut host) = current_host {
+                        host.pubkey_accepted_algorithms = Some(value);
+                    }
+                }
                 _ => {
                     // Store other options
                     if let Some(ref mut host) = current_host {
@@ -117,7 +563,7 @@ impl SshConfigParser {
             hosts.push(host);
         }
 
-        hosts
+        (hosts, warnings)
     }
 
     /// Find host configuration by alias
@@ -128,6 +574,83 @@ impl SshConfigParser {
             .find(|h| Self::match_pattern(&h.host_pattern, alias))
     }
 
+    /// Whether a Host block other than the catch-all `Host *` matches the alias
+    pub fn has_host(hosts: &[HostConfig], alias: &str) -> bool {
+        hosts
+            .iter()
+            .any(|h| h.host_pattern != "*" && Self::match_pattern(&h.host_pattern, alias))
+    }
+
+    /// Aliases of Host blocks whose IdentityFile, after merging matching blocks, is `key_path`
+    /// Wildcard and negated patterns are not aliases and are left out
+    pub fn hosts_using_key(hosts: &[HostConfig], key_path: &Path) -> Vec<String> {
+        Self::alias_identity_files(hosts)
+            .into_iter()
+            .filter(|(_, identity_file)| identity_file == key_path)
+            .map(|(alias, _)| alias)
+            .collect()
+    }
+
+    /// Each alias with the IdentityFile it resolves to after merging, in file order
+    /// Aliases without an IdentityFile and wildcard or negated patterns are left out
+    pub fn alias_identity_files(hosts: &[HostConfig]) -> Vec<(String, PathBuf)> {
+        let mut resolved: Vec<(String, PathBuf)> = Vec::new();
+        let mut seen: Vec<&str> = Vec::new();
+        for host in hosts {
+            let alias = host.host_pattern.as_str();
+            if alias.contains(['*', '?', '!']) || seen.contains(&alias) {
+                continue;
+            }
+            seen.push(alias);
+            if let Some(identity_file) = Self::merge_configs(hosts, alias).identity_file {
+                resolved.push((alias.to_string(), identity_file));
+            }
+        }
+        resolved
+    }
+
+    /// Settings of concrete Host blocks that a later matching block with another pattern
+    /// overrides, since later blocks win in `merge_configs`
+    pub fn conflicts(hosts: &[HostConfig]) -> Vec<ConfigConflict> {
+        let mut conflicts = Vec::new();
+        for (idx, host) in hosts.iter().enumerate() {
+            let alias = host.host_pattern.as_str();
+            if alias.contains(['*', '?', '!']) {
+                continue;
+            }
+
+            let specific = host.effective_options();
+            let effective = Self::merge_configs(hosts, alias).effective_options();
+            let mut settings: Vec<&String> = host.source_lines.keys().collect();
+            settings.sort();
+            for setting in settings {
+                let (Some(specific_value), Some(effective_value)) =
+                    (specific.get(setting), effective.get(setting))
+                else {
+                    continue;
+                };
+                if specific_value.value == effective_value.value {
+                    continue;
+                }
+                let overriding = hosts[idx + 1..].iter().rev().find(|later| {
+                    later.host_pattern != alias
+                        && later.source_lines.contains_key(setting)
+                        && Self::match_pattern(&later.host_pattern, alias)
+                });
+                if let Some(overriding) = overriding {
+                    conflicts.push(ConfigConflict {
+                        alias: alias.to_string(),
+                        setting: setting.clone(),
+                        specific_value: specific_value.value.clone(),
+                        effective_value: effective_value.value.clone(),
+                        overriding_pattern: overriding.host_pattern.clone(),
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+
     /// Match host pattern (supports * wildcard)
     fn match_pattern(pattern: &str, alias: &str) -> bool {
         if pattern == "*" {
@@ -169,14 +692,213 @@ impl SshConfigParser {
                 if host.identity_file.is_some() {
                     merged.identity_file = host.identity_file.clone();
                 }
+                if host.certificate_file.is_some() {
+                    merged.certificate_file = host.certificate_file.clone();
+                }
+                if host.host_key_algorithms.is_some() {
+                    merged.host_key_algorithms = host.host_key_algorithms.clone();
+                }
+                if host.pubkey_accepted_algorithms.is_some() {
+                    merged.pubkey_accepted_algorithms = host.pubkey_accepted_algorithms.clone();
+                }
+                if host.ciphers.is_some() {
+                    merged.ciphers = host.ciphers.clone();
+                }
+                if host.macs.is_some() {
+                    merged.macs = host.macs.clone();
+                }
+                if host.kex_algorithms.is_some() {
+                    merged.kex_algorithms = host.kex_algorithms.clone();
+                }
+                if host.compression.is_some() {
+                    merged.compression = host.compression.clone();
+                }
+                if host.identity_agent.is_some() {
+                    merged.identity_agent = host.identity_agent.clone();
+                }
+                if host.add_keys_to_agent.is_some() {
+                    merged.add_keys_to_agent = host.add_keys_to_agent.clone();
+                }
+                if host.host_key_alias.is_some() {
+                    merged.host_key_alias = host.host_key_alias.clone();
+                }
                 for (k, v) in &host.options {
                     merged.options.insert(k.clone(), v.clone());
                 }
+                for (k, line) in &host.source_lines {
+                    merged.source_lines.insert(k.clone(), *line);
+                }
             }
         }
 
         merged
     }
+
+    /// Options of the `Host *` block, keyed by directive name as written
+    /// The first value wins when a directive repeats, as in OpenSSH
+    pub fn global_options(content: &str) -> BTreeMap<String, String> {
+        let mut options = BTreeMap::new();
+        let mut in_global = false;
+
+        for line in content.lines() {
+            let Some((key, value)) = split_directive(line) else {
+                continue;
+            };
+            if key.eq_ignore_ascii_case("host") || key.eq_ignore_ascii_case("match") {
+                in_global = is_global_host(key, value);
+                continue;
+            }
+            if in_global && !options.keys().any(|k: &String| k.eq_ignore_ascii_case(key)) {
+                options.insert(key.to_string(), value.to_string());
+            }
+        }
+
+        options
+    }
+
+    /// Set `key` to `value` in the `Host *` block, leaving every other line untouched
+    /// Replaces the directive's first line in the block, adds it at the end of the block,
+    /// or adds a new `Host *` block when the config has none. A new block goes before the
+    /// first Host or Match block: `merge_configs` lets later blocks win, so there it is a
+    /// default rather than an override of every host's own settings
+    pub fn set_global_option(content: &str, key: &str, value: &str) -> String {
+        let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+        let directive = format!("{} {}", key, value);
+
+        let mut in_global = false;
+        // Index of the first Host or Match line
+        let mut first_block = None;
+        // Index of the last directive line of the first `Host *` block
+        let mut block_end = None;
+        for (idx, line) in lines.iter().enumerate() {
+            let Some((name, rest)) = split_directive(line) else {
+                continue;
+            };
+            if name.eq_ignore_ascii_case("host") || name.eq_ignore_ascii_case("match") {
+                if block_end.is_some() {
+                    break;
+                }
+                first_block.get_or_insert(idx);
+                in_global = is_global_host(name, rest);
+                if in_global {
+                    block_end = Some(idx);
+                }
+                continue;
+            }
+            if !in_global {
+                continue;
+            }
+            if name.eq_ignore_ascii_case(key) {
+                let indent_len = line.len() - line.trim_start().len();
+                lines[idx] = format!("{}{}", &line[..indent_len], directive);
+                return join_lines(&lines);
+            }
+            block_end = Some(idx);
+        }
+
+        match (block_end, first_block) {
+            (Some(idx), _) => lines.insert(idx + 1, format!("    {}", directive)),
+            (None, Some(mut idx)) => {
+                // Comments right above the first block stay with it
+                while idx > 0 && lines[idx - 1].trim_start().starts_with('#') {
+                    idx -= 1;
+                }
+                let block = [
+                    "Host *".to_string(),
+                    format!("    {}", directive),
+                    String::new(),
+                ];
+                lines.splice(idx..idx, block);
+            }
+            (None, None) => {
+                if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                    lines.push(String::new());
+                }
+                lines.push("Host *".to_string());
+                lines.push(format!("    {}", directive));
+            }
+        }
+        join_lines(&lines)
+    }
+
+    /// Check a global option before it is written
+    /// Values of well-known directives are checked against their format
+    pub fn validate_global_option(key: &str, value: &str) -> Result<(), String> {
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(format!("Invalid option name: {:?}", key));
+        }
+        let lower = key.to_lowercase();
+        if matches!(lower.as_str(), "host" | "match" | "include") {
+            return Err(format!("{} cannot be set as a global option", key));
+        }
+        if value.trim().is_empty() || value.contains(['\n', '\r']) {
+            return Err(format!("Invalid value for {}", key));
+        }
+
+        let normalized = value.trim().to_lowercase();
+        let value_ok = match lower.as_str() {
+            "identitiesonly"
+            | "forwardagent"
+            | "forwardx11"
+            | "compression"
+            | "hashknownhosts"
+            | "visualhostkey"
+            | "batchmode"
+            | "tcpkeepalive"
+            | "passwordauthentication"
+            | "pubkeyauthentication"
+            | "updatehostkeys" => {
+                matches!(normalized.as_str(), "yes" | "no")
+            }
+            "addkeystoagent" => {
+                matches!(normalized.as_str(), "yes" | "no" | "ask" | "confirm")
+                    || parse_time_interval(&normalized).is_some()
+            }
+            "stricthostkeychecking" => {
+                matches!(
+                    normalized.as_str(),
+                    "yes" | "no" | "ask" | "accept-new" | "off"
+                )
+            }
+            "serveraliveinterval" | "connecttimeout" => normalized.parse::<u32>().is_ok(),
+            "serveralivecountmax" | "connectionattempts" => {
+                normalized.parse::<u32>().is_ok_and(|n| n > 0)
+            }
+            "port" => normalized.parse::<u16>().is_ok_and(|n| n > 0),
+            _ => true,
+        };
+
+        if value_ok {
+            Ok(())
+        } else {
+            Err(format!("Invalid value for {}: {}", key, value))
+        }
+    }
+}
+
+/// Directive name and value of a config line, None for blank lines and comments
+fn split_directive(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    // The name ends at the first space or `=`, so values may contain `=`
+    let end = line.find(|c: char| c == '=' || c.is_whitespace())?;
+    let value = line[end..].trim_start();
+    let value = value.strip_prefix('=').unwrap_or(value).trim();
+    Some((&line[..end], value))
+}
+
+/// Whether a Host/Match line opens the block that applies to every host
+fn is_global_host(key: &str, value: &str) -> bool {
+    key.eq_ignore_ascii_case("host") && value.split_whitespace().eq(["*"])
+}
+
+/// Lines back into file content with a trailing newline
+fn join_lines(lines: &[String]) -> String {
+    let mut content = lines.join("\n");
+    content.push('\n');
+    content
 }
 
 #[cfg(test)]
@@ -206,6 +928,295 @@ Host bitbucket
         assert_eq!(github.user.as_deref(), Some("git"));
     }
 
+    #[test]
+    fn test_parse_agent_directives() {
+        let config = r#"
+Host gpg
+    IdentityAgent ~/.gnupg/S.gpg-agent.ssh
+    AddKeysToAgent 1h30m
+
+Host plain
+    IdentityAgent $GPG_AGENT_SOCK
+    AddKeysToAgent ask
+"#;
+
+        let hosts = SshConfigParser::parse(config);
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(
+            hosts[0].identity_agent.as_deref(),
+            Some(
+                home.join(".gnupg/S.gpg-agent.ssh")
+                    .to_string_lossy()
+                    .as_ref()
+            )
+        );
+        assert_eq!(
+            hosts[0].agent_socket(),
+            Ok(home.join(".gnupg/S.gpg-agent.ssh"))
+        );
+        assert_eq!(hosts[0].add_keys_to_agent_lifetime(), Some(Some(5400)));
+
+        assert_eq!(hosts[1].identity_agent.as_deref(), Some("$GPG_AGENT_SOCK"));
+        assert_eq!(hosts[1].add_keys_to_agent_lifetime(), None);
+
+        let merged = SshConfigParser::merge_configs(&hosts, "gpg");
+        assert!(merged.identity_agent.is_some());
+
+        // An unexpandable ~user socket is kept as written and doesn't block saving
+        let (hosts, warnings) =
+            SshConfigParser::parse_with_diagnostics("Host other\n    IdentityAgent ~bob/agent\n");
+        assert_eq!(hosts[0].identity_agent.as_deref(), Some("~bob/agent"));
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, ConfigWarningKind::PathExpansion);
+        assert!(!warnings[0].is_hard_error());
+    }
+
+    #[test]
+    fn test_parse_certificate_file() {
+        let config = r#"
+Host corp
+    IdentityFile ~/.ssh/id_corp
+    CertificateFile ~/.ssh/id_corp-cert.pub
+
+Host *
+    CertificateFile /etc/ssh/default-cert.pub
+"#;
+
+        let hosts = SshConfigParser::parse(config);
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(
+            hosts[0].certificate_file,
+            Some(home.join(".ssh/id_corp-cert.pub"))
+        );
+
+        // Later matches override earlier ones, as for IdentityFile
+        let merged = SshConfigParser::merge_configs(&hosts, "corp");
+        assert_eq!(
+            merged.certificate_file,
+            Some(PathBuf::from("/etc/ssh/default-cert.pub"))
+        );
+    }
+
+    #[test]
+    fn test_effective_options_source_lines() {
+        let config = r#"Host github
+    HostName github.com
+    User git
+
+Host *
+    User deploy
+    PubkeyAcceptedKeyTypes ssh-ed25519
+    ServerAliveInterval 30
+"#;
+
+        let hosts = SshConfigParser::parse(config);
+        let options = SshConfigParser::merge_configs(&hosts, "github").effective_options();
+
+        let value = |key: &str| options.get(key).cloned().unwrap();
+        assert_eq!(
+            value("hostname"),
+            EffectiveValue {
+                value: "github.com".to_string(),
+                line: Some(2)
+            }
+        );
+        // Host * comes later, so its User wins
+        assert_eq!(value("user").value, "deploy");
+        assert_eq!(value("user").line, Some(6));
+        assert_eq!(value("pubkeyacceptedalgorithms").line, Some(7));
+        assert_eq!(value("serveraliveinterval").value, "30");
+        // Defaults have no source line
+        assert_eq!(
+            value("port"),
+            EffectiveValue {
+                value: "22".to_string(),
+                line: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_time_interval() {
+        assert_eq!(parse_time_interval("3600"), Some(3600));
+        assert_eq!(parse_time_interval("10m"), Some(600));
+        assert_eq!(parse_time_interval("1h30m"), Some(5400));
+        assert_eq!(parse_time_interval("1x"), None);
+        assert_eq!(parse_time_interval("0"), None);
+    }
+
+    #[test]
+    fn test_parse_algorithm_directives() {
+        let config = r#"
+Host strict
+    HostName strict.example.com
+    HostKeyAlgorithms ssh-ed25519,rsa-sha2-512
+    PubkeyAcceptedAlgorithms +ssh-rsa
+
+Host legacy
+    PubkeyAcceptedKeyTypes ssh-ed25519
+"#;
+
+        let hosts = SshConfigParser::parse(config);
+        assert_eq!(
+            hosts[0].host_key_algorithms.as_deref(),
+            Some("ssh-ed25519,rsa-sha2-512")
+        );
+        assert_eq!(
+            hosts[0].pubkey_accepted_algorithms.as_deref(),
+            Some("+ssh-rsa")
+        );
+        assert!(!hosts[0].options.contains_key("hostkeyalgorithms"));
+        assert_eq!(
+            hosts[1].pubkey_accepted_algorithms.as_deref(),
+            Some("ssh-ed25519")
+        );
+    }
+
+    #[test]
+    fn test_parse_transport_directives() {
+        let config = r#"
+Host appliance
+    Ciphers aes128-cbc,aes256-ctr
+    MACs hmac-sha1
+    KexAlgorithms +diffie-hellman-group14-sha1
+    Compression yes
+
+Host appliance
+    Ciphers chacha20-poly1305@openssh.com
+"#;
+
+        let hosts = SshConfigParser::parse(config);
+        assert_eq!(hosts[0].ciphers.as_deref(), Some("aes128-cbc,aes256-ctr"));
+        assert_eq!(hosts[0].macs.as_deref(), Some("hmac-sha1"));
+        assert_eq!(
+            hosts[0].kex_algorithms.as_deref(),
+            Some("+diffie-hellman-group14-sha1")
+        );
+        assert_eq!(hosts[0].compression.as_deref(), Some("yes"));
+        assert!(!hosts[0].options.contains_key("ciphers"));
+
+        let merged = SshConfigParser::merge_configs(&hosts, "appliance");
+        assert_eq!(
+            merged.ciphers.as_deref(),
+            Some("chacha20-poly1305@openssh.com")
+        );
+        assert_eq!(merged.compression.as_deref(), Some("yes"));
+        assert_eq!(merged.effective_options()["macs"].value, "hmac-sha1");
+    }
+
+    #[test]
+    fn test_parse_with_diagnostics() {
+        let config = r#"User root
+Host github
+    HostName github.com
+    Port abc
+    IdentitiesOnly
+    User=
+"#;
+
+        let (hosts, warnings) = SshConfigParser::parse_with_diagnostics(config);
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(hosts[0].hostname.as_deref(), Some("github.com"));
+        assert_eq!(hosts[0].port, None);
+
+        let lines: Vec<usize> = warnings.iter().map(|w| w.line).collect();
+        assert_eq!(lines, vec![1, 4, 5, 6]);
+        assert_eq!(warnings[0].text, "User root");
+        assert_eq!(warnings[0].severity, ConfigSeverity::Info);
+        assert!(warnings[0].message.contains("Global default"));
+        assert_eq!(warnings[1].severity, ConfigSeverity::Warning);
+        assert!(warnings[1].message.contains("port"));
+        assert!(warnings[2].message.contains("Unparseable"));
+    }
+
+    #[test]
+    fn test_parse_with_diagnostics_include_is_info() {
+        let config = r#"Include ~/.ssh/config.d/*
+Host github
+    HostName github.com
+"#;
+        let (hosts, warnings) = SshConfigParser::parse_with_diagnostics(config);
+
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].severity, ConfigSeverity::Info);
+        assert!(!hosts[0].options.contains_key("include"));
+    }
+
+    #[test]
+    fn test_parse_with_diagnostics_match_block() {
+        let config = r#"Host github
+    HostName github.com
+Match host *.corp exec "true"
+    User corp
+    Port 2200
+Host gitlab
+    HostName gitlab.com
+"#;
+        let (hosts, warnings) = SshConfigParser::parse_with_diagnostics(config);
+
+        assert_eq!(hosts.len(), 2);
+        // Match block lines are not credited to the previous host
+        assert_eq!(hosts[0].user, None);
+        assert_eq!(hosts[0].port, None);
+        assert!(!hosts[0].options.contains_key("match"));
+        assert_eq!(hosts[1].hostname.as_deref(), Some("gitlab.com"));
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].line, 3);
+        assert_eq!(warnings[0].severity, ConfigSeverity::Warning);
+        assert!(warnings[0].message.contains("Match"));
+    }
+
+    #[test]
+    fn test_parse_with_diagnostics_deprecated_directive() {
+        let config = r#"Host legacy
+    HostName legacy.example.com
+    Protocol 2
+    UseRoaming no
+"#;
+        let (hosts, warnings) = SshConfigParser::parse_with_diagnostics(config);
+
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].line, 3);
+        assert_eq!(warnings[0].kind, ConfigWarningKind::Deprecated);
+        assert_eq!(warnings[0].severity, ConfigSeverity::Warning);
+        assert!(warnings[0].message.contains("Protocol"));
+        assert_eq!(warnings[1].line, 4);
+        assert!(warnings[1].message.contains("UseRoaming"));
+    }
+
+    #[test]
+    fn test_parse_with_diagnostics_duplicate_host_alias() {
+        let config = r#"Host github work
+    HostName github.com
+Host gitlab github
+    HostName gitlab.com
+"#;
+        let (hosts, warnings) = SshConfigParser::parse_with_diagnostics(config);
+
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].line, 3);
+        assert_eq!(warnings[0].kind, ConfigWarningKind::DuplicateHost);
+        assert_eq!(warnings[0].severity, ConfigSeverity::Warning);
+        assert!(warnings[0].message.contains("github"));
+        assert!(warnings[0].message.contains("line 1"));
+    }
+
+    #[test]
+    fn test_parse_with_diagnostics_clean_config() {
+        let config = r#"
+# Personal
+Host github
+    HostName github.com
+    User git
+"#;
+        let (_, warnings) = SshConfigParser::parse_with_diagnostics(config);
+        assert!(warnings.is_empty());
+    }
+
     #[test]
     fn test_find_host() {
         let config = r#"
@@ -218,6 +1229,89 @@ Host github
         assert_eq!(found.unwrap().get_hostname(), "github.com");
     }
 
+    #[test]
+    fn test_conflicts_with_trailing_wildcard() {
+        let config = r#"
+Host work
+    HostName work.example.com
+    User alice
+    IdentityFile /home/user/.ssh/id_work
+
+Host personal
+    User bob
+
+Host *
+    User git
+    IdentityFile /home/user/.ssh/id_ed25519
+"#;
+        let hosts = SshConfigParser::parse(config);
+        let conflicts = SshConfigParser::conflicts(&hosts);
+
+        assert_eq!(conflicts.len(), 3);
+        assert_eq!(
+            conflicts[0],
+            ConfigConflict {
+                alias: "work".to_string(),
+                setting: "identityfile".to_string(),
+                specific_value: "/home/user/.ssh/id_work".to_string(),
+                effective_value: "/home/user/.ssh/id_ed25519".to_string(),
+                overriding_pattern: "*".to_string(),
+            }
+        );
+        assert_eq!(conflicts[1].alias, "work");
+        assert_eq!(conflicts[1].setting, "user");
+        assert_eq!(conflicts[1].effective_value, "git");
+        assert_eq!(conflicts[2].alias, "personal");
+        assert_eq!(conflicts[2].specific_value, "bob");
+    }
+
+    #[test]
+    fn test_conflicts_ignore_leading_wildcard() {
+        let config = r#"
+Host *
+    User git
+
+Host work
+    User alice
+"#;
+        let hosts = SshConfigParser::parse(config);
+        assert!(SshConfigParser::conflicts(&hosts).is_empty());
+    }
+
+    #[test]
+    fn test_hosts_using_key() {
+        let config = r#"
+Host work
+    IdentityFile /home/user/.ssh/id_work
+
+Host personal
+    IdentityFile /home/user/.ssh/id_personal
+
+Host *.corp
+    IdentityFile /home/user/.ssh/id_work
+
+Host legacy
+    HostName legacy.example.com
+
+Host legacy
+    IdentityFile /home/user/.ssh/id_work
+"#;
+        let hosts = SshConfigParser::parse(config);
+
+        assert_eq!(
+            SshConfigParser::hosts_using_key(&hosts, Path::new("/home/user/.ssh/id_work")),
+            vec!["work", "legacy"]
+        );
+        assert_eq!(
+            SshConfigParser::hosts_using_key(&hosts, Path::new("/home/user/.ssh/id_personal")),
+            vec!["personal"]
+        );
+        assert!(
+            SshConfigParser::hosts_using_key(&hosts, Path::new("/home/user/.ssh/id_other"))
+                .is_empty()
+        );
+    }
+
     #[test]
     fn test_wildcard_pattern() {
         assert!(SshConfigParser::match_pattern("*", "anything"));
@@ -230,4 +1324,91 @@ Host github
             "test.other.com"
         ));
     }
+
+    // ========================================
+    // Global option tests
+    // ========================================
+
+    #[test]
+    fn test_set_and_get_global_options() {
+        let config = "Host github\n    HostName github.com\n\nHost *\n    AddKeysToAgent yes\n    ProxyCommand ssh -o Opt=1 jump\n\n# trailing comment\n";
+        let options = SshConfigParser::global_options(config);
+        assert_eq!(options.len(), 2);
+        assert_eq!(
+            options.get("AddKeysToAgent").map(String::as_str),
+            Some("yes")
+        );
+        assert_eq!(
+            options.get("ProxyCommand").map(String::as_str),
+            Some("ssh -o Opt=1 jump")
+        );
+
+        // Existing directive is replaced in place, matched case-insensitively
+        let updated = SshConfigParser::set_global_option(config, "addkeystoagent", "1h");
+        assert!(updated.contains("    addkeystoagent 1h\n"));
+        assert!(!updated.contains("AddKeysToAgent yes"));
+        assert!(updated.contains("# trailing comment"));
+
+        // New directive goes at the end of the block, not after the comment
+        let updated = SshConfigParser::set_global_option(&updated, "IdentitiesOnly", "yes");
+        assert!(updated.contains("    ProxyCommand ssh -o Opt=1 jump\n    IdentitiesOnly yes\n"));
+        let options = SshConfigParser::global_options(&updated);
+        assert_eq!(
+            options.get("IdentitiesOnly").map(String::as_str),
+            Some("yes")
+        );
+        // github's block is unaffected
+        assert!(!SshConfigParser::parse(&updated)[0]
+            .options
+            .contains_key("identitiesonly"));
+
+        // Without a Host * block one is added before the first block, with its comments,
+        // so it doesn't override the hosts' own settings
+        let created = SshConfigParser::set_global_option(
+            "User root\n\n# Code hosting\nHost github\n    HostName github.com\n    User git",
+            "User",
+            "admin",
+        );
+        assert_eq!(
+            created,
+            "User root\n\nHost *\n    User admin\n\n# Code hosting\nHost github\n    HostName github.com\n    User git\n"
+        );
+        let hosts = SshConfigParser::parse(&created);
+        assert_eq!(
+            SshConfigParser::merge_configs(&hosts, "github")
+                .user
+                .as_deref(),
+            Some("git")
+        );
+        assert_eq!(
+            SshConfigParser::merge_configs(&hosts, "other")
+                .user
+                .as_deref(),
+            Some("admin")
+        );
+        assert!(SshConfigParser::conflicts(&hosts).is_empty());
+        assert_eq!(
+            SshConfigParser::set_global_option("", "ServerAliveInterval", "60"),
+            "Host *\n    ServerAliveInterval 60\n"
+        );
+    }
+
+    #[test]
+    fn test_validate_global_option() {
+        assert!(SshConfigParser::validate_global_option("IdentitiesOnly", "yes").is_ok());
+        assert!(SshConfigParser::validate_global_option("AddKeysToAgent", "30m").is_ok());
+        assert!(
+            SshConfigParser::validate_global_option("StrictHostKeyChecking", "accept-new").is_ok()
+        );
+        assert!(SshConfigParser::validate_global_option("ServerAliveInterval", "60").is_ok());
+        assert!(SshConfigParser::validate_global_option("SomeFutureOption", "anything").is_ok());
+
+        assert!(SshConfigParser::validate_global_option("IdentitiesOnly", "maybe").is_err());
+        assert!(SshConfigParser::validate_global_option("ServerAliveInterval", "1m").is_err());
+        assert!(SshConfigParser::validate_global_option("ServerAliveCountMax", "0").is_err());
+        assert!(SshConfigParser::validate_global_option("Host", "*").is_err());
+        assert!(SshConfigParser::validate_global_option("User Name", "x").is_err());
+        assert!(SshConfigParser::validate_global_option("User", "x\nHost evil").is_err());
+        assert!(SshConfigParser::validate_global_option("User", " ").is_err());
+    }
 }
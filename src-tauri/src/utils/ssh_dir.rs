@@ -0,0 +1,112 @@
+use crate::models::{SshBuddyError, SshResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::{expand_path, validate_path_in_ssh_dir};
+
+/// Environment variable that replaces ~/.ssh, for tests and sandboxed setups
+pub const SSH_DIR_ENV: &str = "SSH_BUDDY_SSH_DIR";
+
+/// The SSH directory every service works in
+/// `SSH_BUDDY_SSH_DIR` wins when set (with `~` expanded); otherwise ~/.ssh
+pub fn ssh_dir() -> SshResult<PathBuf> {
+    match std::env::var(SSH_DIR_ENV) {
+        Ok(dir) if !dir.trim().is_empty() => expand_path(&dir),
+        _ => Ok(dirs::home_dir()
+            .ok_or(SshBuddyError::HomeDirNotFound)?
+            .join(".ssh")),
+    }
+}
+
+/// A file of the SSH directory, read for display
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SshFileContents {
+    pub path: String,
+    pub exists: bool,
+    /// Empty when the file does not exist
+    pub content: String,
+}
+
+/// Read a file directly inside `ssh_dir`; a missing file reads as empty
+/// `name` must be a bare file name, and the file must resolve (symlinks followed)
+/// to a path inside `ssh_dir`
+pub async fn read_ssh_file(ssh_dir: &Path, name: &str) -> SshResult<SshFileContents> {
+    let is_bare_name = Path::new(name)
+        .file_name()
+        .is_some_and(|file_name| file_name == name);
+    if !is_bare_name {
+        return Err(SshBuddyError::PathTraversalDetected {
+            path: name.to_string(),
+        });
+    }
+
+    let path = ssh_dir.join(name);
+    if std::fs::symlink_metadata(&path).is_err() {
+        return Ok(SshFileContents {
+            path: path.to_string_lossy().to_string(),
+            exists: false,
+            content: String::new(),
+        });
+    }
+
+    validate_path_in_ssh_dir(&path, ssh_dir)?;
+    let content = tokio::fs::read_to_string(&path).await?;
+
+    Ok(SshFileContents {
+        path: path.to_string_lossy().to_string(),
+        exists: true,
+        content,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_ssh_file() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let ssh_dir = temp.path().join(".ssh");
+        std::fs::create_dir_all(&ssh_dir).unwrap();
+        std::fs::write(ssh_dir.join("config"), "Host github\n").unwrap();
+
+        let config = read_ssh_file(&ssh_dir, "config").await.unwrap();
+        assert!(config.exists);
+        assert_eq!(config.content, "Host github\n");
+        assert_eq!(config.path, ssh_dir.join("config").to_string_lossy());
+
+        let missing = read_ssh_file(&ssh_dir, "known_hosts").await.unwrap();
+        assert!(!missing.exists);
+        assert_eq!(missing.content, "");
+    }
+
+    #[tokio::test]
+    async fn test_read_ssh_file_stays_in_ssh_dir() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let ssh_dir = temp.path().join(".ssh");
+        std::fs::create_dir_all(&ssh_dir).unwrap();
+        std::fs::write(temp.path().join("secret"), "outside").unwrap();
+
+        for name in ["../secret", "/etc/passwd", "..", "sub/config", ""] {
+            assert!(
+                matches!(
+                    read_ssh_file(&ssh_dir, name).await,
+                    Err(SshBuddyError::PathTraversalDetected { .. })
+                ),
+                "{} was not rejected",
+                name
+            );
+        }
+
+        // A symlink in ~/.ssh may not redirect the read elsewhere
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(temp.path().join("secret"), ssh_dir.join("config")).unwrap();
+            assert!(matches!(
+                read_ssh_file(&ssh_dir, "config").await,
+                Err(SshBuddyError::PathTraversalDetected { .. })
+            ));
+        }
+    }
+}
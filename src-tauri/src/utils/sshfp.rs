@@ -0,0 +1,149 @@
+use crate::models::{SshBuddyError, SshResult};
+use hickory_resolver::proto::rr::{RData, Record, RecordType};
+use hickory_resolver::TokioResolver;
+
+/// An SSHFP record: key algorithm, fingerprint type and digest
+#[derive(Debug, Clone, PartialEq)]
+pub struct SshfpRecord {
+    /// 1 RSA, 2 DSA, 3 ECDSA, 4 Ed25519, 6 Ed448
+    pub algorithm: u8,
+    /// 1 SHA-1, 2 SHA-256
+    pub fingerprint_type: u8,
+    pub fingerprint: Vec<u8>,
+}
+
+/// SSHFP records of a name as returned by the resolver
+#[derive(Debug, Clone, PartialEq)]
+pub struct SshfpAnswer {
+    /// Empty when the name has no SSHFP records or does not exist
+    pub records: Vec<SshfpRecord>,
+    /// Every record in the answer (CNAMEs included) was proven secure with DNSSEC,
+    /// checked here from the signatures up to the root trust anchor
+    pub authenticated: bool,
+}
+
+/// Look up the SSHFP records of `hostname` with the system's name servers
+/// Answers failing DNSSEC validation come back as not authenticated, not as errors
+pub async fn lookup_sshfp(hostname: &str) -> SshResult<SshfpAnswer> {
+    let resolver = validating_resolver(hostname)?;
+
+    match resolver.lookup(hostname, RecordType::SSHFP).await {
+        Ok(lookup) => Ok(sshfp_answer(lookup.records())),
+        Err(e) if e.is_no_records_found() || e.is_nx_domain() => Ok(SshfpAnswer {
+            records: Vec::new(),
+            authenticated: false,
+        }),
+        Err(e) => {
+            log::warn!("[sshfp] SSHFP lookup of {} failed: {}", hostname, e);
+            Err(SshBuddyError::DnsResolutionFailed {
+                hostname: hostname.to_string(),
+            })
+        }
+    }
+}
+
+/// Resolver with the system configuration that validates answers with DNSSEC
+fn validating_resolver(hostname: &str) -> SshResult<TokioResolver> {
+    let mut builder = TokioResolver::builder_tokio().map_err(|e| {
+        log::warn!("[sshfp] Failed to read the system DNS configuration: {}", e);
+        SshBuddyError::DnsResolutionFailed {
+            hostname: hostname.to_string(),
+        }
+    })?;
+    builder.options_mut().validate = true;
+    Ok(builder.build())
+}
+
+/// SSHFP records of a lookup, authenticated only when every record is secure
+fn sshfp_answer(records: &[Record]) -> SshfpAnswer {
+    let sshfp: Vec<SshfpRecord> = records
+        .iter()
+        .filter_map(|record| match record.data() {
+            RData::SSHFP(sshfp) => Some(SshfpRecord {
+                algorithm: sshfp.algorithm().into(),
+                fingerprint_type: sshfp.fingerprint_type().into(),
+                fingerprint: sshfp.fingerprint().to_vec(),
+            }),
+            _ => None,
+        })
+        .collect();
+
+    SshfpAnswer {
+        authenticated: !sshfp.is_empty() && records.iter().all(|record| record.proof().is_secure()),
+        records: sshfp,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hickory_resolver::proto::dnssec::Proof;
+    use hickory_resolver::proto::rr::rdata::sshfp::{Algorithm, FingerprintType};
+    use hickory_resolver::proto::rr::rdata::{CNAME, SSHFP};
+    use hickory_resolver::Name;
+
+    fn record(rdata: RData, proof: Proof) -> Record {
+        let name = Name::from_ascii("host.example.com.").unwrap();
+        let mut record = Record::from_rdata(name, 300, rdata);
+        record.set_proof(proof);
+        record
+    }
+
+    fn sshfp(algorithm: u8, fingerprint_type: u8, fingerprint: &[u8]) -> RData {
+        RData::SSHFP(SSHFP::new(
+            Algorithm::from(algorithm),
+            FingerprintType::from(fingerprint_type),
+            fingerprint.to_vec(),
+        ))
+    }
+
+    #[test]
+    fn test_sshfp_answer_records() {
+        let answer = sshfp_answer(&[
+            record(sshfp(4, 2, &[0xaa, 0xbb]), Proof::Secure),
+            record(sshfp(1, 1, &[0xcc]), Proof::Secure),
+        ]);
+        assert!(answer.authenticated);
+        assert_eq!(
+            answer.records,
+            vec![
+                SshfpRecord {
+                    algorithm: 4,
+                    fingerprint_type: 2,
+                    fingerprint: vec![0xaa, 0xbb],
+                },
+                SshfpRecord {
+                    algorithm: 1,
+                    fingerprint_type: 1,
+                    fingerprint: vec![0xcc],
+                },
+            ]
+        );
+
+        let answer = sshfp_answer(&[]);
+        assert!(answer.records.is_empty());
+        assert!(!answer.authenticated);
+    }
+
+    #[test]
+    fn test_sshfp_answer_needs_every_record_secure() {
+        let answer = sshfp_answer(&[
+            record(sshfp(4, 2, &[0xaa]), Proof::Secure),
+            record(sshfp(1, 1, &[0xcc]), Proof::Insecure),
+        ]);
+        assert_eq!(answer.records.len(), 2);
+        assert!(!answer.authenticated);
+
+        let answer = sshfp_answer(&[record(sshfp(4, 2, &[0xaa]), Proof::Bogus)]);
+        assert!(!answer.authenticated);
+
+        // An unsigned alias leading to signed records
+        let alias = Name::from_ascii("alias.example.com.").unwrap();
+        let answer = sshfp_answer(&[
+            record(RData::CNAME(CNAME(alias)), Proof::Insecure),
+            record(sshfp(4, 2, &[0xaa]), Proof::Secure),
+        ]);
+        assert_eq!(answer.records.len(), 1);
+        assert!(!answer.authenticated);
+    }
+}
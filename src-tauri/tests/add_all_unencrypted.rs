@@ -0,0 +1,65 @@
+//! Adding every unencrypted key in a mock SSH directory to the agent
+
+#![cfg(unix)]
+
+#[allow(dead_code)]
+mod common;
+
+use common::MockSshDir;
+use rand::rngs::OsRng;
+use ssh_buddy_lib::test_support::AgentService;
+use ssh_key::{Algorithm, LineEnding, PrivateKey};
+use std::os::unix::fs::PermissionsExt;
+
+#[tokio::test]
+async fn test_add_all_unencrypted_skips_encrypted_keys() {
+    let mock = MockSshDir::new();
+    let key = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).unwrap();
+    let plain = mock.write_file("id_plain", &key.to_openssh(LineEnding::LF).unwrap());
+    let locked = mock.write_file("id_locked", common::SAMPLE_ENCRYPTED_ED25519_KEY);
+
+    // A stand-in ssh-add that records the key it was given and succeeds
+    let bin_dir = mock.temp_dir.path().join("bin");
+    let invocations = mock.temp_dir.path().join("ssh-add.log");
+    std::fs::create_dir_all(&bin_dir).unwrap();
+    let ssh_add = bin_dir.join("ssh-add");
+    std::fs::write(
+        &ssh_add,
+        format!(
+            "#!/bin/sh\nfor arg; do last=\"$arg\"; done\necho \"$last\" >> '{}'\n",
+            invocations.display()
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&ssh_add, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    // Only this test runs in this binary, so the variables can't leak into other tests
+    let path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", format!("{}:{}", bin_dir.display(), path));
+    std::env::set_var("SSH_BUDDY_SSH_DIR", mock.path());
+    std::env::remove_var("SSH_AUTH_SOCK");
+
+    let results = AgentService::add_all_unencrypted(None).await.unwrap();
+    let result = |path: &std::path::Path| {
+        let path = path.to_string_lossy();
+        results
+            .iter()
+            .find(|(key_path, _)| *key_path == path)
+            .map(|(_, result)| result)
+            .unwrap()
+    };
+
+    let locked_result = result(&locked);
+    assert!(!locked_result.success);
+    assert!(locked_result.needs_passphrase);
+
+    let plain_result = result(&plain);
+    assert!(plain_result.success);
+    assert!(!plain_result.needs_passphrase);
+
+    let attempted = std::fs::read_to_string(&invocations).unwrap();
+    assert_eq!(
+        attempted.lines().collect::<Vec<_>>(),
+        vec![plain.to_str().unwrap()]
+    );
+}
@@ -0,0 +1,73 @@
+//! Bulk encryption of the private keys in a mock SSH directory
+
+#[allow(dead_code)]
+mod common;
+
+use common::MockSshDir;
+use rand::rngs::OsRng;
+use ssh_buddy_lib::test_support::KeyManager;
+use ssh_key::{Algorithm, LineEnding, PrivateKey};
+
+#[tokio::test]
+async fn test_bulk_encrypt_keys_encrypts_unencrypted_keys() {
+    let mock = MockSshDir::new();
+    let key = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).unwrap();
+    let plain = mock.write_file("id_ed25519", &key.to_openssh(LineEnding::LF).unwrap());
+    mock.write_file("id_locked", common::SAMPLE_ENCRYPTED_ED25519_KEY);
+    let locked_before = std::fs::read_to_string(mock.path().join("id_locked")).unwrap();
+
+    // A key kept outside the SSH directory and linked in
+    #[cfg(unix)]
+    let (linked_target, linked_before) = {
+        let linked = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).unwrap();
+        let content = linked.to_openssh(LineEnding::LF).unwrap().to_string();
+        let target = mock.temp_dir.path().join("id_vault");
+        std::fs::write(&target, &content).unwrap();
+        std::os::unix::fs::symlink(&target, mock.path().join("id_linked")).unwrap();
+        (target, content)
+    };
+
+    // Only this test runs in this binary, so the variable can't leak into other tests
+    std::env::set_var("SSH_BUDDY_SSH_DIR", mock.path());
+
+    let results = KeyManager::new()
+        .unwrap()
+        .bulk_encrypt_keys("correct horse")
+        .await
+        .unwrap();
+
+    let status = |name: &str| {
+        results
+            .iter()
+            .find(|r| r.key_name == name)
+            .map(|r| serde_json::to_value(r.status).unwrap())
+    };
+    assert_eq!(status("id_ed25519"), Some("encrypted".into()));
+    assert_eq!(status("id_locked"), Some("already-encrypted".into()));
+
+    let key = PrivateKey::from_openssh(std::fs::read_to_string(&plain).unwrap()).unwrap();
+    assert!(key.is_encrypted());
+    assert!(key.decrypt("correct horse").is_ok());
+    assert_eq!(
+        std::fs::read_to_string(mock.path().join("id_locked")).unwrap(),
+        locked_before
+    );
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        assert_eq!(status("id_linked"), Some("skipped-symlink".into()));
+        assert!(std::fs::symlink_metadata(mock.path().join("id_linked"))
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(
+            std::fs::read_to_string(&linked_target).unwrap(),
+            linked_before
+        );
+
+        let mode = std::fs::metadata(&plain).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
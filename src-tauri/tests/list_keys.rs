@@ -0,0 +1,35 @@
+//! End-to-end tests that point the services at a mock SSH directory
+
+#[allow(dead_code)]
+mod common;
+
+use common::MockSshDir;
+use rand::rngs::OsRng;
+use ssh_buddy_lib::test_support::KeyManager;
+use ssh_key::{Algorithm, LineEnding, PrivateKey};
+
+#[tokio::test]
+async fn test_list_keys_in_configured_ssh_dir() {
+    let mock = MockSshDir::new();
+
+    let mut key = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).unwrap();
+    key.set_comment("test@example");
+    let fingerprint = key.public_key().fingerprint(ssh_key::HashAlg::Sha256);
+    mock.write_file("id_ed25519", &key.to_openssh(LineEnding::LF).unwrap());
+    mock.write_file("id_ed25519.pub", &key.public_key().to_openssh().unwrap());
+    mock.write_known_hosts(common::SAMPLE_KNOWN_HOSTS);
+
+    // Only this test runs in this binary, so the variable can't leak into other tests
+    std::env::set_var("SSH_BUDDY_SSH_DIR", mock.path());
+
+    let keys = KeyManager::new().unwrap().list_keys().await.unwrap();
+
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].name, "id_ed25519");
+    assert_eq!(
+        keys[0].private_key_path,
+        mock.path().join("id_ed25519").to_string_lossy()
+    );
+    assert_eq!(keys[0].fingerprint, Some(fingerprint.to_string()));
+    assert_eq!(keys[0].comment.as_deref(), Some("test@example"));
+}
@@ -0,0 +1,28 @@
+//! Reading the public half of a private key without its passphrase
+
+#[allow(dead_code)]
+mod common;
+
+use common::MockSshDir;
+use ssh_buddy_lib::test_support::KeyManager;
+use ssh_key::PublicKey;
+
+#[tokio::test]
+async fn test_public_from_encrypted_private() {
+    let mock = MockSshDir::new();
+    let path = mock.write_file("id_locked", common::SAMPLE_ENCRYPTED_ED25519_KEY);
+
+    let pub_key = KeyManager::public_from_private_key_file(&path)
+        .await
+        .unwrap();
+    let expected = PublicKey::from_openssh(
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIJdXFUzuH3JQTQRSJK3e2hVJ3NrFxZbSNKMqL5+2j8Vo",
+    )
+    .unwrap();
+
+    assert_eq!(pub_key.key_data(), expected.key_data());
+    assert_eq!(
+        pub_key.fingerprint(ssh_key::HashAlg::Sha256).to_string(),
+        "SHA256:UgCdBs1ks5bER4To81GKWoW9Zg4sQ80TjaTQs+7IQEI"
+    );
+}
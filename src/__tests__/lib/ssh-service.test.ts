@@ -131,6 +131,18 @@ describe('ssh-service', () => {
       })
     })
 
+    it('should pass the secure delete flag', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce({ referencedBy: [], message: 'overwritten' })
+
+      const { deleteSSHKey } = await import('../../lib/ssh-service')
+      await deleteSSHKey('id_test', true)
+
+      expect(invoke).toHaveBeenCalledWith('delete_ssh_key', {
+        keyName: 'id_test',
+        secureDelete: true,
+      })
+    })
+
     it('should throw error on deletion failure', async () => {
       vi.mocked(invoke).mockRejectedValueOnce(new Error('Permission denied'))
 
@@ -140,6 +152,27 @@ describe('ssh-service', () => {
     })
   })
 
+  describe('archiveSSHKey', () => {
+    it('should archive and restore a key by name', async () => {
+      vi.mocked(invoke)
+        .mockResolvedValueOnce(undefined)
+        .mockResolvedValueOnce(undefined)
+
+      const { archiveSSHKey, unarchiveSSHKey } = await import(
+        '../../lib/ssh-service'
+      )
+      await archiveSSHKey('id_old')
+      await unarchiveSSHKey('id_old')
+
+      expect(invoke).toHaveBeenCalledWith('archive_ssh_key', {
+        keyName: 'id_old',
+      })
+      expect(invoke).toHaveBeenCalledWith('unarchive_ssh_key', {
+        keyName: 'id_old',
+      })
+    })
+  })
+
   // ========================================
   // generateSSHKey tests
   // ========================================
@@ -215,6 +248,42 @@ describe('ssh-service', () => {
       expect(result.platform).toBe('github')
     })
 
+    it('should pass the identity file override', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce({ success: true, output: '' })
+
+      const { testSSHConnection } = await import('../../lib/ssh-service')
+      await testSSHConnection('github', undefined, '~/.ssh/id_work')
+
+      expect(invoke).toHaveBeenCalledWith('test_ssh_connection', {
+        hostAlias: 'github',
+        identityFile: '~/.ssh/id_work',
+      })
+    })
+
+    it('should pass the fetch banner option', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce({ success: true, output: '' })
+
+      const { testSSHConnection } = await import('../../lib/ssh-service')
+      await testSSHConnection('myserver', undefined, undefined, false)
+
+      expect(invoke).toHaveBeenCalledWith('test_ssh_connection', {
+        hostAlias: 'myserver',
+        fetchBanner: false,
+      })
+    })
+
+    it('should pass the prefer agent option', async () => {
+      vi.mocked(invoke).mockResolvedValueOnce({ success: true, output: '' })
+
+      const { testSSHConnection } = await import('../../lib/ssh-service')
+      await testSSHConnection('myserver', undefined, undefined, undefined, true)
+
+      expect(invoke).toHaveBeenCalledWith('test_ssh_connection', {
+        hostAlias: 'myserver',
+        preferAgent: true,
+      })
+    })
+
     it('should handle host_key_unknown error', async () => {
       const mockResult = {
         success: false,
@@ -145,7 +145,11 @@ export function HostDetail({
     if (!testResult?.hostToRemove) return
     setIsRemovingHost(true)
     try {
-      await removeKnownHost(testResult.hostToRemove)
+      await removeKnownHost(
+        testResult.hostToRemove,
+        testResult.knownHostsFile,
+        testResult.portToRemove
+      )
       // Clear result and re-test
       setTestResult(null)
       // Auto re-test after removing
@@ -161,7 +165,15 @@ export function HostDetail({
     if (!testResult?.hostToAdd) return
     setIsAddingHost(true)
     try {
-      await addKnownHost(testResult.hostToAdd)
+      const fixParams = testResult.errorDetails?.fixParams
+      await addKnownHost(
+        testResult.hostToAdd,
+        fixParams?.port ? Number(fixParams.port) : undefined,
+        testResult.knownHostsFile,
+        undefined,
+        undefined,
+        fixParams?.hostKeyAlias
+      )
       // Clear result and re-test
       setTestResult(null)
       // Auto re-test after adding
@@ -618,6 +630,34 @@ const errorConfigs: Record<
       'The server is online and reachable',
     ],
   },
+  rate_limited: {
+    icon: Clock,
+    title: 'Too Many Attempts',
+    description:
+      'The server is limiting connections, or this host was tested several times in a short time.',
+    suggestions: [
+      'Wait a minute before testing again',
+      'Use IdentitiesOnly yes so only the configured key is offered',
+    ],
+  },
+  certificate_expired: {
+    icon: Clock,
+    title: 'Certificate Not Valid',
+    description: 'The SSH certificate for this key is outside its validity period.',
+    suggestions: [
+      'Request a new certificate from your certificate authority',
+      'Check that your system clock is correct',
+    ],
+  },
+  certificate_principal_mismatch: {
+    icon: Ban,
+    title: 'Certificate Not Valid for User',
+    description: "The SSH certificate's principals don't include the user for this host.",
+    suggestions: [
+      'Set User to one of the certificate principals',
+      'Request a certificate issued for this user',
+    ],
+  },
   dns_failed: {
     icon: Globe2,
     title: 'Hostname Not Found',
@@ -29,6 +29,7 @@ interface RustPermissionCheckResult {
   currentMode: string | null
   expectedMode: string
   message: string
+  symlinkTarget: string | null // Set when the key is a symlink; the mode is the target's
 }
 
 /**
@@ -121,6 +122,41 @@ export async function fixKeyPermissions(keyPath: string): Promise<{
   }
 }
 
+/**
+ * A private key whose permissions are too open
+ */
+export interface InsecureKey {
+  name: string
+  path: string
+  currentMode: string | null // Octal mode such as "644" ("ACL" on Windows)
+}
+
+/**
+ * List private keys in ~/.ssh whose permissions are not 600
+ * Uses Rust backend
+ */
+export async function listInsecureKeys(): Promise<InsecureKey[]> {
+  return invoke<InsecureKey[]>('list_insecure_keys')
+}
+
+export interface InsecureKeyFix {
+  path: string
+  result: {
+    success: boolean
+    message: string
+    newMode: string | null
+  }
+}
+
+/**
+ * Fix the permissions of every key `listInsecureKeys` reports
+ * Uses Rust backend
+ * @returns One result per key, so a single failure doesn't hide the others
+ */
+export async function fixAllInsecureKeys(): Promise<InsecureKeyFix[]> {
+  return invoke<InsecureKeyFix[]>('fix_all_insecure_keys')
+}
+
 /**
  * Check if SSH directory has correct permissions
  * Uses Rust backend
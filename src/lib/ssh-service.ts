@@ -11,6 +11,7 @@ import {
 } from '@tauri-apps/plugin-fs'
 import { homeDir } from '@tauri-apps/api/path'
 import { invoke } from '@tauri-apps/api/core'
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
 import {
   parseSSHConfig,
   serializeSSHConfig,
@@ -32,6 +33,29 @@ export interface SSHKeyInfo {
   fingerprint?: string
   comment?: string
   bitSize?: number // Key bit size (e.g., 4096 for RSA)
+  strength?: 'strong' | 'acceptable' | 'weak' | 'deprecated'
+  certificate?: SSHCertificateInfo | null
+  permissionWarning?: string | null // Private key is readable by group/others
+}
+
+/**
+ * SSH certificate details (`<name>-cert.pub`)
+ */
+export interface SSHCertificateInfo {
+  name: string
+  path: string
+  type: 'ed25519' | 'rsa' | 'ecdsa' | 'dsa' | 'unknown'
+  certType: 'user' | 'host'
+  keyId: string
+  serial: number
+  principals: string[]
+  validAfter: number // Unix seconds
+  validBefore: number // Unix seconds
+  expired: boolean
+  notYetValid: boolean
+  fingerprint: string
+  caFingerprint: string
+  comment: string
 }
 
 let sshDirPath: string | null = null
@@ -155,6 +179,55 @@ export async function listSSHKeys(): Promise<SSHKeyInfo[]> {
   }
 }
 
+export type KeySortKey = 'name' | 'type' | 'bitSize' | 'age'
+
+export interface KeyFilter {
+  keyType?: SSHKeyInfo['type']
+  needsAttention?: boolean // Weak, readable by others, or missing its public key
+}
+
+/**
+ * List SSH keys matching a filter, sorted by the backend
+ * Includes private keys that have no public key file
+ * Uses Rust backend
+ */
+export async function listSSHKeysFiltered(
+  sortBy?: KeySortKey,
+  filter?: KeyFilter
+): Promise<SSHKeyInfo[]> {
+  return invoke<SSHKeyInfo[]>('list_ssh_keys_filtered', { sortBy, filter })
+}
+
+/**
+ * A key with the last time it passed a connection test
+ */
+export interface SSHKeyWithUsage extends SSHKeyInfo {
+  lastUsed?: number | null // Unix seconds; null if never used in a successful test
+}
+
+/**
+ * List SSH keys with when each last authenticated a successful connection test
+ * Uses Rust backend
+ */
+export async function listSSHKeysWithUsage(): Promise<SSHKeyWithUsage[]> {
+  return invoke<SSHKeyWithUsage[]>('list_ssh_keys_with_usage')
+}
+
+/**
+ * A key with the config aliases that use it
+ */
+export interface SSHKeyWithHosts extends SSHKeyInfo {
+  usedByHosts: string[] // Empty when no host uses the key
+}
+
+/**
+ * List SSH keys with the ~/.ssh/config aliases whose IdentityFile points at each
+ * Uses Rust backend
+ */
+export async function listSSHKeysWithHosts(): Promise<SSHKeyWithHosts[]> {
+  return invoke<SSHKeyWithHosts[]>('list_ssh_keys_with_hosts')
+}
+
 /**
  * Read public key content
  * Uses Rust backend with path traversal protection
@@ -168,20 +241,170 @@ export async function readPublicKey(keyName: string): Promise<string> {
   }
 }
 
+/**
+ * Public key in the encodings other tools ask for
+ */
+export interface PublicKeyFormats {
+  openssh: string
+  rfc4716: string // "---- BEGIN SSH2 PUBLIC KEY ----" block
+  base64: string
+}
+
+/**
+ * Read a public key as OpenSSH line, RFC 4716 block and bare base64
+ * Uses Rust backend
+ */
+export async function getPublicKeyFormats(
+  keyName: string
+): Promise<PublicKeyFormats> {
+  return invoke<PublicKeyFormats>('get_public_key_formats', { keyName })
+}
+
+/**
+ * Whether two OpenSSH public keys are the same key; comments are ignored
+ * Uses Rust backend, which rejects input that isn't a public key
+ */
+export async function comparePublicKeys(a: string, b: string): Promise<boolean> {
+  return invoke<boolean>('compare_public_keys', { a, b })
+}
+
+export interface DeleteKeyResult {
+  referencedBy: string[] // Config aliases whose IdentityFile was the deleted key
+  message?: string | null // Caveat shown after a secure delete
+}
+
 /**
  * Delete an SSH key pair
  * Uses Rust backend with path traversal protection
+ * Hosts that used the key don't block deletion; they are returned in `referencedBy`
+ * @param secureDelete - Overwrite the private key with random bytes before removing it
  */
-export async function deleteSSHKey(keyName: string): Promise<void> {
+export async function deleteSSHKey(
+  keyName: string,
+  secureDelete?: boolean
+): Promise<DeleteKeyResult> {
   try {
-    await invoke('delete_ssh_key', { keyName })
+    const result = await invoke<DeleteKeyResult>('delete_ssh_key', {
+      keyName,
+      secureDelete,
+    })
     console.log('[ssh-service] Key deleted:', keyName)
+    return result
   } catch (error) {
     console.error('[ssh-service] Failed to delete key:', error)
     throw error
   }
 }
 
+/**
+ * Result of checking a private key file for corruption
+ */
+export interface KeyValidation {
+  parseable: boolean
+  encrypted: boolean
+  decrypted: boolean // Private section decrypted (or not encrypted)
+  publicKeyMatches?: boolean | null // null when there is no .pub file
+  error?: string | null
+}
+
+/**
+ * Fully parse a private key, decrypting it when a passphrase is given
+ * Uses Rust backend
+ */
+export async function validateKeyFile(
+  keyName: string,
+  passphrase?: string
+): Promise<KeyValidation> {
+  return invoke<KeyValidation>('validate_key_file', { keyName, passphrase })
+}
+
+export interface MigrateKeyResult {
+  oldKey: SSHKeyInfo
+  newKey: SSHKeyInfo
+  newPublicKey: string // Public key line of the new key, ready to deploy
+  hostsToUpdate: string[] // Config aliases still using the old key
+}
+
+/**
+ * DSA and RSA keys below 3072 bits that should be replaced
+ * Uses Rust backend
+ */
+export async function legacyKeyReport(): Promise<SSHKeyInfo[]> {
+  return invoke<SSHKeyInfo[]>('legacy_key_report')
+}
+
+/**
+ * Generate an Ed25519 replacement for a legacy key; the old key is kept
+ * Uses Rust backend
+ */
+export async function migrateToEd25519(
+  oldName: string,
+  newName: string
+): Promise<MigrateKeyResult> {
+  return invoke<MigrateKeyResult>('migrate_to_ed25519', { oldName, newName })
+}
+
+export interface KeyPairStatus {
+  privatePath: string
+  privateExists: boolean
+  publicPath: string
+  publicExists: boolean
+  privateIsEncrypted?: boolean | null // null when there is no private key
+}
+
+/**
+ * Paths of a key pair's two files and which of them exist
+ * Uses Rust backend
+ */
+export async function keyPairStatus(keyName: string): Promise<KeyPairStatus> {
+  return invoke<KeyPairStatus>('key_pair_status', { keyName })
+}
+
+/**
+ * Outcome of encrypting one private key
+ */
+export interface KeyEncryptResult {
+  keyName: string
+  path: string
+  status: 'encrypted' | 'already-encrypted' | 'skipped-symlink' | 'failed'
+  message?: string | null // Reason for a failure or a skipped symlink
+}
+
+/**
+ * Encrypt every unencrypted private key in ~/.ssh with one passphrase
+ * Already encrypted and symlinked keys are skipped
+ * Uses Rust backend
+ */
+export async function bulkEncryptKeys(
+  passphrase: string
+): Promise<KeyEncryptResult[]> {
+  return invoke<KeyEncryptResult[]>('bulk_encrypt_keys', { passphrase })
+}
+
+/**
+ * Move a key pair into ~/.ssh/archive/ so it no longer appears in the key list
+ * Uses Rust backend
+ */
+export async function archiveSSHKey(keyName: string): Promise<void> {
+  return invoke<void>('archive_ssh_key', { keyName })
+}
+
+/**
+ * Move an archived key pair back into ~/.ssh
+ * Uses Rust backend
+ */
+export async function unarchiveSSHKey(keyName: string): Promise<void> {
+  return invoke<void>('unarchive_ssh_key', { keyName })
+}
+
+/**
+ * Keys in ~/.ssh/archive/
+ * Uses Rust backend
+ */
+export async function listArchivedKeys(): Promise<SSHKeyInfo[]> {
+  return invoke<SSHKeyInfo[]>('list_archived_keys')
+}
+
 /**
  * Generate options for SSH key generation
  */
@@ -190,6 +413,34 @@ export interface GenerateSSHKeyOptions {
   type: 'ed25519' | 'rsa'
   comment?: string
   passphrase?: string
+  overwrite?: boolean
+  /** RSA key size in bits (defaults to 4096) */
+  bits?: number
+  /** Existing directory owned by the user to write the key to (defaults to ~/.ssh) */
+  outputDir?: string
+}
+
+/**
+ * Key algorithm the backend can offer for generation
+ */
+export interface KeyAlgorithmInfo {
+  id: string // Value for GenerateSSHKeyOptions.type
+  displayName: string
+  defaultBits?: number
+  minBits?: number
+  maxBits?: number
+  curve?: string
+  requiresHardwareKey: boolean // sk-* keys need a FIDO authenticator
+  strength: 'strong' | 'acceptable' | 'weak' | 'deprecated'
+  recommended: boolean
+}
+
+/**
+ * List the key algorithms this build supports, recommended first
+ * Uses Rust backend
+ */
+export async function getSupportedKeyAlgorithms(): Promise<KeyAlgorithmInfo[]> {
+  return invoke<KeyAlgorithmInfo[]>('supported_key_algorithms')
 }
 
 /**
@@ -207,6 +458,9 @@ export async function generateSSHKey(
         keyType: options.type,
         comment: options.comment,
         passphrase: options.passphrase,
+        overwrite: options.overwrite,
+        bits: options.bits,
+        outputDir: options.outputDir,
       },
     })
     console.log('[ssh-service] Key generated successfully:', keyInfo.name)
@@ -217,6 +471,15 @@ export async function generateSSHKey(
   }
 }
 
+/**
+ * Set the comment given to keys generated without one
+ * Uses Rust backend
+ * @param template - Tokens {user}, {host} and {date}; null or blank restores "{user}@{host}"
+ */
+export async function setDefaultKeyCommentTemplate(template: string | null): Promise<void> {
+  await invoke('set_default_key_comment_template', { template })
+}
+
 /**
  * Get key type display name
  */
@@ -254,9 +517,12 @@ export type SSHErrorType =
   | 'connection_refused'
   | 'timeout'
   | 'dns_failed'
+  | 'rate_limited' // server throttling, or too many tests in a short time
   // Configuration issues
   | 'identity_file_not_found' // specified key doesn't exist
   | 'public_key_missing' // .pub file missing
+  | 'certificate_expired' // CertificateFile expired or not valid yet
+  | 'certificate_principal_mismatch' // certificate not issued for this user
   | 'unknown'
 
 /**
@@ -274,6 +540,47 @@ export interface SSHErrorDetails {
     | 'remove-known-host'
     | 'add-known-host'
   fixParams?: Record<string, string>
+  suggestedActions?: SSHSuggestedAction[]
+}
+
+/**
+ * Follow-up action suggested by the backend, identified by a stable ID
+ */
+export interface SSHSuggestedAction {
+  id: string
+  label: string
+  params?: Record<string, string>
+}
+
+/**
+ * A key offered to the server during a connection test
+ */
+export interface AuthAttempt {
+  fingerprint: string // SHA256 fingerprint of the offered key
+  source: 'file' | 'agent'
+  outcome: 'accepted' | 'rejected' | 'error' | 'skipped'
+  message?: string // Error message when outcome is 'error'
+}
+
+/**
+ * How a host responded to one local key
+ */
+export interface KeyProbe {
+  name: string
+  path: string
+  fingerprint?: string | null
+  outcome: AuthAttempt['outcome'] // 'skipped' for encrypted or disallowed keys
+  message?: string | null
+}
+
+/**
+ * Which local key a host accepts
+ */
+export interface WorkingKeyResult {
+  hostname: string
+  user: string
+  workingKey?: KeyProbe | null // null if no key worked
+  attempts: KeyProbe[]
 }
 
 /**
@@ -286,9 +593,14 @@ export interface SSHConnectionTestResult {
   errorType?: SSHErrorType
   errorDetails?: SSHErrorDetails // Extended error information for diagnostics
   hostToRemove?: string
+  portToRemove?: number // Port of the hostToRemove entries
   hostToAdd?: string // For host_key_unknown - the hostname to add to known_hosts
+  knownHostsFile?: string // The known_hosts file hostToRemove/hostToAdd refer to
   identityFile?: string // The key file actually used for authentication
+  hostKeyRandomart?: string // Randomart of the server host key (SHA256) for visual comparison
+  authAttempts?: AuthAttempt[] // Keys offered to the server, in order
   debugLog?: string // Full verbose output for debugging
+  suggestedUser?: string | null // Likely correct SSH user when the configured one looks wrong
 }
 
 /**
@@ -313,11 +625,15 @@ function detectErrorType(output: string): SSHErrorType | undefined {
 /**
  * Test SSH connection to a host
  * Uses Rust backend for pure Rust SSH connection testing
+ * `identityFile` forces a specific key under ~/.ssh instead of the host's IdentityFile
  */
 export async function testSSHConnection(
   hostAlias: string,
   // eslint-disable-next-line @typescript-eslint/no-unused-vars
-  _hostname?: string
+  _hostname?: string,
+  identityFile?: string,
+  fetchBanner?: boolean, // Read the welcome banner; defaults to true only for Git platforms
+  preferAgent?: boolean // Try the SSH agent before the key file
 ): Promise<SSHConnectionTestResult> {
   console.log(
     '[ssh-service] Testing SSH connection via Rust backend:',
@@ -329,6 +645,9 @@ export async function testSSHConnection(
       'test_ssh_connection',
       {
         hostAlias,
+        identityFile,
+        fetchBanner,
+        preferAgent,
       }
     )
     console.log('[ssh-service] SSH test result:', {
@@ -347,6 +666,149 @@ export async function testSSHConnection(
   }
 }
 
+/**
+ * Try every local key against a host to find one it accepts
+ * Rejects with a RateLimited error when the host was tested too often in the last minute
+ * Uses Rust backend
+ */
+export async function findWorkingKey(
+  hostAlias: string
+): Promise<WorkingKeyResult> {
+  return invoke<WorkingKeyResult>('find_working_key', { hostAlias })
+}
+
+export interface AuthMethodsProbe {
+  hostname: string
+  port: number
+  user: string
+  methods: string[] // e.g. 'publickey', 'password', 'keyboard-interactive'
+}
+
+/**
+ * List the authentication methods a host's server offers, without authenticating
+ * Uses Rust backend
+ */
+export async function probeAuthMethods(
+  hostAlias: string
+): Promise<AuthMethodsProbe> {
+  return invoke<AuthMethodsProbe>('probe_auth_methods', { hostAlias })
+}
+
+/**
+ * How many keys a connection to a host would offer
+ */
+export interface ConnectionPreflight {
+  hostname: string
+  port: number
+  agentKeyCount: number | null // null if the agent couldn't be reached
+  configKeyCount: number
+  identitiesOnly: boolean
+  offeredKeyCount: number
+  likelyTooManyKeys: boolean // More than a typical server's MaxAuthTries (6)
+  suggestion?: string | null
+}
+
+/**
+ * Count the keys a connection would offer, before connecting
+ * Uses Rust backend
+ */
+export async function connectionPreflight(
+  hostAlias: string
+): Promise<ConnectionPreflight> {
+  return invoke<ConnectionPreflight>('connection_preflight', { hostAlias })
+}
+
+export interface Motd {
+  motd: string // Shell output verbatim
+  truncated: boolean // Output was cut off at the size limit
+}
+
+/**
+ * Log in to a host and read its MOTD, without judging the connection
+ * Uses Rust backend
+ */
+export async function fetchMotd(hostAlias: string): Promise<Motd> {
+  return invoke<Motd>('fetch_motd', { hostAlias })
+}
+
+/**
+ * A value of a host's effective configuration
+ */
+export interface EffectiveValue {
+  value: string
+  line?: number | null // Line of ~/.ssh/config it came from; absent for defaults
+}
+
+/**
+ * Fields of a Git host's "Add SSH key" form
+ */
+export interface GitKeyPayload {
+  title: string // "<computer> <comment> <YYYY-MM-DD>"
+  key: string // OpenSSH public key line, no trailing newline
+  keyType: string // e.g. "ssh-ed25519"
+}
+
+/**
+ * Title and key to paste into GitHub/GitLab's "Add SSH key" form
+ * Uses Rust backend
+ */
+export async function githubKeyPayload(keyName: string): Promise<GitKeyPayload> {
+  return invoke<GitKeyPayload>('github_key_payload', { keyName })
+}
+
+/**
+ * Host block for ~/.ssh/config that points hostname at a key
+ * Indented and newline-terminated, ready to paste or append to the config
+ * Uses Rust backend
+ */
+export async function suggestConfigBlock(
+  keyName: string,
+  hostname: string,
+  user: string
+): Promise<string> {
+  return invoke<string>('suggest_config_block', { keyName, hostname, user })
+}
+
+/**
+ * Aliases in ~/.ssh/config whose IdentityFile is ~/.ssh/<keyName>
+ * Uses Rust backend
+ */
+export async function configHostsUsingKey(keyName: string): Promise<string[]> {
+  return invoke<string[]>('config_hosts_using_key', { keyName })
+}
+
+/**
+ * Fully merged configuration for a host, like `ssh -G <alias>`
+ * Keys are lowercase directive names (hostname, port, user, identityfile, ...)
+ * Uses Rust backend
+ */
+export async function getEffectiveConfig(
+  alias: string
+): Promise<Record<string, EffectiveValue>> {
+  return invoke<Record<string, EffectiveValue>>('get_effective_config', {
+    alias,
+  })
+}
+
+/**
+ * A per-host setting that a later, broader Host block overrides
+ */
+export interface ConfigConflict {
+  alias: string
+  setting: string // Lowercase directive name
+  specificValue: string
+  effectiveValue: string
+  overridingPattern: string // e.g. '*'
+}
+
+/**
+ * Find per-host settings in ~/.ssh/config shadowed by later broader blocks
+ * Uses Rust backend
+ */
+export async function getConfigConflicts(): Promise<ConfigConflict[]> {
+  return invoke<ConfigConflict[]>('config_conflicts')
+}
+
 /**
  * Known host operation result
  */
@@ -355,17 +817,26 @@ interface KnownHostResult {
   message: string
   removedCount?: number
   keysAdded?: number
+  keysRejected?: number
 }
 
 /**
  * Remove a host from known_hosts file
  * Uses Rust backend
+ * @param knownHostsFile - File to edit (inside ~/.ssh); defaults to ~/.ssh/known_hosts
+ * @param port - Only remove this port's entries; when omitted, hashed entries for other ports are kept
  */
-export async function removeKnownHost(hostname: string): Promise<void> {
+export async function removeKnownHost(
+  hostname: string,
+  knownHostsFile?: string,
+  port?: number
+): Promise<void> {
   console.log('[ssh-service] Removing known host via Rust backend:', hostname)
 
   const result = await invoke<KnownHostResult>('remove_known_host', {
     hostname,
+    knownHostsFile,
+    port,
   })
   console.log('[ssh-service] Remove known host result:', result)
 
@@ -374,19 +845,213 @@ export async function removeKnownHost(hostname: string): Promise<void> {
   }
 }
 
+/**
+ * Rename a host in ~/.ssh/known_hosts, keeping its keys
+ * `port` selects `[host]:port` entries (default 22); hashed entries are skipped
+ * Uses Rust backend
+ * @returns Number of entries updated
+ */
+export async function relabelKnownHost(
+  oldHostname: string,
+  newHostname: string,
+  port?: number
+): Promise<number> {
+  return invoke<number>('relabel_known_host', {
+    oldHostname,
+    newHostname,
+    port,
+  })
+}
+
+export type KnownHostsLineKind =
+  | 'comment'
+  | 'blank'
+  | 'standard'
+  | 'hashed'
+  | 'cert-authority'
+  | 'revoked'
+  | 'malformed'
+
+export interface AnnotatedKnownHostsLine {
+  line: number
+  text: string
+  kind: KnownHostsLineKind
+  hosts?: string
+  keyType?: string
+  fingerprint?: string
+  warning?: string // Why a line is malformed
+}
+
+/**
+ * A file of ~/.ssh read by the backend
+ */
+export interface SshFileContents {
+  path: string
+  exists: boolean
+  content: string // Empty when the file does not exist
+}
+
+/**
+ * Read ~/.ssh/config as written
+ * Uses Rust backend, so the frontend needs no fs access to ~/.ssh
+ */
+export async function readSSHConfigRaw(): Promise<SshFileContents> {
+  return invoke<SshFileContents>('read_ssh_config')
+}
+
+/**
+ * Save edited content as ~/.ssh/config
+ * Uses Rust backend; rejects with InvalidConfig if a line doesn't parse
+ * @returns Path of the backup of the previous config (null when there was none)
+ */
+export async function writeSSHConfigRaw(content: string): Promise<string | null> {
+  return invoke<string | null>('write_ssh_config', { content })
+}
+
+/**
+ * Options of the `Host *` block in ~/.ssh/config, keyed by directive name
+ * Uses Rust backend
+ */
+export async function getGlobalSSHOptions(): Promise<Record<string, string>> {
+  return invoke<Record<string, string>>('get_global_ssh_options')
+}
+
+/**
+ * Set one option in the `Host *` block, creating the block if needed
+ * Uses Rust backend; rejects with InvalidConfig for an invalid value
+ * @returns Path of the backup of the previous config (null when there was none)
+ */
+export async function setGlobalSSHOption(key: string, value: string): Promise<string | null> {
+  return invoke<string | null>('set_global_ssh_option', { key, value })
+}
+
+/**
+ * Read ~/.ssh/known_hosts as written
+ * Uses Rust backend, so the frontend needs no fs access to ~/.ssh
+ */
+export async function readKnownHostsRaw(): Promise<SshFileContents> {
+  return invoke<SshFileContents>('read_known_hosts_raw')
+}
+
+/**
+ * Read ~/.ssh/known_hosts line by line, each classified for display
+ * Uses Rust backend
+ */
+export async function readKnownHostsAnnotated(): Promise<
+  AnnotatedKnownHostsLine[]
+> {
+  return invoke<AnnotatedKnownHostsLine[]>('read_known_hosts_annotated')
+}
+
+/**
+ * Convert ~/.ssh/known_hosts to LF line endings (e.g. after editing it on Windows)
+ * Uses Rust backend
+ * @returns Number of lines that had a carriage return
+ */
+export async function normalizeKnownHosts(): Promise<number> {
+  return invoke<number>('normalize_known_hosts')
+}
+
+/**
+ * known_hosts lines repeating the same host field and key
+ */
+export interface KnownHostsDuplicateEntries {
+  hosts: string
+  keyType: string
+  fingerprint: string | null
+  lines: number[] // 1-based; a dedupe keeps the first
+}
+
+/**
+ * A host stored with several different keys of the same algorithm
+ */
+export interface KnownHostsConflictingKeys {
+  host: string
+  keyType: string
+  fingerprints: string[]
+  lines: number[]
+}
+
+export interface KnownHostsDuplicates {
+  exact: KnownHostsDuplicateEntries[]
+  conflicting: KnownHostsConflictingKeys[]
+}
+
+/**
+ * Find exact duplicate entries and hosts with conflicting keys in ~/.ssh/known_hosts
+ * Uses Rust backend
+ */
+export async function findKnownHostsDuplicates(): Promise<KnownHostsDuplicates> {
+  return invoke<KnownHostsDuplicates>('find_known_hosts_duplicates')
+}
+
+/**
+ * Remove exact duplicate entries from ~/.ssh/known_hosts, keeping the first of each
+ * Uses Rust backend
+ * @returns Number of lines removed
+ */
+export async function dedupeKnownHosts(): Promise<number> {
+  return invoke<number>('dedupe_known_hosts')
+}
+
+export type SshfpMatch = 'match' | 'mismatch' | 'no-record'
+
+export type SshfpStatus = 'verified' | 'mismatch' | 'no-records' | 'no-matching-algorithm'
+
+/**
+ * Host keys checked against the SSHFP records in DNS
+ */
+export interface SshfpVerification {
+  hostname: string
+  port: number
+  status: SshfpStatus
+  dnssecSigned: boolean // Every record was validated with DNSSEC up to the root trust anchor
+  recordCount: number
+  keys: {
+    keyType: string
+    fingerprint: string
+    status: SshfpMatch
+  }[] // Empty when there are no records; the server is not scanned then
+}
+
+/**
+ * Check a host's keys against its SSHFP DNS records, a trust anchor beyond first use
+ * Uses Rust backend
+ * @param port - Port the keys are scanned on (default 22)
+ */
+export async function verifySshfp(
+  hostname: string,
+  port?: number
+): Promise<SshfpVerification> {
+  return invoke<SshfpVerification>('verify_sshfp', { hostname, port })
+}
+
 /**
  * Add a host to known_hosts file
  * Uses Rust backend with ssh-keyscan
+ * @param knownHostsFile - File to edit (inside ~/.ssh); defaults to ~/.ssh/known_hosts
+ * @param hashHostnames - Store the host name hashed, like `HashKnownHosts yes`
+ * @param timeoutSecs - Wait for slow servers' keys (default 5, at most 60)
+ * @param hostKeyAlias - Store the keys under this name instead, like `HostKeyAlias`
+ * Rejects with a NoHostKeys error when the server answers but returns no keys
  */
 export async function addKnownHost(
   hostname: string,
-  port?: number
+  port?: number,
+  knownHostsFile?: string,
+  hashHostnames?: boolean,
+  timeoutSecs?: number,
+  hostKeyAlias?: string
 ): Promise<void> {
   console.log('[ssh-service] Adding known host via Rust backend:', hostname)
 
   const result = await invoke<KnownHostResult>('add_known_host', {
     hostname,
     port,
+    knownHostsFile,
+    hashHostnames,
+    timeoutSecs,
+    hostKeyAlias,
   })
   console.log('[ssh-service] Add known host result:', result)
 
@@ -395,6 +1060,96 @@ export async function addKnownHost(
   }
 }
 
+/**
+ * A host key returned by a scan
+ */
+export interface ScannedKey {
+  keyType: string // e.g. "ssh-ed25519"
+  key: string // "<type> <base64>", as stored in known_hosts
+  fingerprint: string // SHA256
+}
+
+/**
+ * Result of adding a host after checking its key fingerprint
+ */
+export interface VerifiedHostResult {
+  hostname: string
+  port: number
+  matchedKey: ScannedKey
+  keyAdded: boolean // false when the key was already in known_hosts
+}
+
+/**
+ * Add a host to ~/.ssh/known_hosts only if one of its keys has the expected
+ * SHA256 fingerprint ("SHA256:..." or bare), obtained out-of-band
+ * Rejects with a HostKeyMismatch error when no key matches
+ * Uses Rust backend
+ */
+export async function addKnownHostVerified(
+  hostname: string,
+  expectedFingerprint: string,
+  port?: number
+): Promise<VerifiedHostResult> {
+  return invoke<VerifiedHostResult>('add_known_host_verified', {
+    hostname,
+    port,
+    expectedFingerprint,
+  })
+}
+
+export interface HostScanProgress {
+  host: string
+  keys: ScannedKey[] // Empty when the host failed or has no SSH server
+  error?: string | null
+  completed: number // Hosts finished so far, this one included
+  total: number
+}
+
+/**
+ * Listen for each host of a `scanHostRange` call finishing
+ * @returns Function that stops listening
+ */
+export async function onHostScanResult(
+  handler: (progress: HostScanProgress) => void
+): Promise<UnlistenFn> {
+  return listen<HostScanProgress>('host-scan-result', (event) =>
+    handler(event.payload)
+  )
+}
+
+/**
+ * Scan a small IPv4 CIDR ("10.0.0.0/28") or comma-separated host list for host keys
+ * Nothing is written to known_hosts; unreachable hosts come back with no keys
+ * Each host's result is also sent to `onHostScanResult` listeners as it finishes
+ * Uses Rust backend
+ */
+export async function scanHostRange(
+  hosts: string,
+  port?: number
+): Promise<[string, ScannedKey[]][]> {
+  return invoke<[string, ScannedKey[]][]>('scan_host_range', { hosts, port })
+}
+
+/**
+ * Addresses a hostname resolves to
+ */
+export interface ResolvedHost {
+  hostname: string
+  addresses: string[] // "203.0.113.5:22", "[2001:db8::1]:22"
+  hasIpv6: boolean
+}
+
+/**
+ * Check that a hostname resolves (DNS) before scanning or connecting
+ * Uses Rust backend
+ */
+export async function resolveHostname(
+  hostname: string,
+  port?: number
+): Promise<ResolvedHost> {
+  return invoke<ResolvedHost>('resolve_hostname', { hostname, port })
+}
+
 // ============================================================
 // SSH Agent Integration
 // ============================================================
@@ -441,6 +1196,15 @@ export async function isKeyInAgent(keyPath: string): Promise<boolean> {
   }
 }
 
+/**
+ * Which agents hold a key: the default agent plus each socket given
+ * Uses Rust backend; unreachable sockets are skipped
+ * @returns Socket paths of the agents holding the key
+ */
+export async function isKeyInAnyAgent(keyPath: string, sockets: string[]): Promise<string[]> {
+  return invoke<string[]>('is_key_in_any_agent', { keyPath, sockets })
+}
+
 /**
  * Result of adding a key to the agent
  */
@@ -503,6 +1267,27 @@ export async function removeKeyFromAgent(keyPath: string): Promise<{
   }
 }
 
+/**
+ * Set how long keys added to the agent from now on stay loaded
+ * Uses Rust backend (ssh-add -t)
+ * @param lifetimeSecs - Seconds before the agent drops a key; null or 0 for unlimited
+ */
+export async function setDefaultKeyLifetime(
+  lifetimeSecs: number | null
+): Promise<void> {
+  console.log('[ssh-service] Setting default key lifetime:', lifetimeSecs)
+  await invoke('set_default_key_lifetime', { lifetimeSecs })
+}
+
+/**
+ * Save the public keys of all agent identities as .pub files in destDir
+ * Files are named after the key comment (or fingerprint) and never overwritten
+ * Returns the written paths
+ */
+export async function exportAgentPublicKeys(destDir: string): Promise<string[]> {
+  return invoke<string[]>('export_agent_public_keys', { destDir })
+}
+
 /**
  * Check if SSH agent is running and accessible
  * Uses Rust backend for direct Unix socket detection
@@ -518,3 +1303,115 @@ export async function isAgentRunning(): Promise<boolean> {
     return false
   }
 }
+
+export interface AgentPing {
+  latencyMs: number
+  identityCount: number
+}
+
+/**
+ * Time a round trip to the SSH agent and count its identities
+ * Rejects with a timeout error if the agent doesn't answer
+ * Uses Rust backend
+ */
+export async function agentPing(): Promise<AgentPing> {
+  return invoke<AgentPing>('agent_ping')
+}
+
+export interface AgentSignTest {
+  fingerprint: string
+  success: boolean // The agent signed and the signature verified
+  message: string
+}
+
+/**
+ * Have the agent sign a random challenge with a key, to check it can really sign
+ * Uses Rust backend
+ */
+export async function testAgentKeySigning(
+  fingerprint: string
+): Promise<AgentSignTest> {
+  return invoke<AgentSignTest>('test_agent_key_signing', { fingerprint })
+}
+
+// ============================================================
+// Cleanup
+// ============================================================
+
+/**
+ * Finding from the ~/.ssh cleanup review
+ */
+export interface CleanupItem {
+  category:
+    | 'loose-permissions'
+    | 'orphan-public-key'
+    | 'missing-public-key'
+    | 'expired-certificate'
+    | 'duplicate-key'
+    | 'weak-key'
+  path: string
+  message: string
+  fix?: 'delete' | 'chmod' | 'restore' // Absent when the user has to decide
+}
+
+/**
+ * Review ~/.ssh for leftovers and risky keys; nothing is changed
+ * Uses Rust backend
+ */
+export async function getCleanupRecommendations(): Promise<CleanupItem[]> {
+  return invoke<CleanupItem[]>('cleanup_recommendations')
+}
+
+/**
+ * Counts and sizes of ~/.ssh for the overview card
+ * Each count is null when that part of the directory could not be read
+ */
+export interface SshDirInventory {
+  keyPairCount: number | null
+  encryptedKeyCount: number | null
+  unencryptedKeyCount: number | null
+  knownHostsCount: number | null
+  configHostCount: number | null // Not counting `Host *`
+  totalBytes: number | null
+  hasBackup: boolean // A config.<time>.bak from a config write exists
+}
+
+/**
+ * Get the ~/.ssh inventory in one call
+ * Uses Rust backend
+ */
+export async function getSshDirInventory(): Promise<SshDirInventory> {
+  return invoke<SshDirInventory>('ssh_dir_inventory')
+}
+
+// ============================================================
+// System tools
+// ============================================================
+
+/**
+ * External command line tool found on PATH
+ */
+export interface ToolInfo {
+  available: boolean
+  path?: string
+  version?: string // Only for tools with a version flag (ssh, puttygen)
+}
+
+/**
+ * OpenSSH/PuTTY tooling installed on this machine
+ */
+export interface SshCapabilities {
+  ssh: ToolInfo
+  sshAdd: ToolInfo
+  sshKeygen: ToolInfo
+  sshKeyscan: ToolInfo
+  puttygen: ToolInfo
+}
+
+/**
+ * Probe the installed SSH tools; cached by the backend for the app's lifetime
+ * Uses Rust backend
+ */
+export async function getSystemSshCapabilities(): Promise<SshCapabilities> {
+  return invoke<SshCapabilities>('system_ssh_capabilities')
+}